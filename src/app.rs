@@ -1,7 +1,35 @@
-use crate::cli::{Cli, Cmd, DaemonCmd};
+use crate::cli::{CacheCmd, Cli, Cmd, DaemonCmd, FrequencyCmd};
 use crate::commands;
+use crate::config::{Config, config_path_from_args};
+use crate::desktop::WriteOpts;
+use crate::search::{FuzzyOptions, Scoring};
+use clap::{CommandFactory, FromArgMatches};
+use std::ffi::OsString;
 
-pub fn run(cli: Cli) -> i32 {
+pub fn run(args: Vec<OsString>) -> i32 {
+    // Config values become clap defaults, so they must be loaded before parsing.
+    let explicit = config_path_from_args(&args);
+    let required = explicit.is_some();
+    let config_path = explicit.unwrap_or_else(crate::xdg::config_file);
+    let config = match Config::load(&config_path, required) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("desktop-indexer: {e}");
+            return 2;
+        }
+    };
+
+    let matches = config.apply_defaults(Cli::command()).get_matches_from(args);
+    let mut cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
+        Err(e) => e.exit(),
+    };
+    cli.config = config;
+
+    dispatch(cli)
+}
+
+fn dispatch(cli: Cli) -> i32 {
     // Resolve scan roots from XDG + -p paths
     let scan_roots = crate::xdg::build_scan_roots(&cli.paths);
 
@@ -9,26 +37,243 @@ pub fn run(cli: Cli) -> i32 {
         Cmd::Daemon { cmd } => match cmd {
             DaemonCmd::Start => commands::daemon::start_daemon(&cli, &scan_roots),
             DaemonCmd::Stop => commands::daemon::stop_daemon(&cli),
-            DaemonCmd::Restart => commands::daemon::restart_daemon(&cli, &scan_roots),
-            DaemonCmd::Status { json } => commands::status::status(&cli, *json),
+            DaemonCmd::Restart { no_warmup } => {
+                commands::daemon::restart_daemon(&cli, &scan_roots, !no_warmup)
+            }
+            DaemonCmd::Reload => commands::daemon::reload_daemon(&cli, &scan_roots),
+            DaemonCmd::Batch => commands::daemon::batch(&cli),
+            DaemonCmd::Status { verbose, json } => commands::status::status(&cli, *verbose, *json),
+            DaemonCmd::GenerateSystemd { activate_socket } => {
+                commands::daemon::generate_systemd(&cli, *activate_socket)
+            }
+        },
+        Cmd::Cache { cmd } => match cmd {
+            CacheCmd::Clear { json } => commands::cache::clear(*json),
+            CacheCmd::Rebuild { json } => commands::cache::rebuild(&cli, &scan_roots, *json),
+            CacheCmd::Verify { fix, json } => commands::cache::verify(*fix, *json),
+            CacheCmd::Inspect { path, json } => commands::cache::inspect(path, *json),
+        },
+        Cmd::Frequency { cmd } => match cmd {
+            FrequencyCmd::Stats { top, sort, json } => {
+                commands::frequency::stats(*top, *sort, *json)
+            }
+            FrequencyCmd::Increment { id } => commands::frequency::increment(&cli, id),
+            FrequencyCmd::Set {
+                id,
+                freq,
+                last_used,
+            } => commands::frequency::set(&cli, id, *freq, *last_used),
+            FrequencyCmd::Export { output } => commands::frequency::export(output.as_deref()),
+            FrequencyCmd::Import { file } => commands::frequency::import(&cli, file),
+            FrequencyCmd::Reset { id, yes } => {
+                commands::frequency::reset(&cli, id.as_deref(), *yes)
+            }
         },
         Cmd::StartDaemon => commands::daemon::start_daemon(&cli, &scan_roots),
         Cmd::StopDaemon => commands::daemon::stop_daemon(&cli),
-        Cmd::RunDaemon => commands::daemon::run_daemon(),
-        Cmd::Status { json } => commands::status::status(&cli, *json),
-        Cmd::Scan { limit, parse, json } => {
-            commands::scan::scan(&scan_roots, *limit, *parse, *json, cli.respect_try_exec)
+        Cmd::RunDaemon { systemd } => commands::daemon::run_daemon(&cli, &scan_roots, *systemd),
+        Cmd::Completions { shell } => {
+            let mut cmd = Cli::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(*shell, &mut cmd, name, &mut std::io::stdout());
+            0
+        }
+        Cmd::Status { verbose, json } => commands::status::status(&cli, *verbose, *json),
+        Cmd::Ps { json } => commands::ps::ps(&cli, *json),
+        Cmd::Doctor { json } => commands::doctor::doctor(&scan_roots, *json),
+        Cmd::Env { json } => commands::env::env(&scan_roots, *json),
+        Cmd::Benchmark {
+            query,
+            iterations,
+            json,
+        } => commands::benchmark::benchmark(&cli, &scan_roots, query, *iterations, *json),
+        Cmd::Scan {
+            limit,
+            max_depth,
+            parse,
+            stats,
+            verbose,
+            output_fields,
+            csv,
+            output_format,
+            json,
+            watch,
+        } => {
+            let args = commands::scan::ScanArgs {
+                limit: *limit,
+                max_depth: *max_depth,
+                parse: *parse,
+                stats: *stats,
+                verbose: *verbose,
+                output_fields,
+                csv: *csv,
+                json: *json,
+                output_format: *output_format,
+                watch: *watch,
+            };
+            commands::scan::scan(&cli, &scan_roots, &args)
         }
         Cmd::Search {
             query,
             limit,
             empty_mode,
+            fuzzy,
+            fuzzy_penalty,
+            fuzzy_distance,
+            min_score,
+            weights,
+            category,
+            source,
+            exact_name,
+            type_filter,
+            since,
+            since_used,
+            dedup_by_name,
+            explain,
+            group_by_category,
+            output_fields,
+            nul,
+            csv,
+            output_format,
+            json,
+        } => {
+            let fuzzy = match fuzzy_distance {
+                Some(0) => None,
+                Some(d) => Some(FuzzyOptions {
+                    max_distance: (*d).into(),
+                    penalty: *fuzzy_penalty,
+                }),
+                None => fuzzy.then_some(FuzzyOptions {
+                    penalty: *fuzzy_penalty,
+                    ..FuzzyOptions::default()
+                }),
+            };
+            let args = commands::search::SearchArgs {
+                query,
+                limit: *limit,
+                empty_mode: *empty_mode,
+                scoring: Scoring {
+                    fuzzy,
+                    weights: weights.apply(cli.config.weights),
+                    pins: &cli.config.pinned,
+                    min_score: *min_score,
+                },
+                category: category.as_deref(),
+                source: source.source(),
+                exact_name: exact_name.as_deref(),
+                type_filter: type_filter.as_deref(),
+                modified_since: *since,
+                used_since: *since_used,
+                dedup_by_name: *dedup_by_name,
+                explain: *explain,
+                group_by_category: *group_by_category,
+                output_fields,
+                nul: *nul,
+                csv: *csv,
+                json: *json,
+                output_format: output_format.unwrap_or_default(),
+            };
+            commands::search::search(&cli, &scan_roots, &args)
+        }
+        Cmd::List {
+            sort,
+            sort_by_score,
+            source,
+            filter_category,
+            filter_mime,
+            dedup_by_name,
+            output_fields,
+            nul,
+            csv,
+            output_format,
+            with_actions,
+            json,
+        } => {
+            let format = commands::list::ListFormat {
+                output_fields,
+                nul: *nul,
+                csv: *csv,
+                json: *json,
+                output_format: output_format.unwrap_or_default(),
+                with_actions: *with_actions,
+            };
+            let filter = commands::list::ListFilter {
+                source: source.source(),
+                category: filter_category.as_deref(),
+                mime_type: filter_mime.as_deref(),
+            };
+            commands::list::list(
+                &cli,
+                &scan_roots,
+                sort,
+                sort_by_score.as_deref(),
+                &filter,
+                *dedup_by_name,
+                &format,
+            )
+        }
+        Cmd::Get { id, json } => commands::get::get(&cli, &scan_roots, id, *json),
+        Cmd::Actions { id, json } => commands::actions::actions(&cli, &scan_roots, id, *json),
+        Cmd::ByMime { mime_type, json } => {
+            commands::by_mime::by_mime(&cli, &scan_roots, mime_type, *json)
+        }
+        Cmd::Parse {
+            path,
+            all_locales,
+            json,
+        } => {
+            if *all_locales {
+                commands::parse::parse_all_locales(path, *json)
+            } else {
+                commands::parse::parse(
+                    &scan_roots,
+                    path,
+                    cli.locale.as_deref(),
+                    cli.no_extra,
+                    *json,
+                )
+            }
+        }
+        Cmd::Validate { path, strict, json } => commands::validate::validate(path, *strict, *json),
+        Cmd::Write {
+            name,
+            exec,
+            categories,
+            icon,
+            terminal,
+            output,
+        } => {
+            let opts = WriteOpts {
+                name: name.clone(),
+                exec: exec.clone(),
+                categories: categories.clone(),
+                icon: icon.clone(),
+                terminal: *terminal,
+            };
+            commands::write::write(&opts, output.as_deref())
+        }
+        Cmd::Open { file } => commands::open::open(&cli, &scan_roots, file),
+        Cmd::Launch {
+            desktop_id,
+            action,
+            files,
+            urls,
+            cwd,
+            env,
+            dry_run,
             json,
-        } => commands::search::search(&cli, &scan_roots, query, *limit, *empty_mode, *json),
-        Cmd::List { json } => commands::list::list(&cli, &scan_roots, *json),
-        Cmd::Parse { path, json } => commands::parse::parse(&scan_roots, path, *json),
-        Cmd::Launch { desktop_id, action } => {
-            commands::launch::launch(&cli, &scan_roots, desktop_id, action.as_deref())
+        } => {
+            let args = commands::launch::LaunchArgs {
+                desktop_id,
+                action: action.as_deref(),
+                files,
+                urls,
+                cwd: cwd.as_deref(),
+                env,
+                dry_run: *dry_run,
+                json: *json,
+            };
+            commands::launch::launch(&cli, &scan_roots, &args)
         }
     }
 }
@@ -1,29 +1,37 @@
 use crate::models::DesktopEntryIndexed;
 use crate::xdg::cache_dir;
 use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
 use std::{
     collections::{HashMap, hash_map::DefaultHasher},
     fs,
     hash::{Hash, Hasher},
+    io,
     path::{Path, PathBuf},
-    time::{SystemTime, UNIX_EPOCH},
+    time::UNIX_EPOCH,
 };
 
-const CACHE_VERSION: u32 = 3;
+const CACHE_VERSION: u32 = 17;
+
+/// Last version of the JSON cache, before the binary format.
+const LEGACY_JSON_VERSION: u32 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedEntry {
     pub path: String,
     pub size: u64,
     pub mtime_sec: u64,
+    /// Sub-second part of the modification time.
+    pub mtime_nsec: u32,
     pub entry: DesktopEntryIndexed,
 }
 
+/// On-disk layout of one cache file.
 #[derive(Debug, Serialize, Deserialize)]
-struct CacheFile {
-    version: u32,
-    roots: Vec<String>,
-    entries: Vec<CachedEntry>,
+pub struct CacheFile {
+    pub version: u32,
+    pub roots: Vec<String>,
+    pub entries: Vec<CachedEntry>,
 }
 
 pub struct CacheIndex {
@@ -41,10 +49,21 @@ impl CacheIndex {
 }
 
 pub fn load(scan_roots: &[String]) -> CacheIndex {
+    load_from(&cache_dir(), scan_roots)
+}
+
+fn load_from(dir: &Path, scan_roots: &[String]) -> CacheIndex {
     // Preferred: binary cache (fast to parse).
-    let bin_path = cache_bin_path(scan_roots, CACHE_VERSION);
-    if let Ok(data) = fs::read(&bin_path)
-        && let Ok(cache) = postcard::from_bytes::<CacheFile>(&data)
+    let bin_path = cache_bin_path(dir, scan_roots, CACHE_VERSION);
+    let (data, migrated) = match fs::read(&bin_path) {
+        Ok(data) => (data, false),
+        Err(_) => match migration_from_default_hasher(dir, scan_roots) {
+            Some(data) => (data, true),
+            None => return CacheIndex::empty(),
+        },
+    };
+
+    if let Ok(cache) = postcard::from_bytes::<CacheFile>(&data)
         && cache.version == CACHE_VERSION
         && cache.roots == scan_roots
     {
@@ -54,76 +73,460 @@ pub fn load(scan_roots: &[String]) -> CacheIndex {
         }
         return CacheIndex {
             by_path,
-            needs_save: false,
+            // Written back under the new name.
+            needs_save: migrated,
         };
     }
 
     CacheIndex::empty()
 }
 
-pub fn save(scan_roots: &[String], entries: Vec<CachedEntry>) {
-    let dir = cache_dir();
-    if fs::create_dir_all(&dir).is_err() {
-        return;
-    }
+/// Contents of the cache file named with the std `DefaultHasher`, which
+/// earlier versions used. `save` removes it once the new file is in place.
+fn migration_from_default_hasher(dir: &Path, scan_roots: &[String]) -> Option<Vec<u8>> {
+    fs::read(default_hasher_cache_path(
+        dir,
+        scan_roots,
+        CACHE_VERSION,
+        "bin",
+    ))
+    .ok()
+}
+
+pub fn save(scan_roots: &[String], entries: Vec<CachedEntry>) -> io::Result<()> {
+    save_in(&cache_dir(), scan_roots, entries)
+}
+
+fn save_in(dir: &Path, scan_roots: &[String], entries: Vec<CachedEntry>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
 
-    let path = cache_bin_path(scan_roots, CACHE_VERSION);
+    let path = cache_bin_path(dir, scan_roots, CACHE_VERSION);
     let cache = CacheFile {
         version: CACHE_VERSION,
         roots: scan_roots.to_vec(),
         entries,
     };
 
-    let Ok(data) = postcard::to_stdvec(&cache) else {
-        return;
-    };
+    let data = postcard::to_stdvec(&cache).map_err(io::Error::other)?;
 
-    // Best-effort write: atomic-ish via temp file + rename.
+    // Atomic write: the temp file sits next to `path`, on the same filesystem.
     let tmp = path.with_extension("bin.tmp");
-    if fs::write(&tmp, data).is_ok() {
-        let _ = fs::rename(tmp, path);
+    fs::write(&tmp, data)?;
+    replace_file(&tmp, &path)?;
+
+    // Only now is the file under the old `DefaultHasher` name redundant.
+    let _ = fs::remove_file(default_hasher_cache_path(
+        dir,
+        scan_roots,
+        CACHE_VERSION,
+        "bin",
+    ));
+    Ok(())
+}
+
+/// Move `tmp` over `path` (same filesystem, so `rename` is atomic); `tmp` is
+/// removed if that fails.
+fn replace_file(tmp: &Path, path: &Path) -> io::Result<()> {
+    let result = fs::rename(tmp, path);
+    if result.is_err() {
+        let _ = fs::remove_file(tmp);
     }
+    result
+}
+
+/// Decode a cache file as is, whatever its version or roots (`cache inspect`).
+/// Files from a version with another layout usually fail to decode.
+pub fn inspect(path: &Path) -> Result<CacheFile, String> {
+    let data = fs::read(path).map_err(|e| format!("unreadable: {e}"))?;
+    postcard::from_bytes::<CacheFile>(&data).map_err(|e| format!("cannot decode: {e}"))
+}
+
+/// Whether `version` is the one this build reads and writes.
+pub fn is_current_version(version: u32) -> bool {
+    version == CACHE_VERSION
+}
+
+/// Outcome of `verify` for one cache file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheVerifyResult {
+    Ok,
+    /// Unreadable, not valid postcard, or written by another cache version.
+    Corrupt(String),
+    /// Loads fine, but these indexed `.desktop` files no longer exist.
+    Missing(Vec<String>),
 }
 
-pub fn meta_for(path: &Path) -> Option<(u64, u64)> {
+/// Check that a cache file can be read and decoded, has the current version,
+/// and that every indexed `.desktop` file still exists.
+pub fn verify(path: &Path) -> CacheVerifyResult {
+    let cache = match inspect(path) {
+        Ok(cache) => cache,
+        Err(reason) => return CacheVerifyResult::Corrupt(reason),
+    };
+    if cache.version != CACHE_VERSION {
+        return CacheVerifyResult::Corrupt(format!(
+            "version {} (expected {CACHE_VERSION})",
+            cache.version
+        ));
+    }
+
+    let missing: Vec<String> = cache
+        .entries
+        .into_iter()
+        .map(|ce| ce.path)
+        .filter(|p| !Path::new(p).exists())
+        .collect();
+    if missing.is_empty() {
+        CacheVerifyResult::Ok
+    } else {
+        CacheVerifyResult::Missing(missing)
+    }
+}
+
+/// What `is_fresh` compares. Whole seconds alone would miss a same-size edit
+/// made within the second the file was cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMeta {
+    pub size: u64,
+    pub mtime_sec: u64,
+    pub mtime_nsec: u32,
+}
+
+pub fn meta_for(path: &Path) -> Option<FileMeta> {
     let meta = fs::metadata(path).ok()?;
-    let size = meta.len();
-    let mtime = meta.modified().ok()?;
-    let mtime_sec = system_time_to_secs(mtime)?;
-    Some((size, mtime_sec))
-}
-
-pub fn cached_entry(
-    path: &Path,
-    entry: DesktopEntryIndexed,
-    size: u64,
-    mtime_sec: u64,
-) -> CachedEntry {
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(FileMeta {
+        size: meta.len(),
+        mtime_sec: mtime.as_secs(),
+        mtime_nsec: mtime.subsec_nanos(),
+    })
+}
+
+pub fn cached_entry(path: &Path, mut entry: DesktopEntryIndexed, meta: FileMeta) -> CachedEntry {
+    entry.out.mtime_sec = Some(meta.mtime_sec);
     CachedEntry {
         path: path.to_string_lossy().to_string(),
-        size,
-        mtime_sec,
+        size: meta.size,
+        mtime_sec: meta.mtime_sec,
+        mtime_nsec: meta.mtime_nsec,
         entry,
     }
 }
 
-pub fn is_fresh(cached: &CachedEntry, size: u64, mtime_sec: u64) -> bool {
-    cached.size == size && cached.mtime_sec == mtime_sec
+pub fn is_fresh(cached: &CachedEntry, meta: FileMeta) -> bool {
+    cached.size == meta.size
+        && cached.mtime_sec == meta.mtime_sec
+        && cached.mtime_nsec == meta.mtime_nsec
 }
 
 pub fn cache_file_path(scan_roots: &[String]) -> PathBuf {
-    cache_bin_path(scan_roots, CACHE_VERSION)
+    cache_bin_path(&cache_dir(), scan_roots, CACHE_VERSION)
+}
+
+/// All cache files (`*.bin`) directly under `dir`, sorted for stable output.
+pub fn cache_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = rd
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "bin"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Delete the pre-binary JSON cache (`index-<hash>.v2.json`) for these roots.
+/// The file is never read, so a malformed one goes the same way.
+pub fn migrate_legacy(scan_roots: &[String]) {
+    migrate_legacy_in(&cache_dir(), scan_roots);
+}
+
+fn migrate_legacy_in(dir: &Path, scan_roots: &[String]) {
+    let path = default_hasher_cache_path(dir, scan_roots, LEGACY_JSON_VERSION, "json");
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!(
+            "desktop-indexer: failed to remove legacy cache {}: {e}",
+            path.display()
+        ),
+    }
 }
 
-fn cache_bin_path(scan_roots: &[String], version: u32) -> PathBuf {
+fn cache_bin_path(dir: &Path, scan_roots: &[String], version: u32) -> PathBuf {
+    let h = roots_hash(scan_roots);
+    dir.join(format!("index-{h:x}.v{version}.bin"))
+}
+
+/// SipHash-1-3 with fixed keys over an explicit encoding of the roots (count,
+/// then each root's length and bytes, little-endian), so cache names don't
+/// change with the Rust version the way `DefaultHasher` and `Hash` may.
+fn roots_hash(scan_roots: &[String]) -> u64 {
+    let mut hasher = SipHasher13::new_with_keys(0, 0);
+    hasher.write(&(scan_roots.len() as u64).to_le_bytes());
+    for root in scan_roots {
+        hasher.write(&(root.len() as u64).to_le_bytes());
+        hasher.write(root.as_bytes());
+    }
+    hasher.finish()
+}
+
+/// File name scheme of earlier versions, kept to find their caches.
+fn default_hasher_cache_path(
+    dir: &Path,
+    scan_roots: &[String],
+    version: u32,
+    ext: &str,
+) -> PathBuf {
     let mut hasher = DefaultHasher::new();
     scan_roots.hash(&mut hasher);
     let h = hasher.finish();
 
-    cache_dir().join(format!("index-{h:x}.v{version}.bin"))
+    dir.join(format!("index-{h:x}.v{version}.{ext}"))
 }
 
-fn system_time_to_secs(t: SystemTime) -> Option<u64> {
-    let d = t.duration_since(UNIX_EPOCH).ok()?;
-    Some(d.as_secs())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `<id>.desktop` under `apps` and parse it into a cache entry.
+    fn cached(apps: &Path, id: &str) -> CachedEntry {
+        let path = apps.join(format!("{id}.desktop"));
+        fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\nName={id}\nExec={id}\n"),
+        )
+        .unwrap();
+        let entry =
+            crate::desktop::parse_desktop_file_using_roots(&path, &[apps.to_path_buf()], Some("C"))
+                .unwrap();
+        cached_entry(&path, entry, meta_for(&path).unwrap())
+    }
+
+    /// A saved cache file for `apps` holding `ids`, under `dir`.
+    fn saved_cache(dir: &Path, apps: &Path, ids: &[&str]) -> PathBuf {
+        let roots = [apps.display().to_string()];
+        let entries = ids.iter().map(|id| cached(apps, id)).collect();
+        save_in(dir, &roots, entries).unwrap();
+        cache_bin_path(dir, &roots, CACHE_VERSION)
+    }
+
+    #[test]
+    fn entry_path_and_source_root_round_trip_through_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = tempfile::tempdir().unwrap();
+        saved_cache(dir.path(), apps.path(), &["firefox"]);
+
+        let index = load_from(dir.path(), &[apps.path().display().to_string()]);
+        let path = apps.path().join("firefox.desktop").display().to_string();
+        let entry = &index.by_path[&path].entry;
+        assert_eq!(entry.out.path, path);
+        assert_eq!(entry.out.source_root, apps.path().display().to_string());
+        assert!(!index.needs_save);
+        // The path is not searchable.
+        let dir_name = apps.path().file_name().unwrap().to_string_lossy();
+        assert!(!entry.norm.contains(&dir_name.to_lowercase()));
+    }
+
+    #[test]
+    fn inspect_reads_back_a_saved_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path(), apps.path(), &["firefox", "vlc"]);
+
+        let cache = inspect(&path).unwrap();
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert_eq!(cache.roots, [apps.path().display().to_string()]);
+        let ids: Vec<&str> = cache
+            .entries
+            .iter()
+            .map(|ce| ce.entry.out.id.as_str())
+            .collect();
+        assert_eq!(ids, ["firefox", "vlc"]);
+        let firefox = &cache.entries[0];
+        assert_eq!(
+            firefox.path,
+            apps.path().join("firefox.desktop").display().to_string()
+        );
+        let meta = meta_for(Path::new(&firefox.path)).unwrap();
+        assert!(is_fresh(firefox, meta));
+        assert_eq!(
+            (firefox.size, firefox.mtime_sec),
+            (meta.size, meta.mtime_sec)
+        );
+
+        assert!(inspect(&dir.path().join("absent.bin")).is_err());
+    }
+
+    #[test]
+    fn verify_accepts_a_fresh_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path(), apps.path(), &["firefox", "vlc"]);
+
+        assert_eq!(verify(&path), CacheVerifyResult::Ok);
+    }
+
+    #[test]
+    fn verify_reports_indexed_files_that_are_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path(), apps.path(), &["firefox", "vlc"]);
+        let removed = apps.path().join("vlc.desktop");
+        fs::remove_file(&removed).unwrap();
+
+        assert_eq!(
+            verify(&path),
+            CacheVerifyResult::Missing(vec![removed.display().to_string()])
+        );
+    }
+
+    #[test]
+    fn verify_reports_truncated_and_unreadable_files_as_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path(), apps.path(), &["firefox"]);
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+        assert!(matches!(
+            verify(&path),
+            CacheVerifyResult::Corrupt(reason) if reason.starts_with("cannot decode")
+        ));
+        assert!(matches!(
+            verify(&dir.path().join("absent.bin")),
+            CacheVerifyResult::Corrupt(reason) if reason.starts_with("unreadable")
+        ));
+    }
+
+    #[test]
+    fn verify_reports_another_version_as_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index-0.v1.bin");
+        let cache = CacheFile {
+            version: CACHE_VERSION + 1,
+            roots: Vec::new(),
+            entries: Vec::new(),
+        };
+        fs::write(&path, postcard::to_stdvec(&cache).unwrap()).unwrap();
+
+        assert_eq!(
+            verify(&path),
+            CacheVerifyResult::Corrupt(format!(
+                "version {} (expected {CACHE_VERSION})",
+                CACHE_VERSION + 1
+            ))
+        );
+    }
+
+    /// Cache file names (`index-<hash>`) must not change between builds or
+    /// Rust versions: pin the hash of a few root lists.
+    #[test]
+    fn roots_hash_is_stable() {
+        assert_eq!(roots_hash(&[]), 0xbd60acb658c79e45);
+        assert_eq!(
+            roots_hash(&[
+                "/home/user/.local/share/applications".to_string(),
+                "/usr/share/applications".to_string(),
+            ]),
+            0x2dc345b5449751db
+        );
+        assert_eq!(
+            roots_hash(&["locale=fr_FR:fr".to_string()]),
+            0x81a9f5f79de3eaf
+        );
+    }
+
+    #[test]
+    fn roots_hash_depends_on_root_boundaries() {
+        let joined = roots_hash(&["/usr/share".to_string()]);
+        let split = roots_hash(&["/usr".to_string(), "/share".to_string()]);
+        assert_ne!(joined, split);
+    }
+
+    #[test]
+    fn replace_file_moves_tmp_over_path() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let dest_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().join("index.bin.tmp");
+        let path = dest_dir.path().join("index.bin");
+        fs::write(&path, b"old").unwrap();
+        fs::write(&tmp, b"new").unwrap();
+
+        replace_file(&tmp, &path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!tmp.exists());
+    }
+
+    #[test]
+    fn replace_file_removes_tmp_on_failure() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let dest_dir = tempfile::tempdir().unwrap();
+        let tmp = tmp_dir.path().join("index.bin.tmp");
+        let path = dest_dir.path().join("missing-dir/index.bin");
+        fs::write(&tmp, b"new").unwrap();
+
+        assert!(replace_file(&tmp, &path).is_err());
+        assert!(!tmp.exists());
+    }
+
+    #[test]
+    fn migrate_legacy_removes_the_json_cache_even_when_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = ["/usr/share/applications".to_string()];
+        let other_roots = ["/opt/apps".to_string()];
+        let legacy = default_hasher_cache_path(dir.path(), &roots, LEGACY_JSON_VERSION, "json");
+        let malformed =
+            default_hasher_cache_path(dir.path(), &other_roots, LEGACY_JSON_VERSION, "json");
+        let unrelated = dir.path().join("index-0.v2.json");
+        fs::write(&legacy, br#"{"version":2,"roots":[],"entries":[]}"#).unwrap();
+        fs::write(&malformed, b"{not json").unwrap();
+        fs::write(&unrelated, b"{}").unwrap();
+
+        migrate_legacy_in(dir.path(), &roots);
+        migrate_legacy_in(dir.path(), &other_roots);
+        // Nothing left to remove: no error.
+        migrate_legacy_in(dir.path(), &roots);
+
+        assert!(!legacy.exists());
+        assert!(!malformed.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[test]
+    fn save_fails_in_a_read_only_cache_home() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let home = tempfile::tempdir().unwrap();
+        fs::set_permissions(home.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        if fs::write(home.path().join("probe"), b"").is_ok() {
+            // Root ignores the mode: put a file where the cache dir would go.
+            fs::write(home.path().join("desktop-indexer"), b"").unwrap();
+        }
+
+        let result = save_in(
+            &home.path().join("desktop-indexer"),
+            &["/usr/share/applications".to_string()],
+            Vec::new(),
+        );
+
+        fs::set_permissions(home.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn same_second_same_size_edit_is_not_fresh() {
+        let apps = tempfile::tempdir().unwrap();
+        let mut ce = cached(apps.path(), "editor");
+        let meta = meta_for(Path::new(&ce.path)).unwrap();
+        assert!(is_fresh(&ce, meta));
+
+        // An edit within the same second that keeps the size.
+        ce.mtime_nsec = ce.mtime_nsec.wrapping_add(1) % 1_000_000_000;
+        assert!(!is_fresh(&ce, meta));
+    }
 }
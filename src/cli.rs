@@ -1,7 +1,61 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
 use std::path::PathBuf;
 
+use crate::commands::list::SortKey;
+use crate::config::Config;
+use crate::daemon_client::DaemonTimeouts;
 use crate::empty_query::EmptyQueryMode;
+use crate::launch::parse_env_assignment;
+use crate::models::ScanOptions;
+use crate::output::{OutputField, OutputFormat};
+use crate::scan_sources::SourceFilter;
+use crate::search::{DEFAULT_FUZZY_PENALTY, MAX_FUZZY_DISTANCE, SearchWeights};
+use crate::time_parse::since_arg;
+
+/// `--weight-*` overrides for `SearchWeights` (on top of `[weights]` in config.toml).
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Ranking weights")]
+pub struct WeightArgs {
+    /// Token at a word boundary of the name [default: 140]
+    #[arg(long)]
+    pub weight_name_boundary: Option<i32>,
+    /// Token elsewhere in the name [default: 80]
+    #[arg(long)]
+    pub weight_name_substring: Option<i32>,
+    /// Token at a -/_/. boundary of the desktop-id [default: 110]
+    #[arg(long)]
+    pub weight_id_boundary: Option<i32>,
+    /// Token elsewhere in the desktop-id [default: 60]
+    #[arg(long)]
+    pub weight_id_substring: Option<i32>,
+    /// Bonus when every token matches the name at a boundary [default: 120]
+    #[arg(long)]
+    pub weight_all_tokens_in_name: Option<i32>,
+    /// Shorter-name bonus cap, in characters [default: 30]
+    #[arg(long)]
+    pub weight_short_name: Option<i32>,
+    /// Points per recorded launch, up to 20 launches [default: 2]
+    #[arg(long)]
+    pub weight_freq: Option<i32>,
+}
+
+impl WeightArgs {
+    /// `base` with every flag that was given applied on top.
+    pub fn apply(&self, base: SearchWeights) -> SearchWeights {
+        SearchWeights {
+            name_boundary: self.weight_name_boundary.unwrap_or(base.name_boundary),
+            name_substring: self.weight_name_substring.unwrap_or(base.name_substring),
+            id_boundary: self.weight_id_boundary.unwrap_or(base.id_boundary),
+            id_substring: self.weight_id_substring.unwrap_or(base.id_substring),
+            all_tokens_in_name: self
+                .weight_all_tokens_in_name
+                .unwrap_or(base.all_tokens_in_name),
+            short_name: self.weight_short_name.unwrap_or(base.short_name),
+            freq: self.weight_freq.unwrap_or(base.freq),
+        }
+    }
+}
 
 #[derive(Subcommand, Debug)]
 pub enum DaemonCmd {
@@ -10,12 +64,119 @@ pub enum DaemonCmd {
     /// Stop IPC daemon
     Stop,
     /// Restart IPC daemon (stop then start)
-    Restart,
+    Restart {
+        /// Don't pre-build the index for the current roots after starting
+        #[arg(long)]
+        no_warmup: bool,
+    },
+    /// Rebuild the daemon's index (picks up newly installed apps)
+    Reload,
+    /// Send JSON requests from stdin (one per line) as a single batch; print one response per line
+    ///
+    /// With --persistent-connection, each line is sent as soon as it is read and
+    /// answered immediately over one kept-alive connection (for interactive launchers).
+    Batch,
     /// Check daemon status
     Status {
+        /// Also show each loaded index (roots, entry count, build time)
+        #[arg(long)]
+        verbose: bool,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a systemd user service for the daemon (Type=notify)
+    GenerateSystemd {
+        /// Print the matching .socket unit (socket activation) instead
+        #[arg(long)]
+        activate_socket: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCmd {
+    /// Delete all on-disk index caches
+    Clear {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete caches, then re-index the current roots
+    Rebuild {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check every cache file for truncation, corruption or vanished entries
+    Verify {
+        /// Delete corrupt cache files
+        #[arg(long)]
+        fix: bool,
+
         #[arg(long)]
         json: bool,
     },
+    /// Print the version, roots and entries stored in one cache file
+    Inspect {
+        path: PathBuf,
+
+        /// Print the whole file, entries included, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FrequencySort {
+    #[value(name = "freq")]
+    Freq,
+    #[value(name = "last-used")]
+    LastUsed,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FrequencyCmd {
+    /// Show recorded launch counts per app
+    Stats {
+        /// Only show the first N entries
+        #[arg(long)]
+        top: Option<usize>,
+
+        #[arg(long, value_enum, default_value_t = FrequencySort::Freq)]
+        sort: FrequencySort,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Record a launch of this app without running it (for external trackers)
+    Increment { id: String },
+    /// Overwrite the launch count and last-use time of one app
+    Set {
+        id: String,
+
+        #[arg(long)]
+        freq: u32,
+
+        /// Unix timestamp in seconds (default: now)
+        #[arg(long)]
+        last_used: Option<u64>,
+    },
+    /// Write launch data as JSON (`[{"id", "freq", "last_used"}, ...]`)
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    /// Merge launch data from a JSON export (higher count and latest use win)
+    Import { file: PathBuf },
+    /// Forget launch data for one app, or for all apps
+    Reset {
+        /// Only reset this desktop-id
+        #[arg(long)]
+        id: Option<String>,
+
+        /// Don't ask for confirmation when resetting everything
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -41,29 +202,276 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub respect_try_exec: bool,
 
+    /// Hide entries whose OnlyShowIn/NotShowIn exclude $XDG_CURRENT_DESKTOP
+    #[arg(long, global = true)]
+    pub de_filter: bool,
+
+    /// Parse .desktop files on all CPU cores (local scans only)
+    #[arg(long, global = true)]
+    pub parallel: bool,
+
+    /// Follow symlinked files and directories under the scan roots; implies local mode unless set in the config
+    #[arg(long, global = true)]
+    pub follow_symlinks: bool,
+
+    /// Resolve translated names for this locale (e.g. fr_FR) instead of LC_ALL/LC_MESSAGES/LANG; implies local mode
+    #[arg(long, global = true)]
+    pub locale: Option<String>,
+
+    /// Hide this desktop-id for this run, on top of `ignore` in the config (repeatable); implies local mode
+    #[arg(long = "ignore", global = true)]
+    pub ignore: Vec<String>,
+
+    /// Daemon socket timeout in milliseconds before falling back to local mode [default: 2000 write, 5000 read]
+    #[arg(long, global = true)]
+    pub daemon_timeout_ms: Option<u64>,
+
+    /// Reuse one daemon connection for all requests (`daemon batch` then answers each stdin line as it arrives)
+    #[arg(long, global = true)]
+    pub persistent_connection: bool,
+
+    /// Include entries with Hidden=true in search, list and by-mime results
+    #[arg(long, global = true)]
+    pub show_hidden: bool,
+
+    /// Include entries with NoDisplay=true in search, list and by-mime results
+    #[arg(long, global = true)]
+    pub show_nodisplay: bool,
+
+    /// Leave non-standard `X-...` keys out of the `extra` map in JSON output
+    #[arg(long, global = true)]
+    pub no_extra: bool,
+
+    /// Neither read nor write the on-disk index cache (e.g. a read-only cache dir); passed on by `daemon start`
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Config file to use instead of $XDG_CONFIG_HOME/desktop-indexer/config.toml
+    #[arg(long = "config", global = true, value_name = "PATH")]
+    pub config_path: Option<PathBuf>,
+
+    /// Settings loaded from the config file (filled in by `app::run`).
+    #[arg(skip)]
+    pub config: Config,
+
     #[command(subcommand)]
     pub cmd: Cmd,
 }
 
+impl Cli {
+    pub fn daemon_timeouts(&self) -> DaemonTimeouts {
+        self.daemon_timeout_ms
+            .map(DaemonTimeouts::from_millis)
+            .unwrap_or_default()
+    }
+
+    /// Whether entry lookups may go through the daemon. Its index is resolved
+    /// for the daemon's own locale and config (`ignore`, `follow_symlinks`), so
+    /// `--locale`, `--ignore` and a `--follow-symlinks` the config doesn't set
+    /// force a local scan.
+    pub fn use_daemon_index(&self) -> bool {
+        !self.no_daemon
+            && self.locale.is_none()
+            && self.ignore.is_empty()
+            && self.follow_symlinks == self.config.follow_symlinks.unwrap_or(false)
+    }
+
+    /// How local scans walk the roots (`--parallel`, `--follow-symlinks`,
+    /// config `max_depth`).
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            parallel: self.parallel,
+            max_depth: self.config.max_depth,
+            follow_symlinks: self.follow_symlinks,
+            respect_try_exec: self.respect_try_exec,
+            de_filter: self.de_filter,
+            locale: self.locale.clone(),
+            ignore: self.ignored_ids(),
+            no_cache: self.no_cache,
+        }
+    }
+
+    /// Desktop ids hidden from every index: config `ignore` plus `--ignore`.
+    pub fn ignored_ids(&self) -> HashSet<String> {
+        self.config
+            .ignore
+            .iter()
+            .chain(&self.ignore)
+            .map(|id| id.trim_end_matches(".desktop").to_string())
+            .collect()
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Cmd {
     /// Search desktop entries
     Search {
+        /// Search text (may be omitted with --exact-name)
+        #[arg(required_unless_present = "exact_name", default_value = "")]
         query: String,
-        /// Max results to return (omit for unlimited)
+        /// Max results to return (default: config `default_limit`, else 20)
         #[arg(long)]
         limit: Option<usize>,
 
-        /// When the query is empty/whitespace, return recent or frequent entries.
+        /// When the query is empty/whitespace, return recent, frequent or frecent entries.
         #[arg(long, value_enum, default_value_t = EmptyQueryMode::Recency)]
         empty_mode: EmptyQueryMode,
 
+        /// Tolerate one typo per token (tokens of 4+ chars) when nothing matches exactly
         #[arg(long)]
+        fuzzy: bool,
+
+        /// Relevance penalty applied per edit of a fuzzy-only token match
+        #[arg(long, default_value_t = DEFAULT_FUZZY_PENALTY)]
+        fuzzy_penalty: i32,
+
+        /// Typos tolerated per token, 0-3 (implies --fuzzy; 0 turns it off) [default: 1]
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=MAX_FUZZY_DISTANCE as i64))]
+        fuzzy_distance: Option<u8>,
+
+        /// Drop matches scoring below this (see --explain for scores)
+        #[arg(long, value_name = "N", allow_negative_numbers = true)]
+        min_score: Option<i32>,
+
+        /// Only return entries in this category (e.g. AudioVideo), case-insensitive
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Only return entries from this packaging source
+        #[arg(long, value_enum, default_value_t = SourceFilter::All)]
+        source: SourceFilter,
+
+        /// Only return entries whose name is exactly this (case-insensitive; never fuzzy)
+        #[arg(long)]
+        exact_name: Option<String>,
+
+        /// Only return entries of this desktop entry Type
+        #[arg(long = "type", value_parser = ["Application", "Link", "Directory"])]
+        type_filter: Option<String>,
+
+        /// Only return entries whose .desktop file changed since then: unix seconds or an age (1h, 1d, 7d)
+        #[arg(long, value_name = "TIME", value_parser = since_arg)]
+        since: Option<u64>,
+
+        /// Only return entries launched since then: unix seconds or an age (24h, 7d)
+        #[arg(long, value_name = "TIME", value_parser = since_arg)]
+        since_used: Option<u64>,
+
+        /// Keep only the highest-priority entry (by scan root order) among entries with the same name
+        #[arg(long)]
+        dedup_by_name: bool,
+
+        /// Show how each result's score is made up (always runs locally)
+        #[arg(long, conflicts_with_all = ["nul", "csv", "group_by_category"])]
+        explain: bool,
+
+        /// Output `{"<Category>": [...]}` instead of a flat array (entries without categories go under "Other")
+        #[arg(long, requires = "json")]
+        group_by_category: bool,
+
+        /// Only include these keys in JSON entries (comma-separated, e.g. id,name,exec,icon)
+        #[arg(long, value_enum, value_delimiter = ',', requires = "json")]
+        output_fields: Vec<OutputField>,
+
+        /// Print only ids, each terminated by NUL (for `xargs -0`)
+        #[arg(long, conflicts_with = "json")]
+        nul: bool,
+
+        /// Output RFC 4180 CSV with a header row
+        #[arg(long, conflicts_with_all = ["json", "nul"])]
+        csv: bool,
+
+        /// Print results as id-only, name-only, id-tab-name (default) or json
+        #[arg(long, value_enum, conflicts_with_all = ["nul", "csv"])]
+        output_format: Option<OutputFormat>,
+
+        /// Output JSON (deprecated: same as --output-format json)
+        #[arg(
+            long,
+            default_value_if("output_format", "json", "true"),
+            conflicts_with = "output_format"
+        )]
         json: bool,
+
+        #[command(flatten)]
+        weights: WeightArgs,
     },
 
     /// List desktop entries
     List {
+        /// Sort key; repeat for tie-breaking (default: name)
+        #[arg(long = "sort", value_enum)]
+        sort: Vec<SortKey>,
+
+        /// Order every entry by its search score for this query, best first (no filtering; --sort breaks ties)
+        #[arg(long, value_name = "QUERY")]
+        sort_by_score: Option<String>,
+
+        /// Only list entries from this packaging source
+        #[arg(long, value_enum, default_value_t = SourceFilter::All)]
+        source: SourceFilter,
+
+        /// Only list entries with this category (case-insensitive)
+        #[arg(long)]
+        filter_category: Option<String>,
+
+        /// Only list entries that handle this MIME type (e.g. text/html)
+        #[arg(long)]
+        filter_mime: Option<String>,
+
+        /// Keep only the highest-priority entry (by scan root order) among entries with the same name
+        #[arg(long)]
+        dedup_by_name: bool,
+
+        /// Only include these keys in JSON entries (comma-separated, e.g. id,name,exec,icon)
+        #[arg(long, value_enum, value_delimiter = ',', requires = "json")]
+        output_fields: Vec<OutputField>,
+
+        /// Print only ids, each terminated by NUL (for `xargs -0`)
+        #[arg(long, conflicts_with = "json")]
+        nul: bool,
+
+        /// Output RFC 4180 CSV with a header row
+        #[arg(long, conflicts_with_all = ["json", "nul"])]
+        csv: bool,
+
+        /// Print results as id-only, name-only, id-tab-name (default) or json
+        #[arg(long, value_enum, conflicts_with_all = ["nul", "csv"])]
+        output_format: Option<OutputFormat>,
+
+        /// Follow each entry with its Desktop Actions (`<id>:<action_id>\t<action name>`; JSON: `{entry, actions}`)
+        #[arg(long, conflicts_with_all = ["nul", "csv", "output_fields"])]
+        with_actions: bool,
+
+        /// Output JSON (deprecated: same as --output-format json)
+        #[arg(
+            long,
+            default_value_if("output_format", "json", "true"),
+            conflicts_with = "output_format"
+        )]
+        json: bool,
+    },
+
+    /// Show the entry with exactly this desktop-id
+    Get {
+        id: String,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List the actions (`[Desktop Action]` groups) of an entry, for `launch --action`
+    Actions {
+        id: String,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List apps that can open a MIME type (e.g. text/html), most used first
+    ByMime {
+        mime_type: String,
+
         #[arg(long)]
         json: bool,
     },
@@ -75,36 +483,148 @@ pub enum Cmd {
         /// Optional Desktop Action id
         #[arg(long)]
         action: Option<String>,
+
+        /// File to open, substituted for %f/%F (repeatable)
+        #[arg(long = "file")]
+        files: Vec<String>,
+
+        /// URL to open, substituted for %u/%U (repeatable)
+        #[arg(long = "url")]
+        urls: Vec<String>,
+
+        /// Run the app in this directory instead of the entry's Path= (if any)
+        #[arg(long, value_name = "PATH")]
+        cwd: Option<PathBuf>,
+
+        /// Set an environment variable for the app (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = env_assignment)]
+        env: Vec<String>,
+
+        /// Print the command that would be run instead of running it (local mode; gtk-launch is not tried)
+        #[arg(long)]
+        dry_run: bool,
+
+        /// With --dry-run, print the argv as a JSON array
+        #[arg(long, requires = "dry_run")]
+        json: bool,
     },
 
+    /// Open a file with the most used app for its MIME type (falls back to xdg-open)
+    Open { file: PathBuf },
+
     /// Scan for .desktop files and print what we found
     Scan {
         /// Max number of file paths to print (omit for unlimited)
         #[arg(long)]
         limit: Option<usize>,
 
+        /// Only descend N directory levels below each root (1 = files directly in the root; omit for unlimited)
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+
         /// Parse each found .desktop file and output extracted fields
         #[arg(long)]
         parse: bool,
 
-        /// Output JSON
-        #[arg(long)]
+        /// With --parse: print category/MIME type counts and flag totals instead of the entries
+        #[arg(long, requires = "parse", conflicts_with_all = ["csv", "output_fields"])]
+        stats: bool,
+
+        /// With --parse: report `OK <id>` or `FAIL <path>: <reason>` per file on stderr
+        #[arg(long, requires = "parse")]
+        verbose: bool,
+
+        /// Only include these keys in parsed JSON entries (comma-separated, e.g. id,name,exec,icon)
+        #[arg(long, value_enum, value_delimiter = ',', requires_all = ["json", "parse"])]
+        output_fields: Vec<OutputField>,
+
+        /// Output parsed entries as RFC 4180 CSV with a header row
+        #[arg(long, requires = "parse", conflicts_with = "json")]
+        csv: bool,
+
+        /// With --parse: print only the entries as id-only, name-only, id-tab-name or json
+        #[arg(long, value_enum, requires = "parse", conflicts_with_all = ["csv", "stats"])]
+        output_format: Option<OutputFormat>,
+
+        /// Output JSON (deprecated: same as --output-format json)
+        #[arg(
+            long,
+            default_value_if("output_format", "json", "true"),
+            conflicts_with = "output_format"
+        )]
         json: bool,
+
+        /// After the scan, keep running and print `ADD`/`CHANGE`/`REMOVE <path>` as .desktop files change (with --parse: plus the entry; with --json: one JSON object per line)
+        #[arg(long, conflicts_with_all = ["stats", "csv"])]
+        watch: bool,
     },
     /// Parse a single .desktop file and print extracted fields
     Parse {
         path: PathBuf,
 
+        /// Show every translation of each [Desktop Entry] key instead of resolving one locale
+        #[arg(long)]
+        all_locales: bool,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check a .desktop file against the Desktop Entry spec and list problems
+    Validate {
+        path: PathBuf,
+
+        /// Exit with status 1 when any ERROR is reported
+        #[arg(long)]
+        strict: bool,
+
         #[arg(long)]
         json: bool,
     },
+    /// Generate a Type=Application .desktop file from flags
+    Write {
+        /// Name= value
+        #[arg(long)]
+        name: String,
+
+        /// Exec= command line (e.g. "myapp %U")
+        #[arg(long)]
+        exec: String,
+
+        /// Categories= entry (repeatable or comma-separated)
+        #[arg(long = "category", value_delimiter = ',')]
+        categories: Vec<String>,
+
+        /// Icon= name or absolute path
+        #[arg(long)]
+        icon: Option<String>,
+
+        /// Set Terminal=true
+        #[arg(long)]
+        terminal: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
 
-    /// Manage IPC daemon (start/stop/restart/status)
+    /// Manage IPC daemon (start/stop/restart/reload/status)
     Daemon {
         #[command(subcommand)]
         cmd: DaemonCmd,
     },
 
+    /// Manage the on-disk index cache (clear/rebuild)
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCmd,
+    },
+
+    /// Inspect or reset launch frequency data
+    Frequency {
+        #[command(subcommand)]
+        cmd: FrequencyCmd,
+    },
+
     /// Start IPC daemon
     StartDaemon,
 
@@ -113,11 +633,97 @@ pub enum Cmd {
 
     /// Check daemon status
     Status {
+        /// Also show each loaded index (roots, entry count, build time)
+        #[arg(long)]
+        verbose: bool,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List apps started by `launch` that are still running (pid, desktop-id, launch time)
+    Ps {
         #[arg(long)]
         json: bool,
     },
 
+    /// Check the environment (scan roots, cache, daemon, terminals)
+    Doctor {
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the environment variables the indexer reads and what they resolve to
+    Env {
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Time local scan+parse and search (results go to stderr)
+    Benchmark {
+        /// Query used for the search phase
+        query: String,
+
+        /// Measured iterations per phase (one extra warm-up run is not counted)
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print shell completions to stdout
+    Completions {
+        #[arg(long, value_enum)]
+        shell: clap_complete::Shell,
+    },
+
     /// Internal: run daemon server
     #[command(hide = true)]
-    RunDaemon,
+    RunDaemon {
+        /// Use the listening socket passed by systemd (LISTEN_FDS) when there is one
+        #[arg(long)]
+        systemd: bool,
+    },
+}
+
+fn env_assignment(s: &str) -> Result<String, String> {
+    parse_env_assignment(s).map(|_| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn bash_completions_cover_subcommands_and_flags() {
+        let mut cmd = Cli::command();
+        let mut buf = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut cmd,
+            "desktop-indexer",
+            &mut buf,
+        );
+        let script = String::from_utf8(buf).unwrap();
+        for word in [
+            "search",
+            "list",
+            "launch",
+            "--limit",
+            "--json",
+            "--empty-mode",
+            "--category",
+            "frecency",
+            "all-apps",
+        ] {
+            assert!(script.contains(word), "missing {word}");
+        }
+    }
 }
@@ -0,0 +1,79 @@
+use crate::cli::Cli;
+use crate::daemon_client::{self, ClientError};
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::ipc::{Request, Response};
+use crate::models::DesktopActionOut;
+use crate::output::print_json;
+
+use super::common::{timing, trace};
+
+pub fn actions(cli: &Cli, scan_roots: &[std::path::PathBuf], id: &str, json: bool) -> i32 {
+    let start = std::time::Instant::now();
+    let id = id.trim_end_matches(".desktop");
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    let daemon_resp = if !cli.use_daemon_index() {
+        None
+    } else {
+        daemon_client::try_request(
+            &Request::GetActions {
+                roots,
+                desktop_id: id.to_string(),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
+    };
+
+    let (mode, actions): (&str, Option<Vec<DesktopActionOut>>) = match daemon_resp {
+        Some(Response::Actions { actions }) => ("daemon", Some(actions)),
+        Some(Response::Error { message }) => {
+            eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
+            ("local", local_actions(cli, scan_roots, id))
+        }
+        _ => ("local", local_actions(cli, scan_roots, id)),
+    };
+
+    trace(cli, &format!("mode={mode} (actions)"));
+    timing(mode, start);
+
+    let Some(mut actions) = actions else {
+        eprintln!("desktop-indexer: no entry with id {id}");
+        return 1;
+    };
+
+    if cli.no_extra {
+        for a in &mut actions {
+            a.extra.clear();
+        }
+    }
+
+    if json {
+        print_json(&actions);
+    } else {
+        for a in &actions {
+            println!("{}\t{}", a.id, a.name.as_deref().unwrap_or(""));
+        }
+    }
+
+    0
+}
+
+fn local_actions(
+    cli: &Cli,
+    scan_roots: &[std::path::PathBuf],
+    id: &str,
+) -> Option<Vec<DesktopActionOut>> {
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    result
+        .entries
+        .into_iter()
+        .find(|e| e.out.id == id)
+        .map(|e| e.out.actions)
+}
@@ -0,0 +1,113 @@
+use crate::cli::Cli;
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::empty_query::EmptyQueryMode;
+use crate::frequency::FrequencyStore;
+use crate::output::print_json;
+use crate::search::{EntryFilter, Scoring, search_entries_with_usage_map_and_empty_mode};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+#[derive(Serialize)]
+struct PhaseStats {
+    min_us: u128,
+    mean_us: u128,
+    max_us: u128,
+    p99_us: u128,
+}
+
+impl PhaseStats {
+    fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort();
+        let n = samples.len().max(1);
+        let total: Duration = samples.iter().sum();
+        // Nearest-rank percentile.
+        let p99 = samples
+            .get((n * 99).div_ceil(100).saturating_sub(1))
+            .copied()
+            .unwrap_or_default();
+
+        Self {
+            min_us: samples.first().copied().unwrap_or_default().as_micros(),
+            mean_us: total.as_micros() / n as u128,
+            max_us: samples.last().copied().unwrap_or_default().as_micros(),
+            p99_us: p99.as_micros(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BenchmarkOut {
+    iterations: usize,
+    entries: usize,
+    query: String,
+    scan: PhaseStats,
+    search: PhaseStats,
+}
+
+/// Always runs locally (like `--no-daemon`). The first run of each phase warms
+/// the page cache / on-disk cache and is left out of the statistics.
+pub fn benchmark(
+    cli: &Cli,
+    scan_roots: &[std::path::PathBuf],
+    query: &str,
+    iterations: usize,
+    json: bool,
+) -> i32 {
+    let iterations = iterations.max(1);
+
+    let mut scan_samples = Vec::with_capacity(iterations);
+    let mut entries = Vec::new();
+    for i in 0..=iterations {
+        let start = Instant::now();
+        let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+        if i > 0 {
+            scan_samples.push(start.elapsed());
+        }
+        entries = result.entries;
+    }
+
+    let freqs = FrequencyStore::load();
+    let filter = EntryFilter::default();
+    let mut search_samples = Vec::with_capacity(iterations);
+    for i in 0..=iterations {
+        let start = Instant::now();
+        let matches = search_entries_with_usage_map_and_empty_mode(
+            &entries,
+            query,
+            20,
+            freqs.map(),
+            EmptyQueryMode::Recency,
+            &Scoring::default(),
+            &filter,
+        );
+        std::hint::black_box(matches);
+        if i > 0 {
+            search_samples.push(start.elapsed());
+        }
+    }
+
+    let out = BenchmarkOut {
+        iterations,
+        entries: entries.len(),
+        query: query.to_string(),
+        scan: PhaseStats::from_samples(&mut scan_samples),
+        search: PhaseStats::from_samples(&mut search_samples),
+    };
+
+    if json {
+        print_json(&out);
+    } else {
+        eprintln!(
+            "benchmark: iterations={} entries={} query={:?}",
+            out.iterations, out.entries, out.query
+        );
+        for (phase, s) in [("scan", &out.scan), ("search", &out.search)] {
+            eprintln!(
+                "{phase:<6} min={}us mean={}us max={}us p99={}us",
+                s.min_us, s.mean_us, s.max_us, s.p99_us
+            );
+        }
+    }
+
+    0
+}
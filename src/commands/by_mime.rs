@@ -0,0 +1,77 @@
+use crate::cli::Cli;
+use crate::daemon_client::{self, ClientError};
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::frequency::FrequencyStore;
+use crate::ipc::{Request, Response};
+use crate::models::DesktopEntryOut;
+use crate::output::{print_json, strip_extra};
+use crate::search::{EntryFilter, entries_for_mime};
+
+use super::common::{timing, trace};
+
+pub fn by_mime(cli: &Cli, scan_roots: &[std::path::PathBuf], mime_type: &str, json: bool) -> i32 {
+    let start = std::time::Instant::now();
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    let daemon_resp = if !cli.use_daemon_index() {
+        None
+    } else {
+        daemon_client::try_request(
+            &Request::ByMime {
+                roots,
+                mime_type: mime_type.to_string(),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+                show_hidden: cli.show_hidden,
+                show_nodisplay: cli.show_nodisplay,
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
+    };
+
+    let (mode, mut entries): (&str, Vec<DesktopEntryOut>) = match daemon_resp {
+        Some(Response::Entries { entries }) => ("daemon", entries),
+        Some(Response::Error { message }) => {
+            eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
+            ("local", local_by_mime(cli, scan_roots, mime_type))
+        }
+        _ => ("local", local_by_mime(cli, scan_roots, mime_type)),
+    };
+
+    if cli.no_extra {
+        strip_extra(&mut entries);
+    }
+
+    trace(cli, &format!("mode={mode} (by-mime)"));
+    timing(mode, start);
+
+    if json {
+        print_json(&entries);
+    } else {
+        for e in &entries {
+            println!("{}\t{}", e.id, e.display_name());
+        }
+    }
+
+    0
+}
+
+fn local_by_mime(
+    cli: &Cli,
+    scan_roots: &[std::path::PathBuf],
+    mime_type: &str,
+) -> Vec<DesktopEntryOut> {
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    let freqs = FrequencyStore::load();
+    let filter = EntryFilter {
+        show_hidden: cli.show_hidden,
+        show_nodisplay: cli.show_nodisplay,
+        ..EntryFilter::default()
+    };
+    entries_for_mime(&result.entries, mime_type, freqs.map(), &filter)
+}
@@ -0,0 +1,179 @@
+use crate::cache::{CacheVerifyResult, cache_files, is_current_version};
+use crate::cli::Cli;
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::output::print_json;
+use crate::xdg::cache_dir;
+use std::fs;
+use std::path::Path;
+
+#[derive(serde::Serialize)]
+struct CacheOut {
+    cleared_files: usize,
+    bytes_freed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indexed: Option<usize>,
+}
+
+pub fn clear(json: bool) -> i32 {
+    let out = clear_cache_dir();
+
+    if json {
+        print_json(&out);
+    } else {
+        println!(
+            "cleared {} cache file(s), freed {} bytes",
+            out.cleared_files, out.bytes_freed
+        );
+    }
+
+    0
+}
+
+pub fn rebuild(cli: &Cli, scan_roots: &[std::path::PathBuf], json: bool) -> i32 {
+    let mut out = clear_cache_dir();
+
+    // An empty cache forces a full parse, which also writes a fresh cache file.
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    out.indexed = Some(result.parsed_count);
+
+    if json {
+        print_json(&out);
+    } else {
+        println!(
+            "cleared {} cache file(s), freed {} bytes",
+            out.cleared_files, out.bytes_freed
+        );
+        println!("rebuilt cache with {} entries", result.parsed_count);
+    }
+
+    0
+}
+
+#[derive(serde::Serialize)]
+struct VerifyOut {
+    ok: usize,
+    corrupt: usize,
+    missing: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    removed: Option<usize>,
+}
+
+/// Exits 1 if a corrupt file is left behind (always 0 with `--fix`, unless
+/// removing one failed).
+pub fn verify(fix: bool, json: bool) -> i32 {
+    let mut out = VerifyOut {
+        ok: 0,
+        corrupt: 0,
+        missing: 0,
+        removed: fix.then_some(0),
+    };
+    let mut left_corrupt = false;
+
+    for path in cache_files(&cache_dir()) {
+        match crate::cache::verify(&path) {
+            CacheVerifyResult::Ok => out.ok += 1,
+            CacheVerifyResult::Missing(paths) => {
+                out.missing += 1;
+                if !json {
+                    println!(
+                        "{}: {} indexed file(s) no longer exist",
+                        path.display(),
+                        paths.len()
+                    );
+                }
+            }
+            CacheVerifyResult::Corrupt(reason) => {
+                out.corrupt += 1;
+                if !json {
+                    println!("{}: corrupt ({reason})", path.display());
+                }
+                if !fix {
+                    left_corrupt = true;
+                    continue;
+                }
+                match fs::remove_file(&path) {
+                    Ok(()) => *out.removed.get_or_insert(0) += 1,
+                    Err(e) => {
+                        left_corrupt = true;
+                        eprintln!("desktop-indexer: failed to remove {}: {e}", path.display());
+                    }
+                }
+            }
+        }
+    }
+
+    if json {
+        print_json(&out);
+    } else {
+        println!(
+            "ok={} corrupt={} missing={}",
+            out.ok, out.corrupt, out.missing
+        );
+        if let Some(removed) = out.removed {
+            println!("removed {removed} corrupt file(s)");
+        }
+    }
+
+    if left_corrupt { 1 } else { 0 }
+}
+
+pub fn inspect(path: &Path, json: bool) -> i32 {
+    let cache = match crate::cache::inspect(path) {
+        Ok(cache) => cache,
+        Err(reason) => {
+            eprintln!("desktop-indexer: {}: {reason}", path.display());
+            return 1;
+        }
+    };
+
+    if json {
+        print_json(&cache);
+        return 0;
+    }
+
+    let current = if is_current_version(cache.version) {
+        "current"
+    } else {
+        "outdated"
+    };
+    println!("version: {} ({current})", cache.version);
+    println!("roots:");
+    for r in &cache.roots {
+        println!("  {r}");
+    }
+    println!("entries: {}", cache.entries.len());
+    for ce in &cache.entries {
+        let e = &ce.entry.out;
+        println!(
+            "{}\tsize={}\tmtime_sec={}\t{}\t{}",
+            ce.path,
+            ce.size,
+            ce.mtime_sec,
+            e.id,
+            e.display_name()
+        );
+    }
+
+    0
+}
+
+fn clear_cache_dir() -> CacheOut {
+    let mut out = CacheOut {
+        cleared_files: 0,
+        bytes_freed: 0,
+        indexed: None,
+    };
+
+    for path in cache_files(&cache_dir()) {
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                out.cleared_files += 1;
+                out.bytes_freed += size;
+            }
+            Err(e) => eprintln!("desktop-indexer: failed to remove {}: {e}", path.display()),
+        }
+    }
+
+    out
+}
@@ -1,11 +1,14 @@
 use crate::cli::Cli;
+use crate::daemon_client::{ClientError, DaemonClient};
 use crate::ipc::{Request, Response};
-use crate::{daemon, daemon_client};
+use crate::models::ScanOptions;
+use crate::{daemon, daemon_client, xdg};
+use std::io::Write;
 
 use super::common::trace;
 
 pub fn start_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf]) -> i32 {
-    match daemon::start_daemon() {
+    match daemon::start_daemon(cli.config_path.as_deref(), cli.no_cache) {
         Ok(daemon::StartResult::Started) => {
             warmup_daemon(cli, scan_roots);
             println!("daemon started successfully");
@@ -33,11 +36,16 @@ fn warmup_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf]) {
         .map(|p| p.to_string_lossy().to_string())
         .collect();
 
-    let resp = daemon_client::try_request(&Request::Warmup {
-        roots,
-        respect_try_exec: cli.respect_try_exec,
-    });
-    if matches!(resp, Some(Response::Ok)) {
+    let resp = daemon_client::try_request(
+        &Request::Warmup {
+            roots,
+            respect_try_exec: cli.respect_try_exec,
+            de_filter: cli.de_filter,
+        },
+        cli.daemon_timeouts(),
+    )
+    .inspect_err(ClientError::log);
+    if matches!(resp, Ok(Response::Ok)) {
         trace(cli, "daemon warmup ok");
     } else {
         trace(cli, "daemon warmup failed");
@@ -50,12 +58,12 @@ pub fn stop_daemon(cli: &Cli) -> i32 {
         return 0;
     }
 
-    match daemon_client::try_request(&Request::Shutdown) {
-        Some(Response::Ok) => {
+    match daemon_client::try_request(&Request::Shutdown, cli.daemon_timeouts()) {
+        Ok(Response::Ok) => {
             println!("daemon stopped");
             0
         }
-        Some(Response::Error { message }) => {
+        Ok(Response::Error { message }) => {
             if message.contains("unknown variant `shutdown`") {
                 eprintln!(
                     "desktop-indexer: daemon is running but too old (no shutdown support). Restart it manually, then try again."
@@ -64,6 +72,10 @@ pub fn stop_daemon(cli: &Cli) -> i32 {
             eprintln!("desktop-indexer: daemon error: {message}");
             1
         }
+        Err(e) if !e.is_expected() => {
+            eprintln!("desktop-indexer: daemon request failed: {e}");
+            1
+        }
         _ => {
             println!("daemon not running");
             0
@@ -71,21 +83,344 @@ pub fn stop_daemon(cli: &Cli) -> i32 {
     }
 }
 
-pub fn restart_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf]) -> i32 {
-    // Best-effort stop; ignore failures and still attempt a start.
-    let _ = stop_daemon(cli);
+pub fn reload_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf]) -> i32 {
+    if cli.no_daemon {
+        eprintln!("desktop-indexer: --no-daemon set; nothing to reload");
+        return 0;
+    }
 
-    // Give the old daemon a moment to release the socket.
-    // This is intentionally small to keep restart fast.
-    std::thread::sleep(std::time::Duration::from_millis(120));
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
 
-    start_daemon(cli, scan_roots)
+    match daemon_client::try_request(
+        &Request::Reload {
+            roots,
+            respect_try_exec: cli.respect_try_exec,
+            de_filter: cli.de_filter,
+        },
+        cli.daemon_timeouts(),
+    ) {
+        Ok(Response::Ok) => {
+            println!("daemon index reloaded");
+            0
+        }
+        Ok(Response::Error { message }) => {
+            eprintln!("desktop-indexer: daemon error: {message}");
+            1
+        }
+        Err(e) if !e.is_expected() => {
+            eprintln!("desktop-indexer: daemon request failed: {e}");
+            1
+        }
+        _ => {
+            println!("daemon not running");
+            1
+        }
+    }
 }
 
-pub fn run_daemon() -> i32 {
-    if let Err(e) = daemon::run_daemon_foreground() {
+pub fn batch(cli: &Cli) -> i32 {
+    if cli.no_daemon {
+        eprintln!("desktop-indexer: --no-daemon set; nothing to send");
+        return 0;
+    }
+    if cli.persistent_connection {
+        return stream_requests(cli);
+    }
+
+    let mut requests: Vec<Request> = Vec::new();
+    for (n, line) in std::io::stdin().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("desktop-indexer: failed to read stdin: {e}");
+                return 1;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Request>(&line) {
+            Ok(req) => requests.push(req),
+            Err(e) => {
+                eprintln!("desktop-indexer: invalid request on line {}: {e}", n + 1);
+                return 2;
+            }
+        }
+    }
+
+    let responses = match daemon_client::try_batch_request(&requests, cli.daemon_timeouts()) {
+        Ok(responses) => responses,
+        Err(e) if e.is_expected() => {
+            println!("daemon not running");
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("desktop-indexer: daemon request failed: {e}");
+            return 1;
+        }
+    };
+
+    for resp in &responses {
+        match serde_json::to_string(resp) {
+            Ok(line) => println!("{line}"),
+            Err(e) => {
+                eprintln!("desktop-indexer: failed to serialize response: {e}");
+                return 1;
+            }
+        }
+    }
+
+    0
+}
+
+/// Answer stdin requests one line at a time over a kept-alive connection.
+fn stream_requests(cli: &Cli) -> i32 {
+    let Some(mut client) = DaemonClient::connect(cli.daemon_timeouts()) else {
+        println!("daemon not running");
+        return 1;
+    };
+
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let resp = match serde_json::from_str::<Request>(&line) {
+            Ok(req) => match client.request(&req) {
+                Some(resp) => resp,
+                None => {
+                    eprintln!("desktop-indexer: lost connection to daemon");
+                    return 1;
+                }
+            },
+            Err(e) => Response::Error {
+                message: format!("invalid request: {e}"),
+            },
+        };
+
+        let Ok(out) = serde_json::to_string(&resp) else {
+            return 1;
+        };
+        if writeln!(stdout, "{out}")
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    0
+}
+
+pub fn restart_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf], warmup: bool) -> i32 {
+    if cli.no_daemon {
+        eprintln!("desktop-indexer: --no-daemon set; not restarting daemon");
+        return 0;
+    }
+
+    let was_running = matches!(
+        daemon_client::try_request(&Request::Shutdown, cli.daemon_timeouts())
+            .inspect_err(ClientError::log),
+        Ok(Response::Ok)
+    );
+    if was_running && !wait_for_exit(RESTART_EXIT_TIMEOUT) {
+        eprintln!("desktop-indexer: old daemon did not exit; not restarting");
+        return 1;
+    }
+
+    match daemon::start_daemon(cli.config_path.as_deref(), cli.no_cache) {
+        Ok(daemon::StartResult::Started) => {
+            if warmup {
+                warmup_daemon(cli, scan_roots);
+            }
+            if was_running {
+                println!("daemon restarted");
+            } else {
+                println!("daemon started (was not running)");
+            }
+            0
+        }
+        // Another client started one in the meantime.
+        Ok(daemon::StartResult::AlreadyRunning) => {
+            eprintln!("desktop-indexer: a daemon is already running; not restarting");
+            1
+        }
+        Err(e) => {
+            eprintln!("daemon start error: {e}");
+            1
+        }
+    }
+}
+
+/// How long `restart` waits for the old daemon to exit. It answers clients
+/// queued before the shutdown first, keep-alive ones until they go idle.
+const RESTART_EXIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Poll until the old daemon process has exited (no live PID in the PID file).
+/// The socket is removed before that, so it can't tell. Returns false if
+/// `timeout` runs out first.
+fn wait_for_exit(timeout: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    while daemon::running_pid().is_some() {
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    true
+}
+
+pub fn run_daemon(cli: &Cli, scan_roots: &[std::path::PathBuf], systemd: bool) -> i32 {
+    let max_pending = cli
+        .config
+        .max_pending
+        .unwrap_or(daemon::DEFAULT_MAX_PENDING);
+    // The daemon scans sequentially, in the environment's locale; the rest
+    // comes from the config it was started with.
+    let scan_options = ScanOptions {
+        parallel: false,
+        locale: None,
+        ..cli.scan_options()
+    };
+    let warmup_roots = cli.config.warmup_at_start.unwrap_or(false).then(|| {
+        scan_roots
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect()
+    });
+    let default_limit = cli
+        .config
+        .default_limit
+        .unwrap_or(crate::search::DEFAULT_SEARCH_LIMIT);
+    if let Err(e) = daemon::run_daemon_foreground(
+        max_pending,
+        scan_options,
+        default_limit,
+        warmup_roots,
+        systemd,
+    ) {
         eprintln!("desktop-indexer: daemon failed: {e}");
         return 1;
     }
     0
 }
+
+/// Print a user unit for the daemon: the `.service`, or with `activate_socket`
+/// the `.socket` that hands it its listener.
+pub fn generate_systemd(cli: &Cli, activate_socket: bool) -> i32 {
+    let unit_dir = "~/.config/systemd/user";
+    // An explicit socket has to reach the service too; otherwise systemd's
+    // %t is the same $XDG_RUNTIME_DIR the daemon would use.
+    let socket_override = std::env::var_os("DESKTOP_INDEXER_SOCKET")
+        .filter(|p| !p.is_empty())
+        .map(|_| xdg::socket_path().to_string_lossy().to_string());
+
+    if activate_socket {
+        let listen = socket_override
+            .as_deref()
+            .map(|p| p.replace('%', "%%"))
+            .unwrap_or_else(|| "%t/desktop-indexer.sock".to_string());
+        print!(
+            "[Unit]\n\
+             Description=desktop-indexer daemon socket\n\
+             \n\
+             [Socket]\n\
+             ListenStream={listen}\n\
+             SocketMode=0600\n\
+             \n\
+             [Install]\n\
+             WantedBy=sockets.target\n"
+        );
+        eprintln!(
+            "desktop-indexer: save as {unit_dir}/desktop-indexer.socket next to the .service, then run: systemctl --user enable --now desktop-indexer.socket"
+        );
+        return 0;
+    }
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe.to_string_lossy().to_string(),
+        Err(e) => {
+            eprintln!("desktop-indexer: cannot locate the executable: {e}");
+            return 1;
+        }
+    };
+    let mut exec_start = exec_quote(&exe);
+    if let Some(config) = &cli.config_path {
+        exec_start.push_str(" --config ");
+        exec_start.push_str(&exec_quote(&config.to_string_lossy()));
+    }
+    // --systemd binds its own socket when not socket-activated.
+    exec_start.push_str(" run-daemon --systemd");
+
+    let environment = socket_override
+        .map(|p| {
+            format!(
+                "Environment={}\n",
+                unit_quote(&format!("DESKTOP_INDEXER_SOCKET={p}"))
+            )
+        })
+        .unwrap_or_default();
+    print!(
+        "[Unit]\n\
+         Description=desktop-indexer daemon\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={exec_start}\n\
+         {environment}\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n"
+    );
+    eprintln!(
+        "desktop-indexer: save as {unit_dir}/desktop-indexer.service, then run: systemctl --user daemon-reload && systemctl --user enable --now desktop-indexer.service"
+    );
+    0
+}
+
+/// Quote a word for a systemd unit line when it contains spaces or quotes;
+/// `%` is doubled so systemd doesn't read it as a specifier.
+fn unit_quote(s: &str) -> String {
+    let s = s.replace('%', "%%");
+    if s.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s
+    }
+}
+
+/// `unit_quote` for a word of `ExecStart=`, which also expands `$VAR`.
+fn exec_quote(s: &str) -> String {
+    unit_quote(&s.replace('$', "$$"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_words_are_quoted_and_escaped() {
+        assert_eq!(
+            unit_quote("/usr/bin/desktop-indexer"),
+            "/usr/bin/desktop-indexer"
+        );
+        assert_eq!(
+            unit_quote("/home/me/My Apps/desktop-indexer"),
+            "\"/home/me/My Apps/desktop-indexer\""
+        );
+        assert_eq!(unit_quote("/opt/100%/bin"), "/opt/100%%/bin");
+        assert_eq!(unit_quote("/opt/50% off/bin"), "\"/opt/50%% off/bin\"");
+        assert_eq!(unit_quote(r#"say "hi"\now"#), r#""say \"hi\"\\now""#);
+        // `$` is only special where variables are expanded.
+        assert_eq!(unit_quote("/opt/$HOME"), "/opt/$HOME");
+        assert_eq!(exec_quote("/opt/$HOME/50%"), "/opt/$$HOME/50%%");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::launch::{Terminal, is_executable_in_path};
+use crate::output::print_json;
+use crate::xdg;
+use serde::Serialize;
+use std::{
+    fs,
+    io::{ErrorKind, IsTerminal},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Status::Ok => "\x1b[32m",
+            Status::Warn => "\x1b[33m",
+            Status::Fail => "\x1b[31m",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Check {
+    check: String,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(check: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every check locally; the daemon is only probed, never required.
+pub fn doctor(scan_roots: &[PathBuf], json: bool) -> i32 {
+    let mut checks: Vec<Check> = scan_roots.iter().map(|r| check_scan_root(r)).collect();
+    checks.push(check_cache_dir());
+    checks.push(check_frequency_file());
+    checks.push(check_socket());
+    checks.push(check_terminals());
+
+    if json {
+        print_json(&checks);
+    } else {
+        let color = std::io::stdout().is_terminal();
+        for c in &checks {
+            let label = c.status.label();
+            if color {
+                println!(
+                    "{}{label:<4}\x1b[0m  {}: {}",
+                    c.status.color(),
+                    c.check,
+                    c.detail
+                );
+            } else {
+                println!("{label:<4}  {}: {}", c.check, c.detail);
+            }
+        }
+    }
+
+    if checks.iter().any(|c| c.status == Status::Fail) {
+        1
+    } else {
+        0
+    }
+}
+
+fn check_scan_root(root: &Path) -> Check {
+    let name = format!("scan root {}", root.display());
+    match fs::read_dir(root) {
+        Ok(_) => Check::new(name, Status::Ok, "readable"),
+        // Missing XDG dirs are normal (e.g. no user-local applications yet).
+        Err(e) if e.kind() == ErrorKind::NotFound => Check::new(name, Status::Warn, "missing"),
+        Err(e) => Check::new(name, Status::Fail, format!("not readable: {e}")),
+    }
+}
+
+fn check_cache_dir() -> Check {
+    let dir = xdg::cache_dir();
+    let name = format!("cache dir {}", dir.display());
+    let probe = dir.join(".doctor-probe");
+
+    let res = fs::create_dir_all(&dir).and_then(|_| fs::write(&probe, b""));
+    let _ = fs::remove_file(&probe);
+    match res {
+        Ok(()) => Check::new(name, Status::Ok, "writable"),
+        Err(e) => Check::new(name, Status::Fail, format!("not writable: {e}")),
+    }
+}
+
+fn check_frequency_file() -> Check {
+    let path = crate::frequency::frequency_path();
+    let name = format!("frequency file {}", path.display());
+    match fs::File::open(&path) {
+        Ok(_) => Check::new(name, Status::Ok, "readable"),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            Check::new(name, Status::Ok, "not created yet (no launches recorded)")
+        }
+        Err(e) => Check::new(name, Status::Fail, format!("cannot open: {e}")),
+    }
+}
+
+fn check_socket() -> Check {
+    let path = xdg::socket_path();
+    let name = format!("daemon socket {}", path.display());
+    match UnixStream::connect(&path) {
+        Ok(_) => Check::new(name, Status::Ok, "reachable"),
+        Err(_) => Check::new(name, Status::Warn, "not reachable; commands run locally"),
+    }
+}
+
+fn check_terminals() -> Check {
+    let found: Vec<&str> = Terminal::ALL
+        .iter()
+        .map(|t| t.binary())
+        .filter(|b| is_executable_in_path(b))
+        .collect();
+
+    if found.is_empty() {
+        Check::new(
+            "terminals",
+            Status::Warn,
+            format!(
+                "none in PATH (Terminal=true apps cannot launch); looked for {}",
+                crate::launch::known_terminals()
+            ),
+        )
+    } else {
+        Check::new("terminals", Status::Ok, found.join(", "))
+    }
+}
@@ -0,0 +1,177 @@
+use crate::desktop::{current_desktops, preferred_locales, preferred_locales_from_list};
+use crate::output::print_json;
+use crate::xdg;
+use serde::Serialize;
+use std::{env, path::PathBuf};
+
+/// Every variable the indexer reads, in the order they are shown.
+const VARS: &[&str] = &[
+    "XDG_DATA_HOME",
+    "XDG_DATA_DIRS",
+    "XDG_CACHE_HOME",
+    "XDG_CONFIG_HOME",
+    "XDG_RUNTIME_DIR",
+    "XDG_CURRENT_DESKTOP",
+    "DESKTOP_INDEXER_LOCALES",
+    "LC_ALL",
+    "LC_MESSAGES",
+    "LANG",
+    "HOME",
+    "USER",
+    "PATH",
+    "NIX_PROFILES",
+    "DESKTOP_INDEXER_TIMING",
+    "DESKTOP_INDEXER_SOCKET",
+];
+
+#[derive(Serialize)]
+struct EnvVar {
+    name: &'static str,
+    value: Option<String>,
+    /// What the indexer makes of it, e.g. `scan root: ...`.
+    derived: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct EnvOut {
+    vars: Vec<EnvVar>,
+    scan_roots: Vec<String>,
+    socket: String,
+    cache_dir: String,
+    data_dir: String,
+    config_file: String,
+}
+
+/// Purely local: shows what the variables resolve to, without the daemon.
+pub fn env(scan_roots: &[PathBuf], json: bool) -> i32 {
+    let out = EnvOut {
+        vars: VARS
+            .iter()
+            .map(|&name| EnvVar {
+                name,
+                value: env::var_os(name).map(|v| v.to_string_lossy().to_string()),
+                derived: derived(name),
+            })
+            .collect(),
+        scan_roots: scan_roots.iter().map(|p| display(p)).collect(),
+        socket: display(&xdg::socket_path()),
+        cache_dir: display(&xdg::cache_dir()),
+        data_dir: display(&xdg::data_dir()),
+        config_file: display(&xdg::config_file()),
+    };
+
+    if json {
+        print_json(&out);
+        return 0;
+    }
+
+    for v in &out.vars {
+        match &v.value {
+            Some(value) => println!("{}={value}", v.name),
+            None => println!("{} (unset)", v.name),
+        }
+        for d in &v.derived {
+            println!("  {d}");
+        }
+    }
+    println!();
+    println!("scan roots:");
+    for r in &out.scan_roots {
+        println!("  {r}");
+    }
+    println!("socket={}", out.socket);
+    println!("cache_dir={}", out.cache_dir);
+    println!("data_dir={}", out.data_dir);
+    println!("config_file={}", out.config_file);
+
+    0
+}
+
+fn derived(name: &str) -> Vec<String> {
+    let set = |var: &str| env::var_os(var).is_some_and(|v| !v.is_empty());
+
+    match name {
+        "XDG_DATA_HOME" => {
+            let data_home = xdg::data_dir();
+            let base = data_home.parent().map(PathBuf::from).unwrap_or_default();
+            vec![
+                format!("scan root: {}", display(&base.join("applications"))),
+                format!("data dir: {}", display(&data_home)),
+            ]
+        }
+        "XDG_DATA_DIRS" => env::var("XDG_DATA_DIRS")
+            .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string())
+            .split(':')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                format!(
+                    "scan root: {}",
+                    display(&PathBuf::from(s).join("applications"))
+                )
+            })
+            .collect(),
+        "XDG_CACHE_HOME" => vec![format!("cache dir: {}", display(&xdg::cache_dir()))],
+        "XDG_CONFIG_HOME" => vec![format!("config file: {}", display(&xdg::config_file()))],
+        "XDG_RUNTIME_DIR" if !set("DESKTOP_INDEXER_SOCKET") => {
+            vec![format!("socket: {}", display(&xdg::socket_path()))]
+        }
+        "XDG_CURRENT_DESKTOP" => {
+            let desktops = current_desktops();
+            if desktops.is_empty() {
+                vec!["desktops: none (--de-filter hides OnlyShowIn entries)".to_string()]
+            } else {
+                vec![format!("desktops: {}", desktops.join(", "))]
+            }
+        }
+        // DESKTOP_INDEXER_LOCALES, then the first non-empty one of
+        // LC_ALL > LC_MESSAGES > LANG wins.
+        "DESKTOP_INDEXER_LOCALES" | "LC_ALL" | "LC_MESSAGES" | "LANG" => {
+            let winner = if env::var("DESKTOP_INDEXER_LOCALES")
+                .is_ok_and(|l| !preferred_locales_from_list(&l).is_empty())
+            {
+                Some("DESKTOP_INDEXER_LOCALES")
+            } else {
+                ["LC_ALL", "LC_MESSAGES", "LANG"]
+                    .into_iter()
+                    .find(|var| set(var))
+            };
+            if winner == Some(name) {
+                vec![format!("locales: {}", preferred_locales().join(", "))]
+            } else {
+                Vec::new()
+            }
+        }
+        "USER" if !set("DESKTOP_INDEXER_SOCKET") && !set("XDG_RUNTIME_DIR") => {
+            vec![format!("socket: {}", display(&xdg::socket_path()))]
+        }
+        "PATH" => {
+            let dirs = env::var_os("PATH")
+                .map(|p| env::split_paths(&p).count())
+                .unwrap_or(0);
+            vec![format!(
+                "{dirs} directories searched for TryExec and terminals"
+            )]
+        }
+        "NIX_PROFILES" => crate::scan_sources::nix_extra_roots()
+            .iter()
+            .filter(|p| p.is_dir())
+            .map(|p| format!("scan root: {}", display(p)))
+            .collect(),
+        "DESKTOP_INDEXER_TIMING" => {
+            let on = matches!(
+                env::var("DESKTOP_INDEXER_TIMING").as_deref(),
+                Ok("1") | Ok("true") | Ok("yes")
+            );
+            vec![format!("timing: {}", if on { "on" } else { "off" })]
+        }
+        "DESKTOP_INDEXER_SOCKET" if set("DESKTOP_INDEXER_SOCKET") => {
+            vec![format!("socket: {}", display(&xdg::socket_path()))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn display(p: &std::path::Path) -> String {
+    p.to_string_lossy().to_string()
+}
@@ -0,0 +1,258 @@
+use crate::cli::{Cli, FrequencySort};
+use crate::daemon_client::{self, ClientError};
+use crate::frequency::{FrequencyStore, Usage, UsageRecord, format_unix_ts, unix_seconds_now};
+use crate::ipc::{Request, Response};
+use crate::output::print_json;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use super::common::trace;
+
+pub fn stats(top: Option<usize>, sort: FrequencySort, json: bool) -> i32 {
+    let store = FrequencyStore::load();
+    let mut rows = sorted_rows(store.map(), sort);
+    if let Some(top) = top {
+        rows.truncate(top);
+    }
+
+    if json {
+        #[derive(serde::Serialize)]
+        struct StatOut<'a> {
+            id: &'a str,
+            freq: u32,
+            last_used: u64,
+        }
+
+        let out: Vec<StatOut> = rows
+            .iter()
+            .map(|(id, freq, last_used)| StatOut {
+                id,
+                freq: *freq,
+                last_used: *last_used,
+            })
+            .collect();
+        print_json(&out);
+    } else {
+        for (id, freq, last_used) in &rows {
+            // 0 means "unknown" in the store.
+            let ts = if *last_used == 0 {
+                "-".to_string()
+            } else {
+                format_unix_ts(*last_used)
+            };
+            println!("{id}\t{freq}\t{ts}");
+        }
+    }
+
+    0
+}
+
+/// `(id, freq, last_used)` rows, best first by `sort`; the other key, then the
+/// id, break ties.
+fn sorted_rows(map: &HashMap<String, Usage>, sort: FrequencySort) -> Vec<(&String, u32, u64)> {
+    let mut rows: Vec<(&String, u32, u64)> = map
+        .iter()
+        .map(|(id, u)| (id, u.freq, u.last_used))
+        .collect();
+
+    rows.sort_by(|a, b| {
+        match sort {
+            FrequencySort::Freq => b.1.cmp(&a.1).then_with(|| b.2.cmp(&a.2)),
+            FrequencySort::LastUsed => b.2.cmp(&a.2).then_with(|| b.1.cmp(&a.1)),
+        }
+        .then_with(|| a.0.cmp(b.0))
+    });
+    rows
+}
+
+pub fn reset(cli: &Cli, id: Option<&str>, yes: bool) -> i32 {
+    let mut store = FrequencyStore::load();
+
+    let removed = match id {
+        Some(id) => usize::from(store.remove(id.trim_end_matches(".desktop"))),
+        None => {
+            if !yes
+                && !confirm(&format!(
+                    "Reset usage data for {} app(s)?",
+                    store.map().len()
+                ))
+            {
+                eprintln!("aborted");
+                return 1;
+            }
+            store.clear()
+        }
+    };
+    store.flush();
+
+    // The daemon keeps its own copy and would write it back on the next launch.
+    if !cli.no_daemon {
+        match daemon_client::try_request(
+            &Request::ResetFrequency {
+                id: id.map(|s| s.to_string()),
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        {
+            Ok(Response::Ok) => trace(cli, "daemon frequency reset ok"),
+            Ok(Response::Error { message }) => {
+                eprintln!("desktop-indexer: daemon error: {message}");
+            }
+            _ => trace(cli, "daemon not running (frequency reset)"),
+        }
+    }
+
+    println!(
+        "removed {removed} entr{}",
+        if removed == 1 { "y" } else { "ies" }
+    );
+    0
+}
+
+pub fn increment(cli: &Cli, id: &str) -> i32 {
+    let id = id.trim_end_matches(".desktop");
+    update(
+        cli,
+        &Request::IncrementFrequency { id: id.to_string() },
+        |store| {
+            store.increment(id);
+        },
+    )
+}
+
+pub fn set(cli: &Cli, id: &str, freq: u32, last_used: Option<u64>) -> i32 {
+    let id = id.trim_end_matches(".desktop");
+    let usage = Usage {
+        freq,
+        last_used: last_used.unwrap_or_else(unix_seconds_now),
+    };
+    let req = Request::SetFrequency {
+        id: id.to_string(),
+        freq: usage.freq,
+        last_used: usage.last_used,
+    };
+    update(cli, &req, |store| store.set(id, usage))
+}
+
+/// Apply a change through the daemon when it runs (so its in-memory copy
+/// stays current), otherwise to the file directly.
+fn update(cli: &Cli, req: &Request, local: impl FnOnce(&mut FrequencyStore)) -> i32 {
+    if !cli.no_daemon {
+        match daemon_client::try_request(req, cli.daemon_timeouts()).inspect_err(ClientError::log) {
+            Ok(Response::Ok) => {
+                trace(cli, "mode=daemon (frequency)");
+                return 0;
+            }
+            Ok(Response::Error { message }) => {
+                eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
+            }
+            _ => {}
+        }
+    }
+
+    trace(cli, "mode=local (frequency)");
+    let mut store = FrequencyStore::load();
+    local(&mut store);
+    store.flush();
+    0
+}
+
+pub fn export(output: Option<&Path>) -> i32 {
+    let store = FrequencyStore::load();
+    let records = store.export();
+
+    let Some(output) = output else {
+        print_json(&records);
+        return 0;
+    };
+
+    let data = serde_json::to_string_pretty(&records).unwrap();
+    if let Err(e) = std::fs::write(output, data + "\n") {
+        eprintln!("desktop-indexer: failed to write {}: {e}", output.display());
+        return 1;
+    }
+    eprintln!("exported {} entries to {}", records.len(), output.display());
+    0
+}
+
+pub fn import(cli: &Cli, file: &Path) -> i32 {
+    let records: Vec<UsageRecord> = match std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+    {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("desktop-indexer: failed to import {}: {e}", file.display());
+            return 1;
+        }
+    };
+
+    let mut store = FrequencyStore::load();
+    let changed = store.merge(&records);
+    store.flush();
+
+    // The daemon keeps its own copy and would write it back on the next launch.
+    if changed > 0 && !cli.no_daemon {
+        match daemon_client::try_request(&Request::ReloadFrequency, cli.daemon_timeouts())
+            .inspect_err(ClientError::log)
+        {
+            Ok(Response::Ok) => trace(cli, "daemon frequency reload ok"),
+            Ok(Response::Error { message }) => {
+                eprintln!("desktop-indexer: daemon error: {message}");
+            }
+            _ => trace(cli, "daemon not running (frequency reload)"),
+        }
+    }
+
+    println!("imported {} entries ({changed} changed)", records.len());
+    0
+}
+
+fn confirm(prompt: &str) -> bool {
+    eprint!("{prompt} [y/N] ");
+    let _ = std::io::stderr().flush();
+
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line).is_err() {
+        return false;
+    }
+    matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(map: &HashMap<String, Usage>, sort: FrequencySort) -> Vec<&str> {
+        sorted_rows(map, sort)
+            .into_iter()
+            .map(|(id, _, _)| id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn stats_rows_follow_the_sort_key() {
+        let map: HashMap<String, Usage> = [
+            ("firefox", 40, 1_000),
+            ("vlc", 3, 9_000),
+            ("gimp", 40, 5_000),
+            ("zed", 3, 9_000),
+            ("never", 1, 0),
+        ]
+        .into_iter()
+        .map(|(id, freq, last_used)| (id.to_string(), Usage { freq, last_used }))
+        .collect();
+
+        // Equal counts: the more recent first, then by id.
+        assert_eq!(
+            ids(&map, FrequencySort::Freq),
+            ["gimp", "firefox", "vlc", "zed", "never"]
+        );
+        assert_eq!(
+            ids(&map, FrequencySort::LastUsed),
+            ["vlc", "zed", "gimp", "firefox", "never"]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use crate::cli::Cli;
+use crate::daemon_client::{self, ClientError};
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::ipc::{Request, Response};
+use crate::models::DesktopEntryOut;
+use crate::output::{print_json, strip_extra};
+
+use super::common::{timing, trace};
+
+pub fn get(cli: &Cli, scan_roots: &[std::path::PathBuf], id: &str, json: bool) -> i32 {
+    let start = std::time::Instant::now();
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    let daemon_resp = if !cli.use_daemon_index() {
+        None
+    } else {
+        daemon_client::try_request(
+            &Request::GetEntry {
+                roots,
+                id: id.to_string(),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
+    };
+
+    let (mode, entry): (&str, Option<DesktopEntryOut>) = match daemon_resp {
+        Some(Response::Entries { entries }) => ("daemon", entries.into_iter().next()),
+        Some(Response::Error { message }) => {
+            eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
+            ("local", local_get(cli, scan_roots, id))
+        }
+        _ => ("local", local_get(cli, scan_roots, id)),
+    };
+
+    trace(cli, &format!("mode={mode} (get)"));
+    timing(mode, start);
+
+    let Some(mut e) = entry else {
+        eprintln!("desktop-indexer: no entry with id {id}");
+        return 1;
+    };
+
+    if cli.no_extra {
+        strip_extra(std::slice::from_mut(&mut e));
+    }
+
+    if json {
+        print_json(&e);
+    } else {
+        println!("{}\t{}", e.id, e.display_name());
+    }
+
+    0
+}
+
+fn local_get(cli: &Cli, scan_roots: &[std::path::PathBuf], id: &str) -> Option<DesktopEntryOut> {
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    result
+        .entries
+        .into_iter()
+        .find(|e| e.out.id == id)
+        .map(|e| e.out)
+}
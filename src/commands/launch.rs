@@ -1,31 +1,71 @@
 use crate::cli::Cli;
-use crate::daemon_client;
+use crate::daemon_client::{self, ClientError};
 use crate::desktop::scan_and_parse_desktop_files;
 use crate::frequency::FrequencyStore;
 use crate::ipc::{Request, Response};
-use crate::launch::{Terminal, exec_to_argv, pick_terminal};
+use crate::launch::{
+    LaunchEnv, Terminal, dbus_activate_argv, dbus_service_name, exec_to_argv_with_args,
+    known_terminals, launch_args, pick_terminal,
+};
+use crate::launched::record_launch;
+use crate::models::DesktopEntryOut;
+use crate::output::print_json;
+use std::path::Path;
+use std::process::{Command, Stdio};
 
 use super::common::{timing, trace};
 
-pub fn launch(
-    cli: &Cli,
-    scan_roots: &[std::path::PathBuf],
-    desktop_id: &str,
-    action: Option<&str>,
-) -> i32 {
+pub struct LaunchArgs<'a> {
+    pub desktop_id: &'a str,
+    pub action: Option<&'a str>,
+    pub files: &'a [String],
+    pub urls: &'a [String],
+    /// Overrides the entry's `Path=`.
+    pub cwd: Option<&'a Path>,
+    /// Extra `KEY=VALUE` environment for the app.
+    pub env: &'a [String],
+    /// Print the resolved argv instead of spawning it.
+    pub dry_run: bool,
+    pub json: bool,
+}
+
+pub fn launch(cli: &Cli, scan_roots: &[std::path::PathBuf], args: &LaunchArgs) -> i32 {
+    let LaunchArgs {
+        desktop_id, action, ..
+    } = *args;
     let start = std::time::Instant::now();
     let roots: Vec<String> = scan_roots
         .iter()
         .map(|p| p.to_string_lossy().to_string())
         .collect();
+    // The daemon runs elsewhere, so a relative --cwd is resolved here.
+    let cwd: Option<String> = args.cwd.map(|p| {
+        std::path::absolute(p)
+            .unwrap_or_else(|_| p.to_path_buf())
+            .to_string_lossy()
+            .to_string()
+    });
 
+    // A dry run has to resolve the command here, so it never asks the daemon.
     if !cli.no_daemon
-        && let Some(resp) = daemon_client::try_request(&Request::Launch {
-            roots,
-            desktop_id: desktop_id.to_string(),
-            action: action.map(|s| s.to_string()),
-            respect_try_exec: cli.respect_try_exec,
-        })
+        && !args.dry_run
+        && let Some(resp) = daemon_client::try_request(
+            &Request::Launch {
+                roots,
+                desktop_id: desktop_id.to_string(),
+                action: action.map(|s| s.to_string()),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+                terminal: cli.config.preferred_terminal,
+                files: args.files.to_vec(),
+                urls: args.urls.to_vec(),
+                cwd: cwd.clone(),
+                env: args.env.to_vec(),
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
     {
         match resp {
             Response::Ok => {
@@ -44,35 +84,76 @@ pub fn launch(
     timing("local", start);
 
     // Local fallback
-    use std::process::Command;
     let id = desktop_id.trim_end_matches(".desktop");
 
-    let mut freqs = FrequencyStore::load();
+    let file_args = launch_args(args.files, args.urls);
 
-    let result = scan_and_parse_desktop_files(scan_roots, None, cli.respect_try_exec);
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
     let entry = result.entries.iter().find(|e| e.out.id == id);
     let Some(entry) = entry else {
         eprintln!("Unknown desktop-id: {id}");
         return 1;
     };
 
-    let mut selected_exec: Option<&str> = entry.out.exec.as_deref();
-    if let Some(action_id) = action {
-        let Some(act) = entry.out.actions.iter().find(|a| a.id == action_id) else {
-            eprintln!("Unknown action '{action_id}' for id={id}");
-            if !entry.out.actions.is_empty() {
-                eprintln!("Available actions:");
-                for a in &entry.out.actions {
-                    eprintln!("  {}", a.id);
+    let launch_env = LaunchEnv {
+        cwd: working_dir(&entry.out, cwd.as_deref()),
+        vars: args.env,
+    };
+
+    if args.dry_run {
+        let resolved = match resolve_dbus_activation(&entry.out, action, &file_args, &launch_env) {
+            Some(argv) => Ok(argv),
+            None => resolve_launch(
+                &entry.out,
+                action,
+                &file_args,
+                cli.config.preferred_terminal,
+            ),
+        };
+        return match resolved {
+            Ok(argv) => {
+                if args.json {
+                    print_json(&argv);
+                } else {
+                    let mut line = String::new();
+                    if let Some(dir) = launch_env.cwd {
+                        line.push_str(&format!("cd {} && ", shell_join(&[dir.to_string()])));
+                    }
+                    if !args.env.is_empty() {
+                        line.push_str(&format!("env {} ", shell_join(args.env)));
+                    }
+                    line.push_str(&shell_join(&argv));
+                    println!("{line}");
                 }
+                0
+            }
+            Err(message) => {
+                eprintln!("{message}");
+                1
             }
-            return 1;
         };
-        selected_exec = act.exec.as_deref();
     }
 
+    launch_entry(cli, &entry.out, action, &file_args, &launch_env)
+}
+
+/// Launch `entry` from this process: gtk-launch (default action only), then
+/// D-Bus activation, then `Exec=`. Records the launch in the frequency store.
+pub fn launch_entry(
+    cli: &Cli,
+    entry: &DesktopEntryOut,
+    action: Option<&str>,
+    file_args: &[&str],
+    launch_env: &LaunchEnv,
+) -> i32 {
+    let id = entry.id.as_str();
+    let mut freqs = FrequencyStore::load();
+
+    // gtk-launch only supports the default action.
     if action.is_none() {
-        let gtk_status = Command::new("gtk-launch").arg(id).status();
+        let gtk_status = launch_env
+            .apply(Command::new("gtk-launch").arg(id).args(file_args))
+            .status();
         match gtk_status {
             Ok(s) if s.success() => {
                 freqs.increment(id);
@@ -83,90 +164,156 @@ pub fn launch(
         }
     }
 
-    if entry.out.terminal {
-        let Some(exec_line) = selected_exec else {
-            eprintln!("Terminal app but no Exec= for id={id}");
-            return 1;
-        };
+    if let Some(argv) = resolve_dbus_activation(entry, action, file_args, launch_env)
+        && run_status_ok(&argv)
+    {
+        freqs.increment(id);
+        freqs.flush();
+        return 0;
+    }
 
-        let argv = exec_to_argv(exec_line);
-        if argv.is_empty() {
-            eprintln!("Exec parsed empty for id={id} (Exec={exec_line})");
+    let argv = match resolve_launch(entry, action, file_args, cli.config.preferred_terminal) {
+        Ok(argv) => argv,
+        Err(message) => {
+            eprintln!("{message}");
             return 1;
         }
+    };
 
-        let term = pick_terminal();
-        match term {
-            Some(Terminal::Foot) => {
-                let mut cmd = Command::new("foot");
-                cmd.arg("-e").arg(&argv[0]).args(&argv[1..]);
-                let _ = cmd
-                    .spawn()
-                    .map_err(|e| eprintln!("Failed to spawn foot: {e}"));
-                freqs.increment(id);
-                freqs.flush();
-                return 0;
-            }
-            Some(Terminal::Kitty) => {
-                let mut cmd = Command::new("kitty");
-                cmd.arg(&argv[0]).args(&argv[1..]);
-                let _ = cmd
-                    .spawn()
-                    .map_err(|e| eprintln!("Failed to spawn kitty: {e}"));
-                freqs.increment(id);
-                freqs.flush();
-                return 0;
-            }
-            Some(Terminal::Alacritty) => {
-                let mut cmd = Command::new("alacritty");
-                cmd.arg("-e").arg(&argv[0]).args(&argv[1..]);
-                let _ = cmd
-                    .spawn()
-                    .map_err(|e| eprintln!("Failed to spawn alacritty: {e}"));
-                freqs.increment(id);
-                freqs.flush();
-                return 0;
-            }
-            Some(Terminal::WezTerm) => {
-                let mut cmd = Command::new("wezterm");
-                cmd.args(["start", "--"]).arg(&argv[0]).args(&argv[1..]);
-                let _ = cmd
-                    .spawn()
-                    .map_err(|e| eprintln!("Failed to spawn wezterm: {e}"));
-                freqs.increment(id);
-                freqs.flush();
-                return 0;
-            }
-            None => {
-                eprintln!("gtk-launch failed and no known terminal found for Terminal=true app.");
-                eprintln!("Install one of: foot, kitty, alacritty, wezterm");
-                return 1;
+    match launch_env
+        .apply(Command::new(&argv[0]).args(&argv[1..]))
+        .spawn()
+    {
+        Ok(child) => record_launch(id, child.id()),
+        Err(e) => eprintln!("Exec launch failed for id={id}: {e}"),
+    }
+
+    freqs.increment(id);
+    freqs.flush();
+
+    0
+}
+
+/// The argv a launch spawns when gtk-launch isn't used: the selected action's
+/// `Exec=` with field codes expanded from `files`, wrapped in a terminal for
+/// `Terminal=true` apps. Never empty on success.
+pub fn resolve_launch(
+    entry: &DesktopEntryOut,
+    action: Option<&str>,
+    files: &[&str],
+    terminal: Option<Terminal>,
+) -> Result<Vec<String>, String> {
+    let id = &entry.id;
+
+    let mut selected_exec = entry.exec.as_deref();
+    if let Some(action_id) = action {
+        let Some(act) = entry.actions.iter().find(|a| a.id == action_id) else {
+            let mut message = format!("Unknown action '{action_id}' for id={id}");
+            if !entry.actions.is_empty() {
+                message.push_str("\nAvailable actions:");
+                for a in &entry.actions {
+                    message.push_str(&format!("\n  {}", a.id));
+                }
             }
-        }
+            return Err(message);
+        };
+        selected_exec = act.exec.as_deref();
     }
 
     let Some(exec_line) = selected_exec else {
-        eprintln!("Launch failed and no Exec= for id={id}");
-        return 1;
+        return Err(if entry.terminal {
+            format!("Terminal app but no Exec= for id={id}")
+        } else {
+            format!("Launch failed and no Exec= for id={id}")
+        });
     };
 
-    let argv = exec_to_argv(exec_line);
+    let argv = exec_to_argv_with_args(exec_line, files);
     if argv.is_empty() {
-        eprintln!("Exec parsed empty for id={id} (Exec={exec_line})");
-        return 1;
+        return Err(format!("Exec parsed empty for id={id} (Exec={exec_line})"));
     }
 
-    let mut cmd = Command::new(&argv[0]);
-    if argv.len() > 1 {
-        cmd.args(&argv[1..]);
+    if !entry.terminal {
+        return Ok(argv);
     }
 
-    let _ = cmd
-        .spawn()
-        .map_err(|e| eprintln!("Exec launch failed for id={id}: {e}"));
+    let term = pick_terminal(terminal).ok_or_else(|| {
+        format!(
+            "No known terminal found for Terminal=true app id={id}.\nInstall one of: {}",
+            known_terminals()
+        )
+    })?;
+    Ok(term.wrap(&argv))
+}
 
-    freqs.increment(id);
-    freqs.flush();
+/// `gdbus call` argv that activates a `DBusActivatable=true` app through its
+/// D-Bus service, tried after gtk-launch and before `Exec=`. Only for the
+/// default action without files/URLs (those go through `Exec=`), and not
+/// when extra environment is requested: the bus, not us, starts the service.
+pub fn resolve_dbus_activation(
+    entry: &DesktopEntryOut,
+    action: Option<&str>,
+    files: &[&str],
+    launch_env: &LaunchEnv,
+) -> Option<Vec<String>> {
+    if action.is_some()
+        || !files.is_empty()
+        || !launch_env.vars.is_empty()
+        || entry.dbus_activatable != Some(true)
+    {
+        return None;
+    }
+    dbus_service_name(&entry.id).map(dbus_activate_argv)
+}
 
-    0
+/// Directory a launch runs in: `--cwd` (or the request's `cwd`) first, then
+/// the entry's `Path=`.
+pub fn working_dir<'a>(entry: &'a DesktopEntryOut, cwd: Option<&'a str>) -> Option<&'a str> {
+    cwd.or(entry.working_dir.as_deref())
+}
+
+/// Run `argv` to completion; true when it exited successfully. Its stdout
+/// (e.g. the `()` reply `gdbus call` prints) is discarded.
+pub fn run_status_ok(argv: &[String]) -> bool {
+    Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Space-separated argv, quoted so it can be pasted into a shell.
+fn shell_join(argv: &[String]) -> String {
+    argv.iter()
+        .map(|a| shlex::try_quote(a).map_or_else(|_| a.clone(), |q| q.into_owned()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::parse_desktop_file_using_roots;
+
+    /// Parse `[Desktop Entry]` `keys` as `app.desktop`.
+    fn entry(keys: &str) -> DesktopEntryOut {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.desktop");
+        std::fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\nName=App\nExec=app\n{keys}\n"),
+        )
+        .unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C"))
+            .unwrap()
+            .out
+    }
+
+    #[test]
+    fn cwd_wins_over_the_entry_path() {
+        let entry_with_path = entry("Path=/srv/files");
+        assert_eq!(working_dir(&entry_with_path, None), Some("/srv/files"));
+        assert_eq!(working_dir(&entry_with_path, Some("/tmp")), Some("/tmp"));
+        assert_eq!(working_dir(&entry(""), None), None);
+    }
 }
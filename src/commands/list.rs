@@ -1,63 +1,301 @@
 use crate::cli::Cli;
-use crate::daemon_client;
+use crate::daemon_client::{self, ClientError};
 use crate::desktop::scan_and_parse_desktop_files;
+use crate::frequency::{FrequencyStore, Usage};
 use crate::ipc::{Request, Response};
-use crate::models::DesktopEntryOut;
-use crate::output::print_json;
+use crate::models::{DesktopActionOut, DesktopEntryOut};
+use crate::output::{
+    OutputField, OutputFormat, entries_json, print_csv, print_entries, print_ids_nul, print_json,
+    strip_extra,
+};
+use crate::scan_sources::EntrySource;
+use crate::search::{EntryFilter, sort_by_relevance};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap};
 
 use super::common::{timing, trace};
 
-pub fn list(cli: &Cli, scan_roots: &[std::path::PathBuf], json: bool) -> i32 {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    #[value(name = "name")]
+    Name,
+    #[value(name = "id")]
+    Id,
+    /// First listed category; entries without one sort last.
+    #[value(name = "category")]
+    Category,
+    /// Launch count, most used first.
+    #[value(name = "freq")]
+    Freq,
+    /// Last launch time, most recent first.
+    #[value(name = "last-used")]
+    LastUsed,
+}
+
+/// Sort by `keys` in order, each later key breaking ties of the previous ones.
+/// Remaining ties (and an empty `keys`) fall back to name order.
+pub fn sort_entries(
+    entries: &mut [DesktopEntryOut],
+    keys: &[SortKey],
+    usage: &HashMap<String, Usage>,
+) {
+    let usage_of = |e: &DesktopEntryOut| usage.get(&e.id).copied().unwrap_or_default();
+
+    entries.sort_by(|a, b| {
+        keys.iter()
+            .chain([&SortKey::Name])
+            .fold(Ordering::Equal, |ord, key| {
+                ord.then_with(|| match key {
+                    SortKey::Name => a.display_name().cmp(b.display_name()),
+                    SortKey::Id => a.id.cmp(&b.id),
+                    SortKey::Category => match (a.categories.first(), b.categories.first()) {
+                        (Some(x), Some(y)) => x.cmp(y),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    },
+                    SortKey::Freq => usage_of(b).freq.cmp(&usage_of(a).freq),
+                    SortKey::LastUsed => usage_of(b).last_used.cmp(&usage_of(a).last_used),
+                })
+            })
+    });
+}
+
+/// Which entries `list` keeps; all filters must match.
+pub struct ListFilter<'a> {
+    pub source: Option<EntrySource>,
+    pub category: Option<&'a str>,
+    pub mime_type: Option<&'a str>,
+}
+
+/// How `list` prints its entries.
+pub struct ListFormat<'a> {
+    pub output_fields: &'a [OutputField],
+    pub nul: bool,
+    pub csv: bool,
+    pub json: bool,
+    pub output_format: OutputFormat,
+    /// Follow each entry with its actions.
+    pub with_actions: bool,
+}
+
+pub fn list(
+    cli: &Cli,
+    scan_roots: &[std::path::PathBuf],
+    sort: &[SortKey],
+    score_by: Option<&str>,
+    filter: &ListFilter,
+    dedup_by_name: bool,
+    format: &ListFormat,
+) -> i32 {
     let start = std::time::Instant::now();
     let roots: Vec<String> = scan_roots
         .iter()
         .map(|p| p.to_string_lossy().to_string())
         .collect();
 
-    let daemon_resp = if cli.no_daemon {
+    let daemon_resp = if !cli.use_daemon_index() {
         None
     } else {
-        daemon_client::try_request(&Request::List {
-            roots,
-            respect_try_exec: cli.respect_try_exec,
-        })
+        daemon_client::try_request(
+            &Request::List {
+                roots: roots.clone(),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+                show_hidden: cli.show_hidden,
+                show_nodisplay: cli.show_nodisplay,
+                sort_keys: sort.to_vec(),
+                source: filter.source,
+                filter_category: filter.category.map(str::to_string),
+                filter_mime: filter.mime_type.map(str::to_string),
+                score_by: score_by.map(str::to_string),
+                weights: score_by.map(|_| cli.config.weights),
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
     };
 
-    let (mode, mut entries): (&str, Vec<DesktopEntryOut>) = if let Some(resp) = daemon_resp {
-        match resp {
-            Response::Entries { entries } => ("daemon", entries),
-            Response::Error { message } => {
+    // The daemon sorts (and scores) itself; locally the parsed entries are
+    // kept for `--sort-by-score`.
+    let (mode, mut entries, indexed): (&str, Vec<DesktopEntryOut>, _) = match daemon_resp {
+        Some(Response::Entries { entries }) => ("daemon", entries, None),
+        resp => {
+            if let Some(Response::Error { message }) = resp {
                 eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
-                let result = scan_and_parse_desktop_files(scan_roots, None, cli.respect_try_exec);
-                ("local", result.entries.into_iter().map(|e| e.out).collect())
-            }
-            _ => {
-                let result = scan_and_parse_desktop_files(scan_roots, None, cli.respect_try_exec);
-                ("local", result.entries.into_iter().map(|e| e.out).collect())
             }
+            let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+            let entries = local_entries(cli, &result.entries, filter);
+            ("local", entries, Some(result.entries))
         }
-    } else {
-        let result = scan_and_parse_desktop_files(scan_roots, None, cli.respect_try_exec);
-        ("local", result.entries.into_iter().map(|e| e.out).collect())
     };
 
-    entries.sort_by(|a, b| {
-        a.name
-            .as_deref()
-            .unwrap_or("")
-            .cmp(b.name.as_deref().unwrap_or(""))
-    });
+    if let Some(indexed) = &indexed {
+        let freqs = FrequencyStore::load();
+        sort_entries(&mut entries, sort, freqs.map());
+        if let Some(query) = score_by {
+            let scoring = crate::search::Scoring {
+                weights: cli.config.weights,
+                ..Default::default()
+            };
+            sort_by_relevance(&mut entries, indexed, query, freqs.map(), &scoring);
+        }
+    }
+    if dedup_by_name {
+        crate::search::dedup_by_name(&mut entries, &roots);
+    }
+
+    if cli.no_extra {
+        strip_extra(&mut entries);
+    }
 
     trace(cli, &format!("mode={mode} (list)"));
     timing(mode, start);
 
-    if json {
-        print_json(&entries);
+    if format.with_actions {
+        print_with_actions(&entries, format.json, format.output_format);
+    } else if format.json {
+        print_json(&entries_json(&entries, format.output_fields));
+    } else if format.nul {
+        print_ids_nul(&entries);
+    } else if format.csv {
+        print_csv(&entries);
     } else {
-        for e in &entries {
-            println!("{}\t{}", e.id, e.name.as_deref().unwrap_or(""));
-        }
+        print_entries(&entries, format.output_format);
     }
 
     0
 }
+
+/// `list --with-actions`: each entry, then one line per action in the same
+/// format, keyed `<id>:<action_id>`.
+fn print_with_actions(entries: &[DesktopEntryOut], json: bool, fmt: OutputFormat) {
+    if json || fmt == OutputFormat::Json {
+        #[derive(Serialize)]
+        struct EntryWithActions<'a> {
+            entry: &'a DesktopEntryOut,
+            actions: &'a [DesktopActionOut],
+        }
+
+        let out: Vec<EntryWithActions> = entries
+            .iter()
+            .map(|e| EntryWithActions {
+                entry: e,
+                actions: &e.actions,
+            })
+            .collect();
+        print_json(&out);
+        return;
+    }
+
+    for e in entries {
+        print_entries(std::slice::from_ref(e), fmt);
+        for a in &e.actions {
+            let name = a.name.as_deref().unwrap_or("");
+            match fmt {
+                OutputFormat::IdOnly => println!("{}:{}", e.id, a.id),
+                OutputFormat::NameOnly => {
+                    println!("{}", if name.is_empty() { &a.id } else { name })
+                }
+                OutputFormat::IdTabName | OutputFormat::Json => {
+                    println!("{}:{}\t{name}", e.id, a.id)
+                }
+            }
+        }
+    }
+}
+
+/// Apply the Hidden/NoDisplay/source/category/MIME filters the daemon would
+/// apply for `Request::List`.
+fn local_entries(
+    cli: &Cli,
+    entries: &[crate::models::DesktopEntryIndexed],
+    filter: &ListFilter,
+) -> Vec<DesktopEntryOut> {
+    let filter = EntryFilter {
+        category: filter.category,
+        show_hidden: cli.show_hidden,
+        show_nodisplay: cli.show_nodisplay,
+        source: filter.source,
+        mime_type: filter.mime_type,
+        ..EntryFilter::default()
+    };
+    entries
+        .iter()
+        .filter(|e| filter.accepts(&e.out))
+        .map(|e| e.out.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::parse_desktop_file_using_roots;
+
+    /// Parse `[Desktop Entry]` `keys` as `<id>.desktop`.
+    fn entry(id: &str, keys: &str) -> DesktopEntryOut {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{id}.desktop"));
+        std::fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\nExec={id}\n{keys}\n"),
+        )
+        .unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C"))
+            .unwrap()
+            .out
+    }
+
+    fn sorted(
+        entries: &[DesktopEntryOut],
+        keys: &[SortKey],
+        usage: &HashMap<String, Usage>,
+    ) -> Vec<String> {
+        let mut entries = entries.to_vec();
+        sort_entries(&mut entries, keys, usage);
+        entries.into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn sort_keys_order_entries_differently() {
+        let entries = [
+            entry("zim", "Name=Zim\nCategories=Office;"),
+            entry("atom", "Name=Atom\nCategories=Development;"),
+            entry("mpv", "Name=MPV\nCategories=AudioVideo;"),
+            entry("bare", "Name=Bare"),
+        ];
+        let usage: HashMap<String, Usage> = [("zim", 9, 100), ("mpv", 2, 900), ("bare", 2, 50)]
+            .into_iter()
+            .map(|(id, freq, last_used)| (id.to_string(), Usage { freq, last_used }))
+            .collect();
+
+        let by_name = sorted(&entries, &[SortKey::Name], &usage);
+        let by_freq = sorted(&entries, &[SortKey::Freq], &usage);
+        assert_eq!(by_name, ["atom", "bare", "mpv", "zim"]);
+        // Equal counts fall back to name order.
+        assert_eq!(by_freq, ["zim", "bare", "mpv", "atom"]);
+        assert_ne!(by_name, by_freq);
+
+        assert_eq!(sorted(&entries, &[], &usage), by_name);
+        assert_eq!(
+            sorted(&entries, &[SortKey::LastUsed], &usage),
+            ["mpv", "zim", "bare", "atom"]
+        );
+        assert_eq!(
+            sorted(&entries, &[SortKey::Freq, SortKey::LastUsed], &usage),
+            ["zim", "mpv", "bare", "atom"]
+        );
+        assert_eq!(
+            sorted(&entries, &[SortKey::Category], &usage),
+            ["mpv", "atom", "zim", "bare"]
+        );
+        assert_eq!(
+            sorted(&entries, &[SortKey::Id], &usage),
+            ["atom", "bare", "mpv", "zim"]
+        );
+    }
+}
@@ -1,8 +1,20 @@
+pub mod actions;
+pub mod benchmark;
+pub mod by_mime;
+pub mod cache;
 pub mod common;
 pub mod daemon;
+pub mod doctor;
+pub mod env;
+pub mod frequency;
+pub mod get;
 pub mod launch;
 pub mod list;
+pub mod open;
 pub mod parse;
+pub mod ps;
 pub mod scan;
 pub mod search;
 pub mod status;
+pub mod validate;
+pub mod write;
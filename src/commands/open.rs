@@ -0,0 +1,98 @@
+use crate::cli::Cli;
+use crate::daemon_client::{self, ClientError};
+use crate::desktop::scan_and_parse_desktop_files;
+use crate::frequency::FrequencyStore;
+use crate::ipc::{Request, Response};
+use crate::launch::LaunchEnv;
+use crate::mime::sniff_mime;
+use crate::search::{OPEN_HANDLER_FILTER, entries_for_mime};
+use std::path::Path;
+use std::process::Command;
+
+use super::common::{timing, trace};
+use super::launch::{launch_entry, working_dir};
+
+pub fn open(cli: &Cli, scan_roots: &[std::path::PathBuf], file: &Path) -> i32 {
+    let start = std::time::Instant::now();
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    // The daemon runs elsewhere, so the path is resolved here.
+    let file_path = std::path::absolute(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .to_string();
+
+    if cli.use_daemon_index()
+        && let Some(resp) = daemon_client::try_request(
+            &Request::Open {
+                roots,
+                file_path: file_path.clone(),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+                terminal: cli.config.preferred_terminal,
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
+    {
+        match resp {
+            Response::Ok => {
+                trace(cli, "mode=daemon (open)");
+                timing("daemon", start);
+                return 0;
+            }
+            Response::Error { message } => {
+                eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
+            }
+            _ => {}
+        }
+    }
+
+    trace(cli, "mode=local (open)");
+    timing("local", start);
+
+    let Some(mime_type) = sniff_mime(Path::new(&file_path)) else {
+        return xdg_open_exit(&file_path);
+    };
+
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    let freqs = FrequencyStore::load();
+    let Some(entry) = entries_for_mime(
+        &result.entries,
+        &mime_type,
+        freqs.map(),
+        &OPEN_HANDLER_FILTER,
+    )
+    .into_iter()
+    .next() else {
+        return xdg_open_exit(&file_path);
+    };
+
+    let launch_env = LaunchEnv {
+        cwd: working_dir(&entry, None),
+        vars: &[],
+    };
+    launch_entry(cli, &entry, None, &[file_path.as_str()], &launch_env)
+}
+
+/// Last resort when no indexed app handles the file.
+pub fn xdg_open(file_path: &str) -> Result<(), String> {
+    match Command::new("xdg-open").arg(file_path).status() {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(format!("no application found and xdg-open failed ({s})")),
+        Err(e) => Err(format!("no application found and xdg-open failed: {e}")),
+    }
+}
+
+fn xdg_open_exit(file_path: &str) -> i32 {
+    match xdg_open(file_path) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("desktop-indexer: {message}");
+            1
+        }
+    }
+}
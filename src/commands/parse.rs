@@ -1,13 +1,27 @@
-use crate::desktop::parse_desktop_file_using_roots;
-use crate::output::print_json;
+use crate::desktop::{parse_desktop_file_all_locales, parse_desktop_file_using_roots};
+use crate::output::{print_json, strip_extra};
+use std::collections::BTreeMap;
 use std::path::Path;
 
-pub fn parse(scan_roots: &[std::path::PathBuf], path: &Path, json: bool) -> i32 {
-    let Some(entry) = parse_desktop_file_using_roots(path, scan_roots) else {
-        eprintln!("Failed to parse {}", path.display());
-        return 1;
+pub fn parse(
+    scan_roots: &[std::path::PathBuf],
+    path: &Path,
+    locale: Option<&str>,
+    no_extra: bool,
+    json: bool,
+) -> i32 {
+    let mut entry = match parse_desktop_file_using_roots(path, scan_roots, locale) {
+        Ok(entry) => entry,
+        Err(e) => {
+            eprintln!("Failed to parse {}: {e}", path.display());
+            return 1;
+        }
     };
 
+    if no_extra {
+        strip_extra(std::slice::from_mut(&mut entry.out));
+    }
+
     if json {
         print_json(&entry.out);
     } else {
@@ -17,3 +31,36 @@ pub fn parse(scan_roots: &[std::path::PathBuf], path: &Path, json: bool) -> i32
 
     0
 }
+
+pub fn parse_all_locales(path: &Path, json: bool) -> i32 {
+    let Some(fields) = parse_desktop_file_all_locales(path) else {
+        eprintln!("Failed to parse {}", path.display());
+        return 1;
+    };
+
+    if json {
+        // JSON keys must be strings: the untranslated value goes under "".
+        let fields: BTreeMap<&str, BTreeMap<&str, &str>> = fields
+            .iter()
+            .map(|(key, values)| {
+                let values = values
+                    .iter()
+                    .map(|(locale, v)| (locale.as_deref().unwrap_or(""), v.as_str()))
+                    .collect();
+                (key.as_str(), values)
+            })
+            .collect();
+        print_json(&fields);
+    } else {
+        for (key, values) in &fields {
+            for (locale, v) in values {
+                match locale {
+                    Some(locale) => println!("{key}[{locale}]={v}"),
+                    None => println!("{key}={v}"),
+                }
+            }
+        }
+    }
+
+    0
+}
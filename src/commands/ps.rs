@@ -0,0 +1,51 @@
+use crate::cli::Cli;
+use crate::daemon_client::{self, ClientError};
+use crate::frequency::format_unix_ts;
+use crate::ipc::{Request, Response};
+use crate::launched::{LaunchedPid, running_launched};
+use crate::output::print_json;
+
+use super::common::{timing, trace};
+
+pub fn ps(cli: &Cli, json: bool) -> i32 {
+    let start = std::time::Instant::now();
+
+    let daemon_pids = if cli.no_daemon {
+        None
+    } else {
+        match daemon_client::try_request(&Request::ListPids, cli.daemon_timeouts())
+            .inspect_err(ClientError::log)
+            .ok()
+        {
+            Some(Response::LaunchedPids { pids }) => Some(pids),
+            _ => None,
+        }
+    };
+
+    let (mode, pids) = match daemon_pids {
+        Some(pids) => ("daemon", pids),
+        None => ("local", running_launched()),
+    };
+
+    trace(cli, &format!("mode={mode} (ps)"));
+    timing(mode, start);
+
+    print_pids(&pids, json);
+    0
+}
+
+fn print_pids(pids: &[LaunchedPid], json: bool) {
+    if json {
+        print_json(&pids);
+        return;
+    }
+
+    for p in pids {
+        println!(
+            "{}\t{}\t{}",
+            p.pid,
+            p.desktop_id,
+            format_unix_ts(p.launched_at)
+        );
+    }
+}
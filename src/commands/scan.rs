@@ -1,28 +1,80 @@
-use crate::desktop::{scan_and_parse_desktop_files, scan_desktop_files};
-use crate::models::DesktopEntryOut;
-use crate::output::print_json;
-
-pub fn scan(
-    scan_roots: &[std::path::PathBuf],
-    limit: Option<usize>,
-    parse: bool,
-    json: bool,
-    respect_try_exec: bool,
-) -> i32 {
-    if parse {
-        let result = scan_and_parse_desktop_files(scan_roots, limit, respect_try_exec);
-
-        if json {
+use crate::cli::Cli;
+use crate::desktop::{
+    is_desktop_file, parse_desktop_file_using_roots, scan_and_parse_desktop_files_with_progress,
+    scan_desktop_files,
+};
+use crate::models::{DesktopEntryOut, ScanOptions, ScanProgress};
+use crate::output::{
+    EntryJson, OutputField, OutputFormat, entries_json, print_csv, print_entries, print_json,
+    strip_extra,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+pub struct ScanArgs<'a> {
+    pub limit: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub parse: bool,
+    pub stats: bool,
+    pub verbose: bool,
+    pub output_fields: &'a [OutputField],
+    pub csv: bool,
+    pub json: bool,
+    /// Set only by an explicit `--output-format`; `None` keeps the summary view.
+    pub output_format: Option<OutputFormat>,
+    pub watch: bool,
+}
+
+pub fn scan(cli: &Cli, scan_roots: &[std::path::PathBuf], args: &ScanArgs) -> i32 {
+    let options = ScanOptions {
+        max_depth: args.max_depth,
+        ..cli.scan_options()
+    };
+
+    if args.parse {
+        // On stderr, so stdout stays parseable with --json/--csv.
+        let report = |p: ScanProgress| match p {
+            ScanProgress::Ok { id } => eprintln!("OK {id}"),
+            ScanProgress::Failed { path, reason } => eprintln!("FAIL {path}: {reason}"),
+        };
+        let progress: Option<&(dyn Fn(ScanProgress) + Sync)> =
+            if args.verbose { Some(&report) } else { None };
+        let mut result =
+            scan_and_parse_desktop_files_with_progress(scan_roots, args.limit, &options, progress);
+        if cli.no_extra {
+            for e in &mut result.entries {
+                strip_extra(std::slice::from_mut(&mut e.out));
+            }
+        }
+
+        if args.stats {
+            let entries: Vec<DesktopEntryOut> = result.entries.into_iter().map(|e| e.out).collect();
+            let stats = compute_stats(&entries);
+            if args.json {
+                print_json(&stats);
+            } else {
+                print_stats(&stats);
+            }
+        } else if let Some(fmt) = args.output_format.filter(|f| *f != OutputFormat::Json) {
+            let entries: Vec<DesktopEntryOut> = result.entries.into_iter().map(|e| e.out).collect();
+            print_entries(&entries, fmt);
+        } else if args.json {
             let entries: Vec<DesktopEntryOut> =
                 result.entries.iter().map(|e| e.out.clone()).collect();
 
             #[derive(serde::Serialize)]
-            struct ScanParseOut {
+            struct ScanParseOut<'a> {
                 scanned_roots: Vec<String>,
                 found_count: usize,
                 parsed_count: usize,
                 parse_failed: usize,
-                entries: Vec<DesktopEntryOut>,
+                parse_errors: Vec<String>,
+                entries: Vec<EntryJson<'a>>,
             }
 
             let out = ScanParseOut {
@@ -30,10 +82,14 @@ pub fn scan(
                 found_count: result.found_count,
                 parsed_count: result.parsed_count,
                 parse_failed: result.parse_failed,
-                entries,
+                parse_errors: result.parse_errors,
+                entries: entries_json(&entries, args.output_fields),
             };
 
             print_json(&out);
+        } else if args.csv {
+            let entries: Vec<DesktopEntryOut> = result.entries.into_iter().map(|e| e.out).collect();
+            print_csv(&entries);
         } else {
             println!("roots:");
             for r in &result.scanned_roots {
@@ -51,11 +107,15 @@ pub fn scan(
                 }
             }
         }
-        return 0;
+        return if args.watch {
+            watch(cli, scan_roots, &options, args)
+        } else {
+            0
+        };
     }
 
-    let result = scan_desktop_files(scan_roots, limit);
-    if json {
+    let result = scan_desktop_files(scan_roots, args.limit, &options);
+    if args.json {
         print_json(&result);
     } else {
         println!("roots:");
@@ -69,5 +129,412 @@ pub fn scan(
         }
     }
 
+    if args.watch {
+        return watch(cli, scan_roots, &options, args);
+    }
     0
 }
+
+/// Quiet period after an event before reporting, so an editor's burst of
+/// writes and renames becomes one line per file.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// `scan --watch`: report `.desktop` files added, changed or removed under
+/// the roots until interrupted (Ctrl-C). Changes are classified against the
+/// files seen so far rather than by event kind, which varies with the backend
+/// and with how editors save.
+fn watch(cli: &Cli, scan_roots: &[PathBuf], options: &ScanOptions, args: &ScanArgs) -> i32 {
+    // The watcher and the Ctrl-C handler send from their own threads.
+    let (tx, rx) = mpsc::channel();
+    let interrupt = tx.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        let _ = interrupt.send(WatchMessage::Interrupt);
+    }) {
+        eprintln!("desktop-indexer: cannot handle Ctrl-C: {e}");
+    }
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(WatchMessage::Event(event));
+    }) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("desktop-indexer: cannot watch: {e}");
+            return 1;
+        }
+    };
+    for root in scan_roots.iter().filter(|r| r.is_dir()) {
+        if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+            eprintln!("desktop-indexer: cannot watch {}: {e}", root.display());
+        }
+    }
+
+    let mut known: BTreeSet<PathBuf> = scan_desktop_files(scan_roots, None, options)
+        .files
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+    let mut interrupted = false;
+    while !interrupted {
+        let Ok(WatchMessage::Event(event)) = rx.recv() else {
+            break;
+        };
+        let mut touched: BTreeSet<PathBuf> = BTreeSet::new();
+        add_event_paths(event, &mut touched);
+        while let Ok(message) = rx.recv_timeout(WATCH_DEBOUNCE) {
+            match message {
+                WatchMessage::Event(event) => add_event_paths(event, &mut touched),
+                // Report what was already collected, then stop.
+                WatchMessage::Interrupt => {
+                    interrupted = true;
+                    break;
+                }
+            }
+        }
+
+        for path in desktop_paths(touched, &known, scan_roots, options) {
+            let change = match (path.is_file(), known.contains(&path)) {
+                (true, false) => WatchChange::Add,
+                (true, true) => WatchChange::Change,
+                (false, true) => WatchChange::Remove,
+                (false, false) => continue,
+            };
+            if change == WatchChange::Remove {
+                known.remove(&path);
+            } else {
+                known.insert(path.clone());
+            }
+            print_change(cli, scan_roots, options, args, change, &path);
+        }
+    }
+
+    drop(watcher);
+    let _ = std::io::stdout().flush();
+    0
+}
+
+enum WatchMessage {
+    Event(notify::Result<notify::Event>),
+    /// SIGINT (Ctrl-C).
+    Interrupt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchChange {
+    Add,
+    Change,
+    Remove,
+}
+
+impl WatchChange {
+    fn as_str(self) -> &'static str {
+        match self {
+            WatchChange::Add => "ADD",
+            WatchChange::Change => "CHANGE",
+            WatchChange::Remove => "REMOVE",
+        }
+    }
+}
+
+fn add_event_paths(event: notify::Result<notify::Event>, touched: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => touched.extend(event.paths),
+        Ok(_) => {}
+        Err(e) => eprintln!("desktop-indexer: watch error: {e}"),
+    }
+}
+
+/// The `.desktop` files behind `touched`. A directory stands for the new files
+/// under it (created or moved in) and the known ones that are gone (removed or
+/// moved out).
+fn desktop_paths(
+    touched: BTreeSet<PathBuf>,
+    known: &BTreeSet<PathBuf>,
+    scan_roots: &[PathBuf],
+    options: &ScanOptions,
+) -> BTreeSet<PathBuf> {
+    let mut out = BTreeSet::new();
+    for path in touched {
+        if is_desktop_file(&path) {
+            if within_depth(&path, scan_roots, options.max_depth) {
+                out.insert(path);
+            }
+            continue;
+        }
+        out.extend(
+            known
+                .iter()
+                .filter(|k| k.starts_with(&path) && !k.is_file())
+                .cloned(),
+        );
+        if path.is_dir() {
+            out.extend(
+                WalkDir::new(&path)
+                    .follow_links(options.follow_symlinks)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.into_path())
+                    .filter(|p| {
+                        is_desktop_file(p)
+                            && p.is_file()
+                            && !known.contains(p)
+                            && within_depth(p, scan_roots, options.max_depth)
+                    }),
+            );
+        }
+    }
+    out
+}
+
+/// Whether `path` is no deeper below its root than `--max-depth` allows.
+fn within_depth(path: &Path, scan_roots: &[PathBuf], max_depth: Option<usize>) -> bool {
+    let Some(max_depth) = max_depth else {
+        return true;
+    };
+    scan_roots.iter().any(|root| {
+        path.strip_prefix(root)
+            .is_ok_and(|rel| rel.components().count() <= max_depth)
+    })
+}
+
+fn print_change(
+    cli: &Cli,
+    scan_roots: &[PathBuf],
+    options: &ScanOptions,
+    args: &ScanArgs,
+    change: WatchChange,
+    path: &Path,
+) {
+    let path_str = path.to_string_lossy();
+    let entry = (args.parse && change != WatchChange::Remove).then(|| {
+        parse_desktop_file_using_roots(path, scan_roots, options.locale.as_deref()).map(|e| {
+            let mut out = e.out;
+            if cli.no_extra {
+                strip_extra(std::slice::from_mut(&mut out));
+            }
+            out
+        })
+    });
+
+    if args.json {
+        #[derive(serde::Serialize)]
+        struct WatchLine<'a> {
+            event: &'static str,
+            path: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            entry: Option<EntryJson<'a>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            error: Option<String>,
+        }
+
+        let (entry, error) = match &entry {
+            Some(Ok(e)) => (
+                entries_json(std::slice::from_ref(e), args.output_fields).pop(),
+                None,
+            ),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+        let line = WatchLine {
+            event: change.as_str(),
+            path: &path_str,
+            entry,
+            error,
+        };
+        if let Ok(s) = serde_json::to_string(&line) {
+            println!("{s}");
+        }
+        return;
+    }
+
+    match entry {
+        Some(Ok(e)) => match serde_json::to_string(&e) {
+            Ok(s) => println!("{} {path_str}\t{s}", change.as_str()),
+            Err(_) => println!("{} {path_str}", change.as_str()),
+        },
+        Some(Err(e)) => {
+            println!("{} {path_str}", change.as_str());
+            eprintln!("desktop-indexer: {path_str}: {e}");
+        }
+        None => println!("{} {path_str}", change.as_str()),
+    }
+}
+
+/// How many entries the `--stats` tables show.
+const STATS_TOP: usize = 20;
+
+#[derive(Debug, serde::Serialize)]
+pub struct NameCount {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ScanStats {
+    pub entry_count: usize,
+    /// Most common categories, at most `STATS_TOP`, most frequent first.
+    pub top_categories: Vec<NameCount>,
+    /// Most common MIME types, at most `STATS_TOP`, most frequent first.
+    pub top_mime_types: Vec<NameCount>,
+    pub terminal_count: usize,
+    pub nodisplay_count: usize,
+    pub missing_exec_count: usize,
+}
+
+pub fn compute_stats(entries: &[DesktopEntryOut]) -> ScanStats {
+    let mut categories: HashMap<&str, usize> = HashMap::new();
+    let mut mime_types: HashMap<&str, usize> = HashMap::new();
+
+    for e in entries {
+        for c in &e.categories {
+            *categories.entry(c).or_default() += 1;
+        }
+        for m in &e.mime_types {
+            *mime_types.entry(m).or_default() += 1;
+        }
+    }
+
+    ScanStats {
+        entry_count: entries.len(),
+        top_categories: top_counts(categories),
+        top_mime_types: top_counts(mime_types),
+        terminal_count: entries.iter().filter(|e| e.terminal).count(),
+        nodisplay_count: entries.iter().filter(|e| e.nodisplay == Some(true)).count(),
+        missing_exec_count: entries
+            .iter()
+            .filter(|e| e.exec.as_deref().is_none_or(|x| x.trim().is_empty()))
+            .count(),
+    }
+}
+
+/// Highest counts first, ties by name, cut to `STATS_TOP`.
+fn top_counts(counts: HashMap<&str, usize>) -> Vec<NameCount> {
+    let mut out: Vec<NameCount> = counts
+        .into_iter()
+        .map(|(name, count)| NameCount {
+            name: name.to_string(),
+            count,
+        })
+        .collect();
+    out.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    out.truncate(STATS_TOP);
+    out
+}
+
+fn print_stats(stats: &ScanStats) {
+    println!("entries={}", stats.entry_count);
+    println!("terminal={}", stats.terminal_count);
+    println!("nodisplay={}", stats.nodisplay_count);
+    println!("missing_exec={}", stats.missing_exec_count);
+
+    println!("top categories:");
+    for c in &stats.top_categories {
+        println!("  {}\t{}", c.count, c.name);
+    }
+    println!("top mime types:");
+    for m in &stats.top_mime_types {
+        println!("  {}\t{}", m.count, m.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `[Desktop Entry]` `keys` as `<id>.desktop`.
+    fn entry(id: &str, keys: &str) -> DesktopEntryOut {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{id}.desktop"));
+        std::fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\nName={id}\n{keys}\n"),
+        )
+        .unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C"))
+            .unwrap()
+            .out
+    }
+
+    fn counts(top: &[NameCount]) -> Vec<(&str, usize)> {
+        top.iter().map(|c| (c.name.as_str(), c.count)).collect()
+    }
+
+    #[test]
+    fn stats_over_ten_entries() {
+        let entries = [
+            entry(
+                "firefox",
+                "Exec=firefox %u\nCategories=Network;WebBrowser;\nMimeType=text/html;x-scheme-handler/https;",
+            ),
+            entry(
+                "chromium",
+                "Exec=chromium %U\nCategories=Network;WebBrowser;\nMimeType=text/html;",
+            ),
+            entry(
+                "mail",
+                "Exec=mail %u\nCategories=Network;Email;\nMimeType=x-scheme-handler/mailto;",
+            ),
+            entry(
+                "editor",
+                "Exec=editor %F\nCategories=Utility;TextEditor;\nMimeType=text/plain;text/html;",
+            ),
+            entry(
+                "htop",
+                "Exec=htop\nTerminal=true\nCategories=System;Monitor;",
+            ),
+            entry(
+                "vim",
+                "Exec=vim %F\nTerminal=true\nCategories=Utility;TextEditor;\nMimeType=text/plain;",
+            ),
+            entry(
+                "mpv",
+                "Exec=mpv %U\nCategories=AudioVideo;Player;\nMimeType=video/mp4;",
+            ),
+            entry(
+                "handler",
+                "Exec=handler %u\nNoDisplay=true\nMimeType=x-scheme-handler/https;",
+            ),
+            entry("portal", "DBusActivatable=true\nCategories=System;"),
+            entry("notes", "Exec=notes"),
+        ];
+        let stats = compute_stats(&entries);
+
+        assert_eq!(stats.entry_count, 10);
+        assert_eq!(stats.terminal_count, 2);
+        assert_eq!(stats.nodisplay_count, 1);
+        assert_eq!(stats.missing_exec_count, 1);
+        // Most frequent first, ties by name.
+        assert_eq!(
+            counts(&stats.top_categories),
+            [
+                ("Network", 3),
+                ("System", 2),
+                ("TextEditor", 2),
+                ("Utility", 2),
+                ("WebBrowser", 2),
+                ("AudioVideo", 1),
+                ("Email", 1),
+                ("Monitor", 1),
+                ("Player", 1),
+            ]
+        );
+        assert_eq!(
+            counts(&stats.top_mime_types),
+            [
+                ("text/html", 3),
+                ("text/plain", 2),
+                ("x-scheme-handler/https", 2),
+                ("video/mp4", 1),
+                ("x-scheme-handler/mailto", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_tables_keep_the_top_twenty() {
+        let mimes: String = (0..25).map(|i| format!("type/x{i:02};")).collect();
+        let stats = compute_stats(&[entry("viewer", &format!("Exec=viewer\nMimeType={mimes}"))]);
+        assert_eq!(stats.top_mime_types.len(), STATS_TOP);
+        assert_eq!(stats.top_mime_types[0].name, "type/x00");
+        assert_eq!(stats.top_mime_types[19].name, "type/x19");
+    }
+}
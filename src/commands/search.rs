@@ -1,86 +1,311 @@
 use crate::cli::Cli;
-use crate::daemon_client;
+use crate::daemon_client::{self, ClientError};
 use crate::desktop::scan_and_parse_desktop_files;
 use crate::empty_query::EmptyQueryMode;
-use crate::frequency::FrequencyStore;
+use crate::frequency::{FrequencyStore, Usage, unix_seconds_now};
 use crate::ipc::{Request, Response};
-use crate::models::DesktopEntryOut;
-use crate::output::print_json;
-use crate::search::search_entries_with_usage_map_and_empty_mode;
+use crate::models::{DesktopEntryIndexed, DesktopEntryOut};
+use crate::output::{
+    OutputField, OutputFormat, entries_json, group_by_category, print_csv, print_entries,
+    print_ids_nul, print_json, strip_extra,
+};
+use crate::scan_sources::EntrySource;
+use crate::search::{
+    DEFAULT_SEARCH_LIMIT, EntryFilter, ScoreBreakdown, Scoring, normalize_query, score_breakdown,
+    search_entries_with_usage_map_and_empty_mode,
+};
+use std::collections::HashMap;
 
 use super::common::{timing, trace};
 
-pub fn search(
-    cli: &Cli,
-    scan_roots: &[std::path::PathBuf],
-    query: &str,
-    limit: Option<usize>,
-    empty_mode: EmptyQueryMode,
-    json: bool,
-) -> i32 {
+pub struct SearchArgs<'a> {
+    pub query: &'a str,
+    pub limit: Option<usize>,
+    pub empty_mode: EmptyQueryMode,
+    pub scoring: Scoring<'a>,
+    pub category: Option<&'a str>,
+    pub source: Option<EntrySource>,
+    pub exact_name: Option<&'a str>,
+    pub type_filter: Option<&'a str>,
+    pub modified_since: Option<u64>,
+    pub used_since: Option<u64>,
+    pub dedup_by_name: bool,
+    pub explain: bool,
+    pub group_by_category: bool,
+    pub output_fields: &'a [OutputField],
+    pub nul: bool,
+    pub csv: bool,
+    pub json: bool,
+    pub output_format: OutputFormat,
+}
+
+/// Filters echoed back in JSON output so callers know what was applied.
+#[derive(serde::Serialize)]
+struct SearchFilters<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact_name: Option<&'a str>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    type_filter: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified_since: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    used_since: Option<u64>,
+}
+
+impl SearchFilters<'_> {
+    fn is_empty(&self) -> bool {
+        self.category.is_none()
+            && self.exact_name.is_none()
+            && self.type_filter.is_none()
+            && self.modified_since.is_none()
+            && self.used_since.is_none()
+    }
+}
+
+pub fn search(cli: &Cli, scan_roots: &[std::path::PathBuf], args: &SearchArgs) -> i32 {
+    if args.explain {
+        return explain(cli, scan_roots, args);
+    }
+
     let start = std::time::Instant::now();
     let roots: Vec<String> = scan_roots
         .iter()
         .map(|p| p.to_string_lossy().to_string())
         .collect();
+    // Deduplicating drops rows, so fetch every match and cut afterwards.
+    let fetch_limit = if args.dedup_by_name {
+        Some(usize::MAX)
+    } else {
+        args.limit
+    };
 
-    let daemon_resp = if cli.no_daemon {
+    let daemon_resp = if !cli.use_daemon_index() {
         None
     } else {
-        daemon_client::try_request(&Request::Search {
-            roots: roots.clone(),
-            query: query.to_string(),
-            limit,
-            empty_mode: Some(empty_mode),
-            respect_try_exec: cli.respect_try_exec,
-        })
+        daemon_client::try_request(
+            &Request::Search {
+                roots: roots.clone(),
+                query: args.query.to_string(),
+                limit: fetch_limit,
+                empty_mode: Some(args.empty_mode),
+                respect_try_exec: cli.respect_try_exec,
+                de_filter: cli.de_filter,
+                fuzzy: args.scoring.fuzzy.is_some(),
+                fuzzy_penalty: args.scoring.fuzzy.map(|f| f.penalty),
+                fuzzy_distance: args.scoring.fuzzy.map(|f| f.max_distance as u8),
+                weights: Some(args.scoring.weights),
+                pinned: args.scoring.pins.to_vec(),
+                category: args.category.map(|s| s.to_string()),
+                source: args.source,
+                exact_name: args.exact_name.map(|s| s.to_string()),
+                type_filter: args.type_filter.map(|s| s.to_string()),
+                modified_since: args.modified_since,
+                used_since: args.used_since,
+                show_hidden: cli.show_hidden,
+                show_nodisplay: cli.show_nodisplay,
+                exclude: crate::search::exclude_tokens(args.query),
+                min_score: args.scoring.min_score,
+            },
+            cli.daemon_timeouts(),
+        )
+        .inspect_err(ClientError::log)
+        .ok()
     };
 
-    let (mode, matches): (&str, Vec<DesktopEntryOut>) = if let Some(resp) = daemon_resp {
+    let (mode, mut matches): (&str, Vec<DesktopEntryOut>) = if let Some(resp) = daemon_resp {
         match resp {
             Response::Entries { entries } => ("daemon", entries),
             Response::Error { message } => {
                 eprintln!("desktop-indexer: daemon error: {message} (fallback local)");
-                local_search(scan_roots, query, limit, empty_mode, cli.respect_try_exec)
+                local_search(cli, scan_roots, args, fetch_limit)
             }
-            _ => local_search(scan_roots, query, limit, empty_mode, cli.respect_try_exec),
+            _ => local_search(cli, scan_roots, args, fetch_limit),
         }
     } else {
-        local_search(scan_roots, query, limit, empty_mode, cli.respect_try_exec)
+        local_search(cli, scan_roots, args, fetch_limit)
     };
 
+    if args.dedup_by_name {
+        crate::search::dedup_by_name(&mut matches, &roots);
+        matches.truncate(args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+    }
+
+    if cli.no_extra {
+        strip_extra(&mut matches);
+    }
+
     trace(cli, &format!("mode={mode} (search)"));
     timing(mode, start);
 
-    if json {
-        print_json(&matches);
-    } else {
-        for e in &matches {
-            println!("{}\t{}", e.id, e.name.as_deref().unwrap_or(""));
+    if args.json {
+        let filters = SearchFilters {
+            category: args.category,
+            exact_name: args.exact_name,
+            type_filter: args.type_filter,
+            modified_since: args.modified_since,
+            used_since: args.used_since,
+        };
+
+        if args.group_by_category {
+            let groups = group_by_category(matches);
+            let groups: std::collections::BTreeMap<&str, _> = groups
+                .iter()
+                .map(|(c, es)| (c.as_str(), entries_json(es, args.output_fields)))
+                .collect();
+            print_search_json(filters, groups);
+        } else {
+            print_search_json(filters, entries_json(&matches, args.output_fields));
         }
+    } else if args.nul {
+        print_ids_nul(&matches);
+    } else if args.csv {
+        print_csv(&matches);
+    } else {
+        print_entries(&matches, args.output_format);
     }
 
     0
 }
 
+/// Keep the plain `entries` shape unless a filter needs to be reported.
+fn print_search_json<T: serde::Serialize>(filters: SearchFilters, entries: T) {
+    if filters.is_empty() {
+        print_json(&entries);
+    } else {
+        #[derive(serde::Serialize)]
+        struct SearchOut<'a, T> {
+            filters: SearchFilters<'a>,
+            entries: T,
+        }
+
+        print_json(&SearchOut { filters, entries });
+    }
+}
+
 fn local_search(
+    cli: &Cli,
     scan_roots: &[std::path::PathBuf],
-    query: &str,
+    args: &SearchArgs,
     limit: Option<usize>,
-    empty_mode: EmptyQueryMode,
-    respect_try_exec: bool,
 ) -> (&'static str, Vec<DesktopEntryOut>) {
-    let result = scan_and_parse_desktop_files(scan_roots, None, respect_try_exec);
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
     let freqs = FrequencyStore::load();
-    let lim = limit.unwrap_or(20);
     (
         "local",
-        search_entries_with_usage_map_and_empty_mode(
-            &result.entries,
-            query,
-            lim,
-            freqs.map(),
-            empty_mode,
-        ),
+        search_indexed(cli, &result.entries, args, limit, freqs.map()),
     )
 }
+
+fn search_indexed(
+    cli: &Cli,
+    entries: &[DesktopEntryIndexed],
+    args: &SearchArgs,
+    limit: Option<usize>,
+    usage: &HashMap<String, Usage>,
+) -> Vec<DesktopEntryOut> {
+    let lim = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let exclude = crate::search::exclude_tokens(args.query);
+    let filter = EntryFilter {
+        category: args.category,
+        show_hidden: cli.show_hidden,
+        show_nodisplay: cli.show_nodisplay,
+        exclude: &exclude,
+        source: args.source,
+        exact_name: args.exact_name,
+        type_filter: args.type_filter,
+        modified_since: args.modified_since,
+        used_since: args.used_since,
+        ..EntryFilter::default()
+    };
+    search_entries_with_usage_map_and_empty_mode(
+        entries,
+        args.query,
+        lim,
+        usage,
+        args.empty_mode,
+        &args.scoring,
+        &filter,
+    )
+}
+
+/// One `--explain` row: the result plus how its score adds up.
+#[derive(serde::Serialize)]
+struct Explained<'a> {
+    id: &'a str,
+    /// `Name=`, or `GenericName=` when it is empty (what `name_match` scored).
+    name: Option<&'a str>,
+    score: i32,
+    #[serde(flatten)]
+    breakdown: ScoreBreakdown,
+}
+
+/// `search --explain`: run the search locally, then score each result again,
+/// keeping the parts. The daemon only returns entries, so it is never used.
+fn explain(cli: &Cli, scan_roots: &[std::path::PathBuf], args: &SearchArgs) -> i32 {
+    let start = std::time::Instant::now();
+    let roots: Vec<String> = scan_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let fetch_limit = if args.dedup_by_name {
+        Some(usize::MAX)
+    } else {
+        args.limit
+    };
+
+    let result = scan_and_parse_desktop_files(scan_roots, None, &cli.scan_options());
+    let freqs = FrequencyStore::load();
+    let mut matches = search_indexed(cli, &result.entries, args, fetch_limit, freqs.map());
+    if args.dedup_by_name {
+        crate::search::dedup_by_name(&mut matches, &roots);
+        matches.truncate(args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+    }
+
+    let by_id: HashMap<&str, &DesktopEntryIndexed> = result
+        .entries
+        .iter()
+        .map(|e| (e.out.id.as_str(), e))
+        .collect();
+    let tokens = normalize_query(args.query);
+    let now_sec = unix_seconds_now();
+    let rows: Vec<Explained> = matches
+        .iter()
+        .filter_map(|m| by_id.get(m.id.as_str()))
+        .map(|e| {
+            let usage = freqs.map().get(&e.out.id).copied().unwrap_or_default();
+            let breakdown = score_breakdown(e, &tokens, usage, now_sec, &args.scoring);
+            Explained {
+                id: &e.out.id,
+                name: e.out.name_or_generic_name(),
+                score: breakdown.total(),
+                breakdown,
+            }
+        })
+        .collect();
+
+    trace(cli, "mode=local (search --explain)");
+    timing("local", start);
+
+    if args.json {
+        print_json(&rows);
+    } else {
+        for r in &rows {
+            let b = &r.breakdown;
+            println!("{}\t{}", r.id, r.name.unwrap_or(""));
+            println!("  score: {}", r.score);
+            println!("  relevance: {}", b.relevance);
+            println!("  name_match: {}", b.name_match);
+            println!("  id_match: {}", b.id_match);
+            println!("  fuzzy_penalty: {}", b.fuzzy_penalty);
+            println!("  all_tokens_in_name_bonus: {}", b.all_tokens_in_name_bonus);
+            println!("  short_name_bonus: {}", b.short_name_bonus);
+            println!("  freq_bonus: {}", b.freq_bonus);
+            println!("  recency_bonus: {}", b.recency_bonus);
+            println!("  base: {}", b.base);
+        }
+    }
+
+    0
+}
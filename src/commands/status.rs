@@ -1,26 +1,45 @@
 use crate::cli::Cli;
-use crate::daemon_client;
-use crate::ipc::{Request, Response};
+use crate::daemon_client::ClientError;
+use crate::frequency::{format_unix_ts, unix_seconds_now};
+use crate::ipc::{IndexInfo, Request, Response};
 use crate::output::print_json;
 use crate::xdg;
+use crate::{daemon, daemon_client};
 
 use super::common::{timing, trace};
 
-pub fn status(cli: &Cli, json: bool) -> i32 {
+pub fn status(cli: &Cli, verbose: bool, json: bool) -> i32 {
     let start = std::time::Instant::now();
     let socket = xdg::socket_path().to_string_lossy().to_string();
 
     let resp = if cli.no_daemon {
         None
+    } else if verbose {
+        daemon_client::try_request(&Request::StatusVerbose, cli.daemon_timeouts())
+            .inspect_err(ClientError::log)
+            .ok()
     } else {
-        daemon_client::try_request(&Request::Status)
+        daemon_client::try_request(&Request::Status, cli.daemon_timeouts())
+            .inspect_err(ClientError::log)
+            .ok()
     };
 
+    let stale_after = cli
+        .config
+        .stale_after_secs
+        .unwrap_or(DEFAULT_STALE_AFTER_SECS);
+    let now = unix_seconds_now();
+
     #[derive(serde::Serialize)]
     struct StatusOut {
         daemon: bool,
         has_index_count: Option<usize>,
+        pid: Option<u32>,
         socket: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        indexes: Option<Vec<IndexOut>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pending: Option<usize>,
     }
 
     let (mode, out) = match resp {
@@ -29,7 +48,26 @@ pub fn status(cli: &Cli, json: bool) -> i32 {
             StatusOut {
                 daemon: true,
                 has_index_count: Some(has_index_count),
+                pid: daemon::running_pid(),
+                socket,
+                indexes: None,
+                pending: None,
+            },
+        ),
+        Some(Response::StatusVerbose { indexes, pending }) => (
+            "daemon",
+            StatusOut {
+                daemon: true,
+                has_index_count: Some(indexes.len()),
+                pid: daemon::running_pid(),
                 socket,
+                indexes: Some(
+                    indexes
+                        .into_iter()
+                        .map(|info| IndexOut::new(info, now, stale_after))
+                        .collect(),
+                ),
+                pending: Some(pending),
             },
         ),
         _ => (
@@ -37,7 +75,10 @@ pub fn status(cli: &Cli, json: bool) -> i32 {
             StatusOut {
                 daemon: false,
                 has_index_count: None,
+                pid: None,
                 socket,
+                indexes: None,
+                pending: None,
             },
         ),
     };
@@ -52,7 +93,16 @@ pub fn status(cli: &Cli, json: bool) -> i32 {
             "daemon running (indexes={})",
             out.has_index_count.unwrap_or(0)
         );
+        if let Some(pid) = out.pid {
+            println!("pid={pid}");
+        }
         println!("socket={}", out.socket);
+        if let Some(pending) = out.pending {
+            println!("pending={pending}");
+        }
+        if let Some(indexes) = &out.indexes {
+            print_index_table(indexes);
+        }
     } else {
         println!("daemon not running");
         println!("socket={}", out.socket);
@@ -60,3 +110,77 @@ pub fn status(cli: &Cli, json: bool) -> i32 {
 
     0
 }
+
+/// Indexes older than this are reported as STALE (config `stale_after_secs`).
+const DEFAULT_STALE_AFTER_SECS: u64 = 3600;
+
+/// `IndexInfo` plus its age as seen by the client.
+#[derive(serde::Serialize)]
+struct IndexOut {
+    #[serde(flatten)]
+    info: IndexInfo,
+    age_sec: u64,
+    stale: bool,
+}
+
+impl IndexOut {
+    fn new(info: IndexInfo, now: u64, stale_after: u64) -> Self {
+        let age_sec = now.saturating_sub(info.last_built_sec);
+        Self {
+            info,
+            age_sec,
+            stale: age_sec > stale_after,
+        }
+    }
+}
+
+fn print_index_table(indexes: &[IndexOut]) {
+    println!("entries\tbuilt\tage_sec\tstate\ttry_exec\tde_filter\troots");
+    for i in indexes {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            i.info.entry_count,
+            format_unix_ts(i.info.last_built_sec),
+            i.age_sec,
+            if i.stale { "STALE" } else { "fresh" },
+            i.info.respect_try_exec,
+            i.info.de_filter,
+            i.info.roots.join(":")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built_at(last_built_sec: u64) -> IndexInfo {
+        IndexInfo {
+            roots: vec!["/usr/share/applications".to_string()],
+            respect_try_exec: false,
+            de_filter: false,
+            entry_count: 3,
+            last_built_sec,
+        }
+    }
+
+    #[test]
+    fn index_goes_stale_once_older_than_the_threshold() {
+        let built = 1_700_000_000;
+        for (now, age, stale) in [
+            (built, 0, false),
+            (built + 3600, 3600, false),
+            (built + 3601, 3601, true),
+        ] {
+            let out = IndexOut::new(built_at(built), now, 3600);
+            assert_eq!((out.age_sec, out.stale), (age, stale), "now={now}");
+        }
+    }
+
+    #[test]
+    fn clock_behind_build_time_is_fresh() {
+        let out = IndexOut::new(built_at(1_700_000_000), 1_699_999_000, 60);
+        assert_eq!(out.age_sec, 0);
+        assert!(!out.stale);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::output::print_json;
+use crate::validate::{Level, validate_desktop};
+use std::path::Path;
+
+pub fn validate(path: &Path, strict: bool, json: bool) -> i32 {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("desktop-indexer: failed to read {}: {e}", path.display());
+            return 1;
+        }
+    };
+
+    let issues = validate_desktop(&data);
+    let has_errors = issues.iter().any(|i| i.level == Level::Error);
+
+    if json {
+        #[derive(serde::Serialize)]
+        struct ValidateOut<'a> {
+            path: &'a Path,
+            valid: bool,
+            issues: &'a [crate::validate::Issue],
+        }
+
+        print_json(&ValidateOut {
+            path,
+            valid: !has_errors,
+            issues: &issues,
+        });
+    } else if issues.is_empty() {
+        println!("{}: ok", path.display());
+    } else {
+        for i in &issues {
+            match i.line {
+                Some(line) => println!(
+                    "{}:{line}: {}: {}",
+                    path.display(),
+                    i.level.label(),
+                    i.message
+                ),
+                None => println!("{}: {}: {}", path.display(), i.level.label(), i.message),
+            }
+        }
+    }
+
+    if strict && has_errors { 1 } else { 0 }
+}
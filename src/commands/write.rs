@@ -0,0 +1,31 @@
+use crate::desktop::{WriteOpts, write_desktop_entry};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn write(opts: &WriteOpts, output: Option<&Path>) -> i32 {
+    if opts.exec.trim().is_empty() {
+        eprintln!("desktop-indexer: --exec must not be empty");
+        return 1;
+    }
+
+    let data = write_desktop_entry(opts);
+
+    let Some(output) = output else {
+        print!("{data}");
+        return 0;
+    };
+
+    // Temp file + rename so a reader never sees a half-written entry.
+    let mut tmp: OsString = output.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    if let Err(e) = fs::write(&tmp, data).and_then(|()| fs::rename(&tmp, output)) {
+        let _ = fs::remove_file(&tmp);
+        eprintln!("desktop-indexer: failed to write {}: {e}", output.display());
+        return 1;
+    }
+
+    0
+}
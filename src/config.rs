@@ -0,0 +1,324 @@
+use crate::empty_query::EmptyQueryMode;
+use crate::launch::Terminal;
+use crate::search::SearchWeights;
+use clap::{Command, ValueEnum};
+use serde::Deserialize;
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Persistent CLI defaults loaded from `config.toml`.
+///
+/// Keys mirror the long flag names (with `_` instead of `-`). Values only act as
+/// defaults: anything passed on the command line wins.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Extra scan roots, like repeated `-p/--path`.
+    pub paths: Vec<PathBuf>,
+    pub trace: Option<bool>,
+    pub no_daemon: Option<bool>,
+    pub respect_try_exec: Option<bool>,
+    pub de_filter: Option<bool>,
+    pub parallel: Option<bool>,
+    pub follow_symlinks: Option<bool>,
+    pub no_cache: Option<bool>,
+    pub daemon_timeout_ms: Option<u64>,
+    pub show_hidden: Option<bool>,
+    pub show_nodisplay: Option<bool>,
+    /// Desktop ids hidden everywhere (search, list, get, launch, ...).
+    pub ignore: Vec<String>,
+    /// Directory depth limit for every scan, like `scan --max-depth`.
+    pub max_depth: Option<usize>,
+
+    // `search` defaults
+    /// Max results when `--limit` is omitted (also the daemon's default).
+    pub default_limit: Option<usize>,
+    pub empty_mode: Option<EmptyQueryMode>,
+    pub fuzzy: Option<bool>,
+    pub fuzzy_penalty: Option<i32>,
+    pub fuzzy_distance: Option<u8>,
+    pub category: Option<String>,
+    /// `[weights]` table; missing keys keep the built-in values.
+    pub weights: SearchWeights,
+    /// Desktop ids always listed first in search results, in this order.
+    pub pinned: Vec<String>,
+
+    // daemon settings
+    /// Queued connections beyond this are answered with a "busy" error.
+    pub max_pending: Option<usize>,
+    /// `daemon status --verbose` flags indexes older than this as STALE.
+    pub stale_after_secs: Option<u64>,
+    /// Build the index for the default roots as soon as the daemon starts.
+    pub warmup_at_start: Option<bool>,
+
+    // `launch` settings
+    /// Terminal for Terminal=true apps, tried before auto-detection.
+    pub preferred_terminal: Option<Terminal>,
+}
+
+impl Config {
+    /// Load `path`. A missing file is only an error when `required` is set
+    /// (i.e. the user pointed `--config` at it explicitly).
+    pub fn load(path: &Path, required: bool) -> Result<Self, String> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+
+        toml::from_str(&data).map_err(|e| format!("invalid config {}: {e}", path.display()))
+    }
+
+    /// Install config values as clap defaults so explicit flags still override them.
+    pub fn apply_defaults(&self, cmd: Command) -> Command {
+        let mut cmd = cmd;
+
+        if !self.paths.is_empty() {
+            let paths: Vec<OsString> = self.paths.iter().map(|p| p.clone().into()).collect();
+            cmd = cmd.mut_arg("paths", |a| a.default_values(paths));
+        }
+
+        for (id, value) in [
+            ("trace", self.trace),
+            ("no_daemon", self.no_daemon),
+            ("respect_try_exec", self.respect_try_exec),
+            ("de_filter", self.de_filter),
+            ("parallel", self.parallel),
+            ("follow_symlinks", self.follow_symlinks),
+            ("no_cache", self.no_cache),
+            ("show_hidden", self.show_hidden),
+            ("show_nodisplay", self.show_nodisplay),
+        ] {
+            if let Some(v) = value {
+                cmd = cmd.mut_arg(id, |a| a.default_value(bool_str(v)));
+            }
+        }
+
+        if let Some(ms) = self.daemon_timeout_ms {
+            cmd = cmd.mut_arg("daemon_timeout_ms", |a| a.default_value(ms.to_string()));
+        }
+
+        if let Some(depth) = self.max_depth {
+            cmd = cmd.mut_subcommand("scan", |sc| {
+                sc.mut_arg("max_depth", |a| a.default_value(depth.to_string()))
+            });
+        }
+
+        cmd.mut_subcommand("search", |sc| {
+            let mut sc = sc;
+            if let Some(v) = self.default_limit {
+                sc = sc.mut_arg("limit", |a| a.default_value(v.to_string()));
+            }
+            if let Some(mode) = self.empty_mode.and_then(|m| m.to_possible_value()) {
+                sc = sc.mut_arg("empty_mode", |a| {
+                    a.default_value(mode.get_name().to_string())
+                });
+            }
+            if let Some(v) = self.fuzzy {
+                sc = sc.mut_arg("fuzzy", |a| a.default_value(bool_str(v)));
+            }
+            if let Some(v) = self.fuzzy_penalty {
+                sc = sc.mut_arg("fuzzy_penalty", |a| a.default_value(v.to_string()));
+            }
+            if let Some(v) = self.fuzzy_distance {
+                sc = sc.mut_arg("fuzzy_distance", |a| a.default_value(v.to_string()));
+            }
+            if let Some(v) = &self.category {
+                sc = sc.mut_arg("category", |a| a.default_value(v.clone()));
+            }
+            sc
+        })
+    }
+}
+
+/// `--config <path>` has to be known before clap runs, so look for it by hand.
+pub fn config_path_from_args(args: &[OsString]) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(v) = arg.to_str().and_then(|s| s.strip_prefix("--config=")) {
+            return Some(PathBuf::from(v));
+        }
+    }
+    None
+}
+
+fn bool_str(v: bool) -> &'static str {
+    if v { "true" } else { "false" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Cmd};
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn load_str(toml: &str) -> Result<Config, String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, toml).unwrap();
+        Config::load(&path, true)
+    }
+
+    /// Parse `args` the way `app::run` does, with `config` supplying defaults.
+    fn parse(config: &Config, args: &[&str]) -> Cli {
+        let args = std::iter::once("desktop-indexer").chain(args.iter().copied());
+        let matches = config
+            .apply_defaults(Cli::command())
+            .try_get_matches_from(args)
+            .unwrap();
+        Cli::from_arg_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn load_reads_keys_and_tables() {
+        let config = load_str(
+            r#"
+            paths = ["/opt/apps"]
+            empty_mode = "frecency"
+            default_limit = 5
+            no_daemon = true
+            pinned = ["firefox.desktop"]
+            preferred_terminal = "foot"
+
+            [weights]
+            freq = 7
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.paths, [PathBuf::from("/opt/apps")]);
+        assert_eq!(config.empty_mode, Some(EmptyQueryMode::Frecency));
+        assert_eq!(config.default_limit, Some(5));
+        assert_eq!(config.no_daemon, Some(true));
+        assert_eq!(config.pinned, ["firefox.desktop"]);
+        assert_eq!(config.preferred_terminal, Some(Terminal::Foot));
+        assert_eq!(config.weights.freq, 7);
+        assert_eq!(
+            config.weights.name_boundary,
+            SearchWeights::default().name_boundary
+        );
+        assert_eq!(config.trace, None);
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys_and_bad_values() {
+        let err = load_str("empty_mod = \"recency\"\n").unwrap_err();
+        assert!(err.contains("unknown field `empty_mod`"), "{err}");
+        let err = load_str("[weights]\nname_boost = 1\n").unwrap_err();
+        assert!(err.contains("unknown field `name_boost`"), "{err}");
+        let err = load_str("default_limit = \"ten\"\n").unwrap_err();
+        assert!(err.contains("invalid config"), "{err}");
+        let err = load_str("empty_mode = \"sometimes\"\n").unwrap_err();
+        assert!(err.contains("invalid config"), "{err}");
+    }
+
+    #[test]
+    fn missing_file_is_an_error_only_when_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("none.toml");
+        let config = Config::load(&missing, false).unwrap();
+        assert!(config.paths.is_empty() && config.empty_mode.is_none());
+        let err = Config::load(&missing, true).unwrap_err();
+        assert!(err.contains("failed to read"), "{err}");
+    }
+
+    #[test]
+    fn config_values_are_defaults_that_flags_override() {
+        let config = load_str(
+            "paths = [\"/opt/apps\"]\nno_daemon = true\nempty_mode = \"frecency\"\n\
+             default_limit = 5\nfuzzy = true\ncategory = \"Game\"\nmax_depth = 3\n",
+        )
+        .unwrap();
+
+        let cli = parse(&config, &["search", "x"]);
+        assert_eq!(cli.paths, [PathBuf::from("/opt/apps")]);
+        assert!(cli.no_daemon);
+        let Cmd::Search {
+            limit,
+            empty_mode,
+            fuzzy,
+            category,
+            ..
+        } = cli.cmd
+        else {
+            panic!("not a search");
+        };
+        assert_eq!(limit, Some(5));
+        assert_eq!(empty_mode, EmptyQueryMode::Frecency);
+        assert!(fuzzy);
+        assert_eq!(category.as_deref(), Some("Game"));
+
+        let cli = parse(
+            &config,
+            &[
+                "-p",
+                "/srv/apps",
+                "search",
+                "x",
+                "--limit",
+                "2",
+                "--empty-mode",
+                "recency",
+                "--category",
+                "Office",
+            ],
+        );
+        assert_eq!(cli.paths, [PathBuf::from("/srv/apps")]);
+        let Cmd::Search {
+            limit,
+            empty_mode,
+            category,
+            ..
+        } = cli.cmd
+        else {
+            panic!("not a search");
+        };
+        assert_eq!(limit, Some(2));
+        assert_eq!(empty_mode, EmptyQueryMode::Recency);
+        assert_eq!(category.as_deref(), Some("Office"));
+
+        let Cmd::Scan { max_depth, .. } = parse(&config, &["scan"]).cmd else {
+            panic!("not a scan");
+        };
+        assert_eq!(max_depth, Some(3));
+        let Cmd::Scan { max_depth, .. } = parse(&config, &["scan", "--max-depth", "1"]).cmd else {
+            panic!("not a scan");
+        };
+        assert_eq!(max_depth, Some(1));
+
+        // Without a config, clap's own defaults apply.
+        let Cmd::Search { limit, .. } = parse(&Config::default(), &["search", "x"]).cmd else {
+            panic!("not a search");
+        };
+        assert_eq!(limit, None);
+    }
+
+    #[test]
+    fn config_path_is_found_before_clap_runs() {
+        let args = |list: &[&str]| -> Vec<OsString> { list.iter().map(OsString::from).collect() };
+        assert_eq!(
+            config_path_from_args(&args(&["bin", "search", "--config", "/tmp/c.toml", "x"])),
+            Some(PathBuf::from("/tmp/c.toml"))
+        );
+        assert_eq!(
+            config_path_from_args(&args(&["bin", "--config=/tmp/c.toml", "list"])),
+            Some(PathBuf::from("/tmp/c.toml"))
+        );
+        assert_eq!(
+            config_path_from_args(&args(&["bin", "search", "--", "--config"])),
+            None
+        );
+        // argv[0] is never the flag.
+        assert_eq!(config_path_from_args(&args(&["--config", "x"])), None);
+    }
+}
@@ -1,24 +1,68 @@
-use crate::desktop::scan_and_parse_desktop_files;
-use crate::frequency::FrequencyStore;
-use crate::ipc::{Request, Response};
-use crate::launch::{Terminal, exec_to_argv, pick_terminal};
-use crate::xdg::socket_path;
+use crate::commands::launch::{
+    resolve_dbus_activation, resolve_launch, run_status_ok, working_dir,
+};
+use crate::commands::open::xdg_open;
+use crate::daemon_client;
+use crate::desktop::scan_and_parse_desktop_files_with_progress;
+use crate::frequency::{FrequencyStore, Usage};
+use crate::ipc::{IPC_VERSION, Request, Response, with_protocol_version};
+use crate::launch::{LaunchEnv, Terminal, launch_args, parse_env_assignment};
+use crate::launched::{record_launch, running_launched};
+use crate::mime::sniff_mime;
+use crate::models::{DesktopEntryIndexed, ScanOptions, ScanProgress};
+use crate::trigram::TrigramIndex;
+use crate::xdg::{pid_path, socket_path};
+use serde::Deserialize;
 use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader, Write},
-    os::unix::net::{UnixListener, UnixStream},
-    path::PathBuf,
-    process::Command,
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader, IsTerminal, Write},
+    ops::Deref,
+    os::unix::{
+        io::{FromRawFd, IntoRawFd, RawFd},
+        net::{UnixDatagram, UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+    },
     time::{Duration, Instant},
 };
 
-type IndexKey = (Vec<String>, bool);
+/// (roots, respect_try_exec, de_filter)
+type IndexKey = (Vec<String>, bool, bool);
 
 struct IndexState {
-    entries: Vec<crate::models::DesktopEntryIndexed>,
+    entries: Vec<DesktopEntryIndexed>,
+    /// Id => position in the key's roots of the file that claimed it, including
+    /// files whose entry was dropped (parse failure, filters), which still
+    /// shadow later roots.
+    claimed: HashMap<String, usize>,
+    trigrams: TrigramIndex,
     last_tokens: Vec<String>,
     last_candidates: Vec<usize>,
     last_query_key: String,
+    last_built: Instant,
+}
+
+impl IndexState {
+    fn new(
+        entries: Vec<DesktopEntryIndexed>,
+        claimed: HashMap<String, usize>,
+        last_built: Instant,
+    ) -> Self {
+        Self {
+            trigrams: TrigramIndex::build(&entries),
+            entries,
+            claimed,
+            last_tokens: Vec::new(),
+            last_candidates: Vec::new(),
+            last_query_key: String::new(),
+            last_built,
+        }
+    }
 }
 
 fn query_key(query: &str) -> String {
@@ -49,11 +93,36 @@ fn tokens_contain_all(tokens: &[String], prev: &[String]) -> bool {
     prev.iter().all(|t| tokens.iter().any(|x| x == t))
 }
 
-pub fn start_daemon() -> std::io::Result<StartResult> {
+/// PID of the running daemon, from the PID file. `None` if the file is missing
+/// or stale (no such process, or the PID was reused by something else).
+pub fn running_pid() -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(pid_path())
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    cmdline
+        .split(|&b| b == 0)
+        .any(|arg| arg == b"run-daemon")
+        .then_some(pid)
+}
+
+/// `config_path` is forwarded so the daemon sees the same explicit `--config`,
+/// and `no_cache` as `--no-cache`.
+pub fn start_daemon(
+    config_path: Option<&std::path::Path>,
+    no_cache: bool,
+) -> std::io::Result<StartResult> {
     let path = socket_path();
 
-    // Already running?
-    if UnixStream::connect(&path).is_ok() {
+    // Already running? The PID file avoids touching the socket at all.
+    if running_pid().is_some() {
+        return Ok(StartResult::AlreadyRunning);
+    }
+    let _ = std::fs::remove_file(pid_path());
+
+    if daemon_client::ping() {
         return Ok(StartResult::AlreadyRunning);
     }
 
@@ -65,6 +134,12 @@ pub fn start_daemon() -> std::io::Result<StartResult> {
     // Spawn detached child: same binary, internal subcommand.
     let exe = std::env::current_exe()?;
     let mut child = std::process::Command::new(exe);
+    if let Some(config) = config_path {
+        child.arg("--config").arg(config);
+    }
+    if no_cache {
+        child.arg("--no-cache");
+    }
     child
         .arg("run-daemon")
         .stdin(std::process::Stdio::null())
@@ -73,10 +148,10 @@ pub fn start_daemon() -> std::io::Result<StartResult> {
 
     let _ = child.spawn()?;
 
-    // Wait briefly for socket to become available.
+    // Wait briefly for the daemon to answer.
     let start = Instant::now();
     while start.elapsed() < Duration::from_millis(800) {
-        if UnixStream::connect(&path).is_ok() {
+        if daemon_client::ping() {
             return Ok(StartResult::Started);
         }
         std::thread::sleep(Duration::from_millis(20));
@@ -90,93 +165,424 @@ pub enum StartResult {
     AlreadyRunning,
 }
 
-pub fn run_daemon_foreground() -> std::io::Result<()> {
+/// Connections waiting to be served, as reported by `StatusVerbose`.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+pub const DEFAULT_MAX_PENDING: usize = 32;
+
+/// Config scan settings (e.g. `max_depth`, `ignore`), fixed for the daemon's
+/// lifetime. `respect_try_exec` and `de_filter` come from each request instead.
+static SCAN_OPTIONS: OnceLock<ScanOptions> = OnceLock::new();
+
+/// Search limit for requests without one (config `default_limit`).
+static DEFAULT_LIMIT: OnceLock<usize> = OnceLock::new();
+
+pub fn run_daemon_foreground(
+    max_pending: usize,
+    scan_options: ScanOptions,
+    default_limit: usize,
+    warmup_roots: Option<Vec<String>>,
+    systemd: bool,
+) -> std::io::Result<()> {
     let path = socket_path();
 
-    // If socket exists, check if daemon is alive.
-    if path.exists() {
-        if UnixStream::connect(&path).is_ok() {
-            eprintln!(
-                "desktop-indexer: daemon already running at {}",
-                path.display()
-            );
-            return Ok(());
+    // With socket activation the socket file belongs to systemd: don't bind,
+    // and don't remove it on shutdown.
+    let activated = if systemd { maybe_sd_socket() } else { None };
+    let owns_socket = activated.is_none();
+    let listener = match activated {
+        Some(listener) => {
+            let addr = listener.local_addr()?;
+            let shown = addr
+                .as_pathname()
+                .map_or_else(|| "(unnamed)".to_string(), |p| p.display().to_string());
+            eprintln!("desktop-indexer: daemon listening on systemd socket {shown}");
+            listener
         }
-        let _ = std::fs::remove_file(&path);
-    }
+        None => {
+            // If socket exists, check if daemon is alive.
+            if path.exists() {
+                if UnixStream::connect(&path).is_ok() {
+                    eprintln!(
+                        "desktop-indexer: daemon already running at {}",
+                        path.display()
+                    );
+                    return Ok(());
+                }
+                let _ = std::fs::remove_file(&path);
+            }
+
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+
+            let listener = UnixListener::bind(&path)?;
+            eprintln!("desktop-indexer: daemon listening on {}", path.display());
+            listener
+        }
+    };
 
-    if let Some(parent) = path.parent() {
-        let _ = std::fs::create_dir_all(parent);
+    let pid_file = pid_path();
+    if let Err(e) = std::fs::write(&pid_file, format!("{}\n", std::process::id())) {
+        eprintln!(
+            "desktop-indexer: failed to write pid file {}: {e}",
+            pid_file.display()
+        );
     }
 
-    let listener = UnixListener::bind(&path)?;
-    eprintln!("desktop-indexer: daemon listening on {}", path.display());
+    let _ = SCAN_OPTIONS.set(scan_options);
+    let _ = DEFAULT_LIMIT.set(default_limit);
+    let warmup = warmup_roots.map(spawn_warmup);
 
+    // Accepting from here on; a warmup keeps building in the background.
+    sd_notify("READY=1\n");
+    spawn_sd_watchdog();
     let mut indexes: HashMap<IndexKey, IndexState> = HashMap::new();
     let mut freqs = FrequencyStore::load();
 
     let mut shutdown = false;
+    let mut queue: VecDeque<Conn> = VecDeque::new();
 
-    for conn in listener.incoming() {
-        match conn {
-            Ok(stream) => {
-                shutdown = handle_connection(stream, &mut indexes, &mut freqs);
-                if shutdown {
-                    break;
+    while !shutdown {
+        if queue.is_empty() {
+            match accept(&listener, false) {
+                Ok(stream) => queue.push_back(Conn::new(stream)),
+                Err(e) => {
+                    eprintln!("desktop-indexer: accept error: {e}");
+                    continue;
                 }
             }
-            Err(e) => {
-                eprintln!("desktop-indexer: accept error: {e}");
+        }
+
+        // Pull in everything already waiting so the backlog is visible, and turn
+        // away what doesn't fit instead of letting it pile up.
+        while let Ok(stream) = accept(&listener, true) {
+            if queue.len() >= max_pending {
+                reject_busy(stream);
+            } else {
+                queue.push_back(Conn::new(stream));
+            }
+        }
+
+        PENDING.store(queue.len().saturating_sub(1), Ordering::Relaxed);
+        if let Some(rx) = &warmup
+            && let Ok((key, state)) = rx.try_recv()
+        {
+            // A request may have built the same index in the meantime.
+            indexes.entry(key).or_insert(state);
+        }
+        if let Some(conn) = queue.pop_front() {
+            match serve_turn(conn, &mut indexes, &mut freqs) {
+                // Back of the line: the others get a turn before its next request.
+                Turn::Open(conn) => queue.push_back(conn),
+                Turn::Closed => {}
+                Turn::Shutdown => shutdown = true,
             }
         }
     }
 
+    sd_notify("STOPPING=1\n");
+
+    // Connections queued before the shutdown still get an answer.
+    for conn in queue {
+        serve_to_end(conn, &mut indexes, &mut freqs);
+    }
+
+    drain_pending(&listener, &mut indexes, &mut freqs);
+
     drop(listener);
-    if shutdown {
-        freqs.flush();
+    freqs.flush();
+    if owns_socket {
         let _ = std::fs::remove_file(&path);
-        eprintln!("desktop-indexer: daemon stopped");
     }
+    let _ = std::fs::remove_file(&pid_file);
+    eprintln!("desktop-indexer: daemon stopped");
 
     Ok(())
 }
 
-fn handle_connection(
-    stream: UnixStream,
+/// First file descriptor systemd passes (`SD_LISTEN_FDS_START`).
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// The listening socket passed by systemd socket activation: `LISTEN_FDS=1`
+/// and, when set, `LISTEN_PID` naming this process. `None` without one.
+pub fn maybe_sd_socket() -> Option<UnixListener> {
+    let listen_pid = std::env::var("LISTEN_PID").ok();
+    let listen_fds = std::env::var("LISTEN_FDS").ok();
+    // SAFETY: with LISTEN_FDS=1 systemd hands this process exactly one open
+    // descriptor at SD_LISTEN_FDS_START, and nothing else in the process
+    // takes ownership of it.
+    unsafe {
+        sd_socket_from(
+            listen_pid.as_deref(),
+            listen_fds.as_deref(),
+            SD_LISTEN_FDS_START,
+        )
+    }
+}
+
+/// `maybe_sd_socket` with the environment values and the passed fd given
+/// explicitly.
+///
+/// # Safety
+///
+/// When `listen_fds` is `"1"` (and `listen_pid` names this process), `fd`
+/// must be an open descriptor this process owns and nothing else uses.
+unsafe fn sd_socket_from(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    fd: RawFd,
+) -> Option<UnixListener> {
+    if let Some(pid) = listen_pid
+        && pid.trim().parse::<u32>().ok() != Some(std::process::id())
+    {
+        return None;
+    }
+    match listen_fds?.trim() {
+        "1" => {}
+        n => {
+            eprintln!("desktop-indexer: LISTEN_FDS={n}, expected 1 socket (binding instead)");
+            return None;
+        }
+    }
+
+    // SAFETY: the caller passes ownership of `fd`.
+    let inherited = unsafe { UnixListener::from_raw_fd(fd) };
+    // Not a Unix socket (e.g. a TCP ListenStream=): leave it alone.
+    if inherited.local_addr().is_err() {
+        eprintln!("desktop-indexer: inherited fd is not a Unix socket (binding instead)");
+        let _ = inherited.into_raw_fd();
+        return None;
+    }
+    // systemd passes the fd without CLOEXEC; the clone has it, so launched
+    // apps don't inherit the daemon's socket.
+    inherited.try_clone().ok()
+}
+
+/// Send `msg` to the service manager's `NOTIFY_SOCKET` (`sd_notify(3)`);
+/// does nothing when it isn't set. Best-effort: errors are ignored.
+fn sd_notify(msg: &str) {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    // `@name` is an abstract socket (Linux only).
+    let addr = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return,
+        None => std::os::unix::net::SocketAddr::from_pathname(path.as_ref()),
+    };
+    if let (Ok(addr), Ok(socket)) = (addr, UnixDatagram::unbound()) {
+        let _ = socket.send_to_addr(msg.as_bytes(), &addr);
+    }
+}
+
+/// With `WATCHDOG_USEC` set (and `WATCHDOG_PID`, if set, naming this process),
+/// send `WATCHDOG=1` every half interval from a background thread.
+fn spawn_sd_watchdog() {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.trim().parse::<u32>().ok() != Some(std::process::id())
+    {
+        return;
+    }
+    let Some(usec) = std::env::var("WATCHDOG_USEC")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&usec| usec > 0)
+    else {
+        return;
+    };
+
+    let interval = Duration::from_micros(usec / 2);
+    std::thread::spawn(move || {
+        loop {
+            sd_notify("WATCHDOG=1\n");
+            std::thread::sleep(interval);
+        }
+    });
+}
+
+/// Accept one connection; with `nonblocking`, fail with `WouldBlock` instead of waiting.
+fn accept(listener: &UnixListener, nonblocking: bool) -> std::io::Result<UnixStream> {
+    listener.set_nonblocking(nonblocking)?;
+    let (stream, _) = listener.accept()?;
+    stream.set_nonblocking(false)?;
+    Ok(stream)
+}
+
+fn reject_busy(mut stream: UnixStream) {
+    let _ = write_response(
+        &mut stream,
+        Response::Error {
+            message: "busy".to_string(),
+        },
+    );
+}
+
+/// How long `drain_pending` keeps accepting after a shutdown request.
+const SHUTDOWN_DRAIN: Duration = Duration::from_millis(100);
+
+/// Answer clients that connected before the shutdown so they get a response
+/// instead of a reset connection.
+fn drain_pending(
+    listener: &UnixListener,
     indexes: &mut HashMap<IndexKey, IndexState>,
     freqs: &mut FrequencyStore,
-) -> bool {
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
-    if reader.read_line(&mut line).is_err() {
-        return false;
+) {
+    if listener.set_nonblocking(true).is_err() {
+        return;
     }
 
-    let req = match serde_json::from_str::<Request>(line.trim()) {
-        Ok(r) => r,
-        Err(e) => {
-            let _ = write_response(
-                reader.into_inner(),
-                Response::Error {
-                    message: format!("invalid request: {e}"),
-                },
-            );
-            return false;
+    let deadline = Instant::now() + SHUTDOWN_DRAIN;
+    while Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Accepted sockets don't inherit non-blocking mode on Linux, but be explicit.
+                let _ = stream.set_nonblocking(false);
+                serve_to_end(Conn::new(stream), indexes, freqs);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// How long a connection may sit idle between requests before it is closed.
+///
+/// Keep-alive clients reconnect transparently after this.
+const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long one turn waits for a connection's next request before moving on
+/// to the next queued connection.
+const TURN_READ_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// A client connection waiting for its next request.
+struct Conn {
+    reader: BufReader<UnixStream>,
+    /// Start of a request line that arrived before a turn timed out.
+    line: Vec<u8>,
+    idle_since: Instant,
+}
+
+impl Conn {
+    fn new(stream: UnixStream) -> Self {
+        let _ = stream.set_read_timeout(Some(TURN_READ_TIMEOUT));
+        Self {
+            reader: BufReader::new(stream),
+            line: Vec::new(),
+            idle_since: Instant::now(),
+        }
+    }
+}
+
+enum Turn {
+    /// The client may send more requests.
+    Open(Conn),
+    Closed,
+    Shutdown,
+}
+
+/// Serve at most one request from `conn`.
+///
+/// The daemon is single-threaded: connections take turns, one request each,
+/// so a keep-alive client can't hold it while others queue up.
+fn serve_turn(
+    mut conn: Conn,
+    indexes: &mut HashMap<IndexKey, IndexState>,
+    freqs: &mut FrequencyStore,
+) -> Turn {
+    match conn.reader.read_until(b'\n', &mut conn.line) {
+        Ok(0) => return Turn::Closed,
+        Ok(_) => {}
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ) =>
+        {
+            return if conn.idle_since.elapsed() >= CONNECTION_IDLE_TIMEOUT {
+                Turn::Closed
+            } else {
+                Turn::Open(conn)
+            };
         }
+        Err(_) => return Turn::Closed,
+    }
+
+    let line = std::mem::take(&mut conn.line);
+    let (resp, shutdown) = match parse_request(line.trim_ascii()) {
+        Ok(req) => handle_request(indexes, freqs, req),
+        Err(resp) => (resp, false),
     };
 
-    let (resp, shutdown) = handle_request(indexes, freqs, req);
-    let _ = write_response(reader.into_inner(), resp);
-    shutdown
+    if shutdown {
+        let _ = write_response(conn.reader.get_mut(), resp);
+        return Turn::Shutdown;
+    }
+    if write_response(conn.reader.get_mut(), resp).is_err() {
+        return Turn::Closed;
+    }
+    conn.idle_since = Instant::now();
+    Turn::Open(conn)
 }
 
-fn write_response(mut stream: UnixStream, resp: Response) -> std::io::Result<()> {
-    let line = serde_json::to_string(&resp).unwrap_or_else(|_| {
-        serde_json::to_string(&Response::Error {
-            message: "failed to serialize response".to_string(),
-        })
-        .unwrap()
-    });
+/// Serve `conn` until the client hangs up or goes idle. Used once the daemon is
+/// shutting down, where a second shutdown request changes nothing.
+fn serve_to_end(
+    mut conn: Conn,
+    indexes: &mut HashMap<IndexKey, IndexState>,
+    freqs: &mut FrequencyStore,
+) {
+    while let Turn::Open(next) = serve_turn(conn, indexes, freqs) {
+        conn = next;
+    }
+}
+
+/// Decode a request line. A request whose `protocol_version` differs from the
+/// daemon's is refused (unless it is version-independent) rather than
+/// misread; requests without one are taken as they are.
+fn parse_request(line: &[u8]) -> Result<Request, Response> {
+    let invalid = |e: serde_json::Error| Response::Error {
+        message: format!("invalid request: {e}"),
+    };
+    let value: serde_json::Value = serde_json::from_slice(line).map_err(invalid)?;
+    let client_version = value
+        .get("protocol_version")
+        .and_then(serde_json::Value::as_u64);
+    let req = Request::deserialize(value).map_err(invalid)?;
+
+    match client_version {
+        Some(v) if v != u64::from(IPC_VERSION) && !req.is_version_independent() => {
+            Err(Response::Error {
+                message: format!(
+                    "client speaks IPC protocol {v}, daemon {IPC_VERSION}; restart the daemon"
+                ),
+            })
+        }
+        _ => Ok(req),
+    }
+}
+
+/// Write `resp` as one line, tagged with the daemon's `protocol_version`.
+fn write_response(stream: &mut UnixStream, resp: Response) -> std::io::Result<()> {
+    let line = match serde_json::to_string(&resp) {
+        // Carries it already.
+        Ok(json) if matches!(resp, Response::Pong { .. }) => json,
+        Ok(json) => with_protocol_version(json),
+        Err(_) => with_protocol_version(
+            serde_json::to_string(&Response::Error {
+                message: "failed to serialize response".to_string(),
+            })
+            .unwrap(),
+        ),
+    };
     stream.write_all(line.as_bytes())?;
     stream.write_all(b"\n")?;
     stream.flush()?;
@@ -189,6 +595,31 @@ fn handle_request(
     req: Request,
 ) -> (Response, bool) {
     match req {
+        Request::Batch { requests } => {
+            let mut shutdown = false;
+            let responses = requests
+                .into_iter()
+                .map(|r| {
+                    if matches!(r, Request::Batch { .. }) {
+                        return Response::Error {
+                            message: "nested batch requests are not supported".to_string(),
+                        };
+                    }
+                    let (resp, stop) = handle_request(indexes, freqs, r);
+                    shutdown |= stop;
+                    resp
+                })
+                .collect();
+            (Response::Batch { responses }, shutdown)
+        }
+
+        Request::Ping { .. } => (
+            Response::Pong {
+                protocol_version: IPC_VERSION,
+            },
+            false,
+        ),
+
         Request::Shutdown => {
             freqs.flush();
             (Response::Ok, true)
@@ -197,8 +628,9 @@ fn handle_request(
         Request::Warmup {
             roots,
             respect_try_exec,
+            de_filter,
         } => {
-            if ensure_index(indexes, &roots, respect_try_exec).is_some() {
+            if ensure_index(indexes, &roots, respect_try_exec, de_filter).is_some() {
                 (Response::Ok, false)
             } else {
                 (
@@ -210,6 +642,69 @@ fn handle_request(
             }
         }
 
+        Request::Reload {
+            roots,
+            respect_try_exec,
+            de_filter,
+        } => {
+            let start = Instant::now();
+            // Always rescan: merging would reuse the indexes being refreshed.
+            let state = indexes
+                .entry((roots.clone(), respect_try_exec, de_filter))
+                .insert_entry(build_index(&roots, respect_try_exec, de_filter))
+                .into_mut();
+
+            if crate::commands::common::timing_enabled() {
+                eprintln!(
+                    "desktop-indexer timing(daemon): reload entries={} elapsed={:?}",
+                    state.entries.len(),
+                    start.elapsed()
+                );
+            }
+            (Response::Ok, false)
+        }
+
+        Request::ResetFrequency { id } => {
+            match id {
+                Some(id) => {
+                    freqs.remove(id.trim_end_matches(".desktop"));
+                }
+                None => {
+                    freqs.clear();
+                }
+            }
+            freqs.flush();
+            (Response::Ok, false)
+        }
+
+        Request::ListPids => (
+            Response::LaunchedPids {
+                pids: running_launched(),
+            },
+            false,
+        ),
+
+        Request::IncrementFrequency { id } => {
+            freqs.increment(id.trim_end_matches(".desktop"));
+            freqs.flush();
+            (Response::Ok, false)
+        }
+
+        Request::SetFrequency {
+            id,
+            freq,
+            last_used,
+        } => {
+            freqs.set(id.trim_end_matches(".desktop"), Usage { freq, last_used });
+            freqs.flush();
+            (Response::Ok, false)
+        }
+
+        Request::ReloadFrequency => {
+            *freqs = FrequencyStore::load();
+            (Response::Ok, false)
+        }
+
         Request::Status => (
             Response::Status {
                 has_index_count: indexes.len(),
@@ -217,14 +712,55 @@ fn handle_request(
             false,
         ),
 
+        Request::StatusVerbose => {
+            let now_sec = crate::frequency::unix_seconds_now();
+            let mut infos: Vec<crate::ipc::IndexInfo> = indexes
+                .iter()
+                .map(
+                    |((roots, respect_try_exec, de_filter), state)| crate::ipc::IndexInfo {
+                        roots: roots.clone(),
+                        respect_try_exec: *respect_try_exec,
+                        de_filter: *de_filter,
+                        entry_count: state.entries.len(),
+                        last_built_sec: now_sec
+                            .saturating_sub(state.last_built.elapsed().as_secs()),
+                    },
+                )
+                .collect();
+            infos.sort_by(|a, b| a.roots.cmp(&b.roots));
+            (
+                Response::StatusVerbose {
+                    indexes: infos,
+                    pending: PENDING.load(Ordering::Relaxed),
+                },
+                false,
+            )
+        }
+
         Request::Search {
             roots,
             query,
             limit,
             empty_mode,
             respect_try_exec,
+            de_filter,
+            fuzzy,
+            fuzzy_penalty,
+            fuzzy_distance,
+            weights,
+            pinned,
+            category,
+            source,
+            exact_name,
+            type_filter,
+            modified_since,
+            used_since,
+            show_hidden,
+            show_nodisplay,
+            exclude,
+            min_score,
         } => {
-            let Some(state) = ensure_index(indexes, &roots, respect_try_exec) else {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
                 return (
                     Response::Error {
                         message: "failed to build index".to_string(),
@@ -233,7 +769,38 @@ fn handle_request(
                 );
             };
 
-            let lim = limit.unwrap_or(20);
+            let lim = limit.unwrap_or_else(|| {
+                *DEFAULT_LIMIT.get_or_init(|| crate::search::DEFAULT_SEARCH_LIMIT)
+            });
+            let fuzzy = fuzzy.then(|| crate::search::FuzzyOptions {
+                penalty: fuzzy_penalty.unwrap_or(crate::search::DEFAULT_FUZZY_PENALTY),
+                max_distance: fuzzy_distance
+                    .map_or(crate::search::DEFAULT_FUZZY_MAX_DISTANCE, |d| {
+                        d.min(crate::search::MAX_FUZZY_DISTANCE).into()
+                    }),
+            });
+            let scoring = crate::search::Scoring {
+                fuzzy,
+                weights: weights.unwrap_or_default(),
+                pins: &pinned,
+                min_score,
+            };
+            let mut exclude = exclude;
+            exclude.extend(crate::search::exclude_tokens(&query));
+            exclude.sort();
+            exclude.dedup();
+            let filter = crate::search::EntryFilter {
+                category: category.as_deref(),
+                show_hidden,
+                show_nodisplay,
+                exclude: &exclude,
+                source,
+                exact_name: exact_name.as_deref(),
+                type_filter: type_filter.as_deref(),
+                modified_since,
+                used_since,
+                ..Default::default()
+            };
             let qkey = query_key(&query);
             let tokens = crate::search::normalize_query(&query);
             if tokens.is_empty() {
@@ -244,6 +811,11 @@ fn handle_request(
                     lim,
                     freqs.map(),
                     mode,
+                    &crate::search::Scoring {
+                        pins: &pinned,
+                        ..Default::default()
+                    },
+                    &filter,
                 );
 
                 state.last_tokens.clear();
@@ -258,6 +830,7 @@ fn handle_request(
             // We treat these as refinements:
             // - token superset ("text" -> "text editor")
             // - typeahead prefix ("v" -> "vs" -> "vsc")
+            // Fuzzy matching widens as tokens grow, so it never reuses candidates.
             let is_typeahead_prefix = state.last_tokens.len() == 1
                 && tokens.len() == 1
                 && !state.last_tokens[0].is_empty()
@@ -267,34 +840,50 @@ fn handle_request(
                 && qkey.len() > state.last_query_key.len()
                 && qkey.starts_with(&state.last_query_key);
 
-            let can_reuse = tokens_contain_all(&tokens, &state.last_tokens)
-                || is_typeahead_prefix
-                || is_query_prefix;
+            let can_reuse = fuzzy.is_none()
+                && (tokens_contain_all(&tokens, &state.last_tokens)
+                    || is_typeahead_prefix
+                    || is_query_prefix);
 
+            // Fresh queries start from the trigram prefilter; fuzzy matches may
+            // lack the exact trigrams, so they scan everything.
             let mut candidates: Vec<usize> = if can_reuse {
                 state.last_candidates.clone()
+            } else if fuzzy.is_none() {
+                state.trigrams.candidates(&query)
             } else {
                 (0..state.entries.len()).collect()
             };
 
             candidates.retain(|&idx| {
-                let e = &state.entries[idx];
-                tokens
-                    .iter()
-                    .all(|t| crate::search::norm_has_token_prefix(&e.norm, t))
+                crate::search::norm_matches_tokens(&state.entries[idx].norm, &tokens, fuzzy)
             });
 
             // Score only within candidates (same scoring as search::search_entries).
             use std::{cmp::Reverse, collections::BinaryHeap};
             let mut heap: BinaryHeap<Reverse<(i32, usize)>> = BinaryHeap::new();
+            // Pinned matches bypass the heap: a low score must not drop them.
+            let mut pinned_matches: Vec<(i32, usize)> = Vec::new();
 
             let now_sec = crate::frequency::unix_seconds_now();
 
+            // Filters are applied here (not to `candidates`) so the refinement cache
+            // stays valid when only the filters change between queries.
             for &idx in &candidates {
                 let e = &state.entries[idx];
+                if !filter.accepts_indexed(e) || !filter.accepts_usage(freqs.map(), &e.out.id) {
+                    continue;
+                }
                 let usage = freqs.get(&e.out.id);
-                let score = crate::search::score_entry(e, &tokens, usage, now_sec);
+                let score = crate::search::score_entry(e, &tokens, usage, now_sec, &scoring);
+                if min_score.is_some_and(|min| score < min) {
+                    continue;
+                }
 
+                if crate::search::is_pinned(&e.out.id, &pinned) {
+                    pinned_matches.push((score, idx));
+                    continue;
+                }
                 heap.push(Reverse((score, idx)));
                 if heap.len() > lim {
                     heap.pop();
@@ -302,12 +891,15 @@ fn handle_request(
             }
 
             let mut picked: Vec<(i32, usize)> = heap.into_iter().map(|Reverse(x)| x).collect();
-            picked.sort_by(|a, b| b.0.cmp(&a.0));
+            picked.extend(pinned_matches);
+            picked.sort_by_key(|p| Reverse(p.0));
 
-            let entries = picked
+            let mut entries: Vec<crate::models::DesktopEntryOut> = picked
                 .into_iter()
                 .map(|(_, idx)| state.entries[idx].out.clone())
                 .collect();
+            crate::search::apply_pins(&mut entries, &pinned);
+            entries.truncate(lim);
 
             // Update incremental cache for next query.
             state.last_tokens = tokens;
@@ -320,8 +912,17 @@ fn handle_request(
         Request::List {
             roots,
             respect_try_exec,
+            de_filter,
+            show_hidden,
+            show_nodisplay,
+            sort_keys,
+            source,
+            filter_category,
+            filter_mime,
+            score_by,
+            weights,
         } => {
-            let Some(state) = ensure_index(indexes, &roots, respect_try_exec) else {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
                 return (
                     Response::Error {
                         message: "failed to build index".to_string(),
@@ -330,14 +931,116 @@ fn handle_request(
                 );
             };
 
-            let mut entries: Vec<crate::models::DesktopEntryOut> =
-                state.entries.iter().map(|e| e.out.clone()).collect();
-            entries.sort_by(|a, b| {
-                a.name
-                    .as_deref()
-                    .unwrap_or("")
-                    .cmp(b.name.as_deref().unwrap_or(""))
-            });
+            let filter = crate::search::EntryFilter {
+                category: filter_category.as_deref(),
+                show_hidden,
+                show_nodisplay,
+                source,
+                mime_type: filter_mime.as_deref(),
+                ..Default::default()
+            };
+            let mut entries: Vec<crate::models::DesktopEntryOut> = state
+                .entries
+                .iter()
+                .filter(|e| filter.accepts(&e.out))
+                .map(|e| e.out.clone())
+                .collect();
+            crate::commands::list::sort_entries(&mut entries, &sort_keys, freqs.map());
+            if let Some(query) = &score_by {
+                crate::search::sort_by_relevance(
+                    &mut entries,
+                    &state.entries,
+                    query,
+                    freqs.map(),
+                    &crate::search::Scoring {
+                        weights: weights.unwrap_or_default(),
+                        ..Default::default()
+                    },
+                );
+            }
+            (Response::Entries { entries }, false)
+        }
+
+        Request::GetEntry {
+            roots,
+            id,
+            respect_try_exec,
+            de_filter,
+        } => {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
+                return (
+                    Response::Error {
+                        message: "failed to build index".to_string(),
+                    },
+                    false,
+                );
+            };
+
+            let entries = state
+                .entries
+                .iter()
+                .find(|e| e.out.id == id)
+                .map(|e| e.out.clone())
+                .into_iter()
+                .collect();
+            (Response::Entries { entries }, false)
+        }
+
+        Request::GetActions {
+            roots,
+            desktop_id,
+            respect_try_exec,
+            de_filter,
+        } => {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
+                return (
+                    Response::Error {
+                        message: "failed to build index".to_string(),
+                    },
+                    false,
+                );
+            };
+
+            match state.entries.iter().find(|e| e.out.id == desktop_id) {
+                Some(e) => (
+                    Response::Actions {
+                        actions: e.out.actions.clone(),
+                    },
+                    false,
+                ),
+                None => (
+                    Response::Error {
+                        message: format!("Unknown desktop-id: {desktop_id}"),
+                    },
+                    false,
+                ),
+            }
+        }
+
+        Request::ByMime {
+            roots,
+            mime_type,
+            respect_try_exec,
+            de_filter,
+            show_hidden,
+            show_nodisplay,
+        } => {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
+                return (
+                    Response::Error {
+                        message: "failed to build index".to_string(),
+                    },
+                    false,
+                );
+            };
+
+            let filter = crate::search::EntryFilter {
+                show_hidden,
+                show_nodisplay,
+                ..crate::search::EntryFilter::default()
+            };
+            let entries =
+                crate::search::entries_for_mime(&state.entries, &mime_type, freqs.map(), &filter);
             (Response::Entries { entries }, false)
         }
 
@@ -346,8 +1049,18 @@ fn handle_request(
             desktop_id,
             action,
             respect_try_exec,
+            de_filter,
+            terminal,
+            files,
+            urls,
+            cwd,
+            env,
         } => {
-            let Some(state) = ensure_index(indexes, &roots, respect_try_exec) else {
+            if let Some(e) = env.iter().find_map(|v| parse_env_assignment(v).err()) {
+                return (Response::Error { message: e }, false);
+            }
+
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
                 return (
                     Response::Error {
                         message: "failed to build index".to_string(),
@@ -356,7 +1069,15 @@ fn handle_request(
                 );
             };
 
-            match do_launch(&state.entries, &desktop_id, action.as_deref()) {
+            match do_launch(
+                &state.entries,
+                &desktop_id,
+                action.as_deref(),
+                terminal,
+                &launch_args(&files, &urls),
+                cwd.as_deref(),
+                &env,
+            ) {
                 Ok(()) => {
                     let id = desktop_id.trim_end_matches(".desktop");
                     freqs.increment(id);
@@ -366,6 +1087,57 @@ fn handle_request(
                 Err(e) => (Response::Error { message: e }, false),
             }
         }
+
+        Request::Open {
+            roots,
+            file_path,
+            respect_try_exec,
+            de_filter,
+            terminal,
+        } => {
+            let Some(state) = ensure_index(indexes, &roots, respect_try_exec, de_filter) else {
+                return (
+                    Response::Error {
+                        message: "failed to build index".to_string(),
+                    },
+                    false,
+                );
+            };
+
+            let handler = sniff_mime(Path::new(&file_path)).and_then(|mime_type| {
+                crate::search::entries_for_mime(
+                    &state.entries,
+                    &mime_type,
+                    freqs.map(),
+                    &crate::search::OPEN_HANDLER_FILTER,
+                )
+                .into_iter()
+                .next()
+            });
+            let Some(handler) = handler else {
+                return match xdg_open(&file_path) {
+                    Ok(()) => (Response::Ok, false),
+                    Err(e) => (Response::Error { message: e }, false),
+                };
+            };
+
+            match do_launch(
+                &state.entries,
+                &handler.id,
+                None,
+                terminal,
+                &[file_path.as_str()],
+                None,
+                &[],
+            ) {
+                Ok(()) => {
+                    freqs.increment(&handler.id);
+                    freqs.flush();
+                    (Response::Ok, false)
+                }
+                Err(e) => (Response::Error { message: e }, false),
+            }
+        }
     }
 }
 
@@ -373,29 +1145,216 @@ fn ensure_index<'a>(
     indexes: &'a mut HashMap<IndexKey, IndexState>,
     roots: &[String],
     respect_try_exec: bool,
+    de_filter: bool,
 ) -> Option<&'a mut IndexState> {
-    let key: IndexKey = (roots.to_vec(), respect_try_exec);
+    let key: IndexKey = (roots.to_vec(), respect_try_exec, de_filter);
 
     if !indexes.contains_key(&key) {
-        let roots_pb: Vec<PathBuf> = roots.iter().map(PathBuf::from).collect();
-        let parsed = scan_and_parse_desktop_files(&roots_pb, None, respect_try_exec);
-        indexes.insert(
-            key.clone(),
-            IndexState {
-                entries: parsed.entries,
-                last_tokens: Vec::new(),
-                last_candidates: Vec::new(),
-                last_query_key: String::new(),
-            },
-        );
+        let state = match merge_indexes(indexes, roots, respect_try_exec, de_filter) {
+            Some(view) => {
+                if crate::commands::common::timing_enabled() {
+                    eprintln!(
+                        "desktop-indexer timing(daemon): merged index entries={} roots={}",
+                        view.len(),
+                        roots.len()
+                    );
+                }
+                IndexState::new(view.entries, view.claimed, view.last_built)
+            }
+            None => build_index(roots, respect_try_exec, de_filter),
+        };
+        indexes.insert(key.clone(), state);
     }
     indexes.get_mut(&key)
 }
 
+/// Entries for a root list assembled from already built indexes, deduplicated
+/// the way a scan of the whole list would be.
+struct MergedView {
+    entries: Vec<DesktopEntryIndexed>,
+    claimed: HashMap<String, usize>,
+    /// Build time of the oldest index it was assembled from.
+    last_built: Instant,
+}
+
+impl Deref for MergedView {
+    type Target = [DesktopEntryIndexed];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+/// Build the index for `roots` from existing indexes whose roots are a subset
+/// of it (in the same order), so a client asking for `[A, B]` reuses `[A]` and
+/// `[B]` instead of parsing everything again. `None` unless every root is
+/// covered.
+fn merge_indexes(
+    indexes: &HashMap<IndexKey, IndexState>,
+    roots: &[String],
+    respect_try_exec: bool,
+    de_filter: bool,
+) -> Option<MergedView> {
+    if roots.is_empty() {
+        return None;
+    }
+    // Position in `roots` of each root of a usable sub-index.
+    let subs: Vec<(Vec<usize>, &IndexState)> = indexes
+        .iter()
+        .filter(|((_, t, d), _)| *t == respect_try_exec && *d == de_filter)
+        .filter_map(|((sub_roots, _, _), state)| {
+            let positions: Vec<usize> = sub_roots
+                .iter()
+                .map(|r| roots.iter().position(|x| x == r))
+                .collect::<Option<_>>()?;
+            let ordered = positions.windows(2).all(|w| w[0] < w[1]);
+            (ordered && positions.len() < roots.len()).then_some((positions, state))
+        })
+        .collect();
+
+    // One sub-index per requested root.
+    let providers: Vec<usize> = (0..roots.len())
+        .map(|g| {
+            subs.iter()
+                .position(|(positions, _)| positions.contains(&g))
+        })
+        .collect::<Option<_>>()?;
+
+    // An id belongs to the first root that has it. Each provider claims it for
+    // its own first such root, so the smallest claim wins overall.
+    let mut claimed: HashMap<String, usize> = HashMap::new();
+    let mut used = providers.clone();
+    used.sort_unstable();
+    used.dedup();
+    for &i in &used {
+        let (positions, state) = &subs[i];
+        for (id, &pos) in &state.claimed {
+            let g = positions[pos];
+            claimed
+                .entry(id.clone())
+                .and_modify(|w| *w = (*w).min(g))
+                .or_insert(g);
+        }
+    }
+
+    // Root by root, in each provider's (path) order, like a full scan.
+    let mut entries: Vec<DesktopEntryIndexed> = Vec::new();
+    for (g, &i) in providers.iter().enumerate() {
+        let (positions, state) = &subs[i];
+        entries.extend(
+            state
+                .entries
+                .iter()
+                .filter(|e| {
+                    state.claimed.get(&e.out.id).map(|&pos| positions[pos]) == Some(g)
+                        && claimed.get(&e.out.id) == Some(&g)
+                })
+                .cloned(),
+        );
+    }
+
+    let last_built = used.iter().map(|&i| subs[i].1.last_built).min()?;
+    Some(MergedView {
+        entries,
+        claimed,
+        last_built,
+    })
+}
+
+fn build_index(roots: &[String], respect_try_exec: bool, de_filter: bool) -> IndexState {
+    let roots_pb: Vec<PathBuf> = roots.iter().map(PathBuf::from).collect();
+    let options = ScanOptions {
+        respect_try_exec,
+        de_filter,
+        ..SCAN_OPTIONS.get_or_init(ScanOptions::default).clone()
+    };
+    let progress = BuildProgress::new();
+    let report = |_: ScanProgress| progress.tick();
+    let show_progress =
+        crate::commands::common::timing_enabled() || std::io::stderr().is_terminal();
+    let parsed = scan_and_parse_desktop_files_with_progress(
+        &roots_pb,
+        None,
+        &options,
+        show_progress.then_some(&report as &(dyn Fn(ScanProgress) + Sync)),
+    );
+    IndexState::new(
+        parsed.entries,
+        parsed.resolved_ids.into_iter().collect(),
+        Instant::now(),
+    )
+}
+
+/// Interval between "indexing..." lines while an index builds.
+const PROGRESS_INTERVAL_MS: u64 = 500;
+
+/// Files indexed so far, reported on stderr every `PROGRESS_INTERVAL_MS` so
+/// a slow first build isn't silent. Builds that finish sooner print nothing.
+struct BuildProgress {
+    start: Instant,
+    files: AtomicUsize,
+    next_report_ms: AtomicU64,
+}
+
+impl BuildProgress {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            files: AtomicUsize::new(0),
+            next_report_ms: AtomicU64::new(PROGRESS_INTERVAL_MS),
+        }
+    }
+
+    /// Called once per file, possibly from several scan threads.
+    fn tick(&self) {
+        let elapsed_ms = self.start.elapsed().as_millis() as u64;
+        if let Some(files) = self.tick_at(elapsed_ms) {
+            eprintln!("desktop-indexer: indexing... files={files} elapsed_ms={elapsed_ms}");
+        }
+    }
+
+    /// Count a file `elapsed_ms` into the build; the file count when a report
+    /// is due.
+    fn tick_at(&self, elapsed_ms: u64) -> Option<usize> {
+        let files = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        let next = self.next_report_ms.load(Ordering::Relaxed);
+        // Only the thread that moves the deadline forward reports.
+        (elapsed_ms >= next
+            && self
+                .next_report_ms
+                .compare_exchange(
+                    next,
+                    elapsed_ms + PROGRESS_INTERVAL_MS,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok())
+        .then_some(files)
+    }
+}
+
+/// Build the index for `roots` (with the configured `respect_try_exec` and
+/// `de_filter`) on a background thread. The accept loop picks it up from the
+/// channel, so `indexes` stays owned by that loop.
+fn spawn_warmup(roots: Vec<String>) -> mpsc::Receiver<(IndexKey, IndexState)> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let options = SCAN_OPTIONS.get_or_init(ScanOptions::default);
+        let (respect_try_exec, de_filter) = (options.respect_try_exec, options.de_filter);
+        let state = build_index(&roots, respect_try_exec, de_filter);
+        let _ = tx.send(((roots, respect_try_exec, de_filter), state));
+    });
+    rx
+}
+
 fn do_launch(
     entries: &[crate::models::DesktopEntryIndexed],
     desktop_id: &str,
     action: Option<&str>,
+    terminal: Option<Terminal>,
+    args: &[&str],
+    cwd: Option<&str>,
+    env: &[String],
 ) -> Result<(), String> {
     let id = desktop_id.trim_end_matches(".desktop");
 
@@ -404,82 +1363,263 @@ fn do_launch(
         .find(|e| e.out.id == id)
         .ok_or_else(|| format!("Unknown desktop-id: {id}"))?;
 
-    let mut selected_exec = entry.out.exec.as_deref();
-    if let Some(action_id) = action {
-        let act = entry
-            .out
-            .actions
-            .iter()
-            .find(|a| a.id == action_id)
-            .ok_or_else(|| format!("Unknown action '{action_id}' for id={id}"))?;
-        selected_exec = act.exec.as_deref();
-    }
+    let launch_env = LaunchEnv {
+        cwd: working_dir(&entry.out, cwd),
+        vars: env,
+    };
 
     // gtk-launch only supports default action
     if action.is_none()
-        && let Ok(s) = Command::new("gtk-launch").arg(id).status()
+        && let Ok(s) = launch_env
+            .apply(Command::new("gtk-launch").arg(id).args(args))
+            .status()
         && s.success()
     {
         return Ok(());
     }
 
-    if entry.out.terminal {
-        let exec_line =
-            selected_exec.ok_or_else(|| format!("Terminal app but no Exec= for id={id}"))?;
-        let argv = exec_to_argv(exec_line);
-        if argv.is_empty() {
-            return Err(format!("Exec parsed empty for id={id} (Exec={exec_line})"));
-        }
+    if let Some(argv) = resolve_dbus_activation(&entry.out, action, args, &launch_env)
+        && run_status_ok(&argv)
+    {
+        return Ok(());
+    }
 
-        let term = pick_terminal().ok_or_else(|| {
-            "gtk-launch failed and no known terminal found for Terminal=true app. Install one of: foot, kitty, alacritty, wezterm".to_string()
-        })?;
+    let argv = resolve_launch(&entry.out, action, args, terminal)?;
+    let child = launch_env
+        .apply(Command::new(&argv[0]).args(&argv[1..]))
+        .spawn()
+        .map_err(|e| format!("Exec launch failed for id={id}: {e}"))?;
+    record_launch(id, child.id());
+    reap(child);
 
-        match term {
-            Terminal::Foot => {
-                let mut cmd = Command::new("foot");
-                cmd.arg("-e").arg(&argv[0]).args(&argv[1..]);
-                cmd.spawn()
-                    .map_err(|e| format!("Failed to spawn foot: {e}"))?;
-                return Ok(());
-            }
-            Terminal::Kitty => {
-                let mut cmd = Command::new("kitty");
-                cmd.arg(&argv[0]).args(&argv[1..]);
-                cmd.spawn()
-                    .map_err(|e| format!("Failed to spawn kitty: {e}"))?;
-                return Ok(());
-            }
-            Terminal::Alacritty => {
-                let mut cmd = Command::new("alacritty");
-                cmd.arg("-e").arg(&argv[0]).args(&argv[1..]);
-                cmd.spawn()
-                    .map_err(|e| format!("Failed to spawn alacritty: {e}"))?;
-                return Ok(());
-            }
-            Terminal::WezTerm => {
-                let mut cmd = Command::new("wezterm");
-                cmd.args(["start", "--"]).arg(&argv[0]).args(&argv[1..]);
-                cmd.spawn()
-                    .map_err(|e| format!("Failed to spawn wezterm: {e}"))?;
-                return Ok(());
+    Ok(())
+}
+
+/// How often the reaper checks its children while any are running.
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Hand a launched app to the reaper thread, which waits on it once it exits
+/// so it doesn't linger as a zombie until the daemon stops.
+fn reap(child: Child) {
+    static REAPER: OnceLock<mpsc::Sender<Child>> = OnceLock::new();
+    let _ = REAPER.get_or_init(spawn_reaper).send(child);
+}
+
+fn spawn_reaper() -> mpsc::Sender<Child> {
+    let (tx, rx) = mpsc::channel::<Child>();
+    std::thread::spawn(move || {
+        let mut children: Vec<Child> = Vec::new();
+        loop {
+            // Nothing to check: sleep until the next launch.
+            let next = if children.is_empty() {
+                rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+            } else {
+                rx.recv_timeout(REAP_INTERVAL)
+            };
+            match next {
+                Ok(child) => children.push(child),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
             }
+            children.retain_mut(|c| matches!(c.try_wait(), Ok(None)));
+        }
+    });
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_app(dir: &Path, id: &str, name: &str) {
+        std::fs::write(
+            dir.join(format!("{id}.desktop")),
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec={id}\n"),
+        )
+        .unwrap();
+    }
+
+    /// Merge `[A]` and `[B]` into `[A, B]` and compare with a fresh build.
+    fn assert_merge_matches_build(a: &Path, b: &Path) -> Vec<(String, String)> {
+        let root = |p: &Path| p.to_string_lossy().into_owned();
+        let roots = vec![root(a), root(b)];
+        let mut indexes: HashMap<IndexKey, IndexState> = HashMap::new();
+        for r in &roots {
+            let sub = vec![r.clone()];
+            indexes.insert((sub.clone(), true, false), build_index(&sub, true, false));
         }
+
+        let merged = merge_indexes(&indexes, &roots, true, false).expect("roots are covered");
+        let fresh = build_index(&roots, true, false);
+        let listed = |entries: &[DesktopEntryIndexed]| -> Vec<(String, String)> {
+            entries
+                .iter()
+                .map(|e| (e.out.id.clone(), e.out.path.clone()))
+                .collect()
+        };
+        assert_eq!(listed(&merged), listed(&fresh.entries));
+        assert_eq!(merged.claimed, fresh.claimed);
+        listed(&merged)
     }
 
-    let exec_line =
-        selected_exec.ok_or_else(|| format!("Launch failed and no Exec= for id={id}"))?;
-    let argv = exec_to_argv(exec_line);
-    if argv.is_empty() {
-        return Err(format!("Exec parsed empty for id={id} (Exec={exec_line})"));
+    #[test]
+    fn merged_sub_indexes_equal_a_fresh_build() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        write_app(a.path(), "shared", "Shared A");
+        write_app(a.path(), "only-a", "Only A");
+        write_app(b.path(), "shared", "Shared B");
+        write_app(b.path(), "only-b", "Only B");
+
+        let listed = assert_merge_matches_build(a.path(), b.path());
+        let shared = listed.iter().find(|(id, _)| id == "shared").unwrap();
+        assert!(shared.1.starts_with(&*a.path().to_string_lossy()));
+        assert_eq!(listed.len(), 3);
     }
 
-    let mut cmd = Command::new(&argv[0]);
-    if argv.len() > 1 {
-        cmd.args(&argv[1..]);
+    #[test]
+    fn a_broken_copy_in_the_first_root_still_shadows_the_second() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        std::fs::write(a.path().join("shared.desktop"), "not a desktop file\n").unwrap();
+        write_app(a.path(), "only-a", "Only A");
+        write_app(b.path(), "shared", "Shared B");
+
+        let listed = assert_merge_matches_build(a.path(), b.path());
+        assert!(listed.iter().all(|(id, _)| id != "shared"));
+        assert_eq!(listed.len(), 1);
     }
-    cmd.spawn()
-        .map_err(|e| format!("Exec launch failed for id={id}: {e}"))?;
 
-    Ok(())
+    #[test]
+    fn build_progress_reports_once_per_interval() {
+        let progress = BuildProgress::new();
+        let reports: Vec<(u64, usize)> = (0..=1600)
+            .step_by(100)
+            .filter_map(|ms| progress.tick_at(ms).map(|files| (ms, files)))
+            .collect();
+        // 17 files, one every 100 ms: due at 500, then 500 ms after each report.
+        assert_eq!(reports, [(500, 6), (1000, 11), (1500, 16)]);
+    }
+
+    #[test]
+    fn build_progress_reports_once_across_threads() {
+        let progress = BuildProgress::new();
+        let reports = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        if progress.tick_at(PROGRESS_INTERVAL_MS).is_some() {
+                            reports.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(reports.into_inner(), 1);
+        assert_eq!(progress.files.into_inner(), 8000);
+    }
+
+    #[test]
+    fn sd_socket_takes_over_a_passed_listener() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sock");
+        let fd = UnixListener::bind(&path).unwrap().into_raw_fd();
+        let pid = std::process::id().to_string();
+
+        // SAFETY: `fd` was just released by the listener above.
+        let listener = unsafe { sd_socket_from(Some(&pid), Some("1"), fd) }.unwrap();
+        let mut client = UnixStream::connect(&path).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        client.write_all(b"ping\n").unwrap();
+        let mut line = String::new();
+        BufReader::new(&mut server).read_line(&mut line).unwrap();
+        assert_eq!(line, "ping\n");
+
+        // Any Unix socket passes the check, e.g. one end of a socketpair.
+        let (a, _b) = UnixStream::pair().unwrap();
+        // SAFETY: `a` gives up its fd here.
+        let taken = unsafe { sd_socket_from(None, Some("1"), a.into_raw_fd()) };
+        assert!(taken.is_some());
+    }
+
+    #[test]
+    fn sd_socket_ignores_fds_meant_for_others() {
+        // Neither call takes the fd, so any number will do.
+        for (listen_pid, listen_fds) in [
+            (None, None),
+            (Some("1"), Some("1")),
+            (None, Some("2")),
+            (None, Some("0")),
+        ] {
+            // SAFETY: these never reach the fd.
+            let listener = unsafe { sd_socket_from(listen_pid, listen_fds, -1) };
+            assert!(listener.is_none(), "{listen_pid:?} {listen_fds:?}");
+        }
+    }
+
+    #[test]
+    fn sd_socket_leaves_a_non_socket_fd_open() {
+        let file = tempfile::tempfile().unwrap();
+        let fd = file.into_raw_fd();
+        // SAFETY: `fd` was just released by the file above.
+        assert!(unsafe { sd_socket_from(None, Some("1"), fd) }.is_none());
+        // SAFETY: `sd_socket_from` gave the fd back unclosed.
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.write_all(b"still open").unwrap();
+    }
+
+    #[test]
+    fn get_entry_round_trips_through_the_codec() {
+        let req = Request::GetEntry {
+            roots: vec!["/usr/share/applications".to_string()],
+            id: "org.gnome.Nautilus".to_string(),
+            respect_try_exec: true,
+            de_filter: false,
+        };
+        // As `daemon_client` sends it.
+        let line = with_protocol_version(serde_json::to_string(&req).unwrap());
+        match parse_request(line.as_bytes()) {
+            Ok(Request::GetEntry {
+                roots,
+                id,
+                respect_try_exec,
+                de_filter,
+            }) => {
+                assert_eq!(roots, ["/usr/share/applications"]);
+                assert_eq!(id, "org.gnome.Nautilus");
+                assert!(respect_try_exec);
+                assert!(!de_filter);
+            }
+            other => panic!("decoded as {other:?}"),
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        write_app(dir.path(), "org.gnome.Nautilus", "Files");
+        let entry = crate::desktop::parse_desktop_file_using_roots(
+            &dir.path().join("org.gnome.Nautilus.desktop"),
+            &[dir.path().to_path_buf()],
+            Some("C"),
+        )
+        .unwrap()
+        .out;
+        for (entries, want) in [
+            (vec![entry], &["org.gnome.Nautilus"][..]),
+            (Vec::new(), &[]),
+        ] {
+            let (mut server, client) = UnixStream::pair().unwrap();
+            write_response(&mut server, Response::Entries { entries }).unwrap();
+            drop(server);
+            let mut line = String::new();
+            BufReader::new(client).read_line(&mut line).unwrap();
+            match serde_json::from_str::<Response>(&line) {
+                Ok(Response::Entries { entries }) => {
+                    let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+                    assert_eq!(ids, want);
+                    assert!(entries.iter().all(|e| e.name.as_deref() == Some("Files")));
+                }
+                other => panic!("decoded as {other:?}"),
+            }
+        }
+    }
 }
@@ -1,28 +1,522 @@
-use crate::ipc::{Request, Response};
+use crate::ipc::{IPC_VERSION, Request, Response, with_protocol_version};
 use crate::xdg::socket_path;
+use serde::Deserialize;
 use std::{
-    io::{BufRead, BufReader, Write},
+    io::{self, BufRead, BufReader, BufWriter, Write},
     os::unix::net::UnixStream,
-    time::Duration,
+    path::Path,
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 
-pub fn try_request(req: &Request) -> Option<Response> {
-    let path = socket_path();
-    let stream = UnixStream::connect(&path).ok()?;
-    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
-    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+/// Socket timeouts for daemon requests. Past them the client gives up and
+/// commands fall back to local mode.
+#[derive(Debug, Clone, Copy)]
+pub struct DaemonTimeouts {
+    pub write: Duration,
+    pub read: Duration,
+}
+
+impl Default for DaemonTimeouts {
+    fn default() -> Self {
+        Self {
+            write: Duration::from_millis(2000),
+            read: Duration::from_millis(5000),
+        }
+    }
+}
+
+impl DaemonTimeouts {
+    /// Same timeout for writes and reads.
+    pub fn from_millis(ms: u64) -> Self {
+        Self {
+            write: Duration::from_millis(ms),
+            read: Duration::from_millis(ms),
+        }
+    }
+
+    fn apply(&self, stream: &UnixStream) {
+        let _ = stream.set_write_timeout(Some(self.write));
+        let _ = stream.set_read_timeout(Some(self.read));
+    }
+}
+
+/// Attempts made when the daemon drops the connection while it is starting.
+const CONNECT_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled after each one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// `Ping` carrying this client's protocol version.
+fn ping_request() -> Request {
+    Request::Ping {
+        protocol_version: IPC_VERSION,
+    }
+}
+
+/// Why a daemon request produced no response.
+#[derive(Debug)]
+pub enum ClientError {
+    /// No daemon is listening on the socket; commands fall back to local mode.
+    NotRunning,
+    /// The daemon speaks another IPC protocol (already warned about).
+    Incompatible,
+    Io(io::Error),
+    Protocol(serde_json::Error),
+    Timeout,
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::NotRunning => write!(f, "daemon not running"),
+            ClientError::Incompatible => write!(f, "daemon speaks another IPC protocol"),
+            ClientError::Io(e) => write!(f, "i/o error: {e}"),
+            ClientError::Protocol(e) => write!(f, "invalid response: {e}"),
+            ClientError::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => ClientError::NotRunning,
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ClientError::Timeout,
+            _ => ClientError::Io(e),
+        }
+    }
+}
+
+impl ClientError {
+    /// A missing or incompatible daemon: the normal reasons to fall back to
+    /// local mode, not worth reporting.
+    pub fn is_expected(&self) -> bool {
+        matches!(self, ClientError::NotRunning | ClientError::Incompatible)
+    }
+
+    /// Report unexpected failures on stderr.
+    pub fn log(&self) {
+        if !self.is_expected() {
+            eprintln!("desktop-indexer: daemon request failed: {self}");
+        }
+    }
+}
+
+/// What a response tells about the daemon's protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DaemonVersion {
+    /// This client's `IPC_VERSION`.
+    Same,
+    /// Another version; `None` for a daemon that predates versioning and
+    /// could not parse the request.
+    Other(Option<u32>),
+    /// A daemon that predates versioning and understood the request.
+    Unknown,
+}
+
+/// Whether the daemon speaks this client's `IPC_VERSION`, once a response has
+/// told. Requests that get no response leave it unset, so a daemon that was
+/// not running yet is checked again by the next request.
+static COMPATIBLE: OnceLock<bool> = OnceLock::new();
+
+/// `req` as a JSON line carrying this client's `protocol_version`. Daemons
+/// refuse requests of another version; older ones ignore the field.
+fn request_line(req: &Request) -> Result<String, serde_json::Error> {
+    let json = serde_json::to_string(req)?;
+    let json = match req {
+        // Carries it already.
+        Request::Ping { .. } => json,
+        _ => with_protocol_version(json),
+    };
+    Ok(json + "\n")
+}
+
+/// Decode a response line, and the daemon version it reports.
+fn parse_response(line: &str) -> Result<(Response, DaemonVersion), ClientError> {
+    let value: serde_json::Value =
+        serde_json::from_str(line.trim()).map_err(ClientError::Protocol)?;
+    let version = value
+        .get("protocol_version")
+        .and_then(serde_json::Value::as_u64);
+    let resp = Response::deserialize(value).map_err(ClientError::Protocol)?;
+
+    let daemon = match (version, &resp) {
+        (Some(v), _) if v == u64::from(IPC_VERSION) => DaemonVersion::Same,
+        (Some(v), _) => DaemonVersion::Other(u32::try_from(v).ok()),
+        // Only a daemon without versioning answers unversioned, and it can't
+        // parse requests added since.
+        (None, Response::Error { message }) if message.starts_with("invalid request:") => {
+            DaemonVersion::Other(None)
+        }
+        (None, _) => DaemonVersion::Unknown,
+    };
+    Ok((resp, daemon))
+}
+
+/// Remember what `daemon` says about the daemon's version. On a mismatch a
+/// warning is printed once and requests fall back to local mode; `Ping`,
+/// `Status` and `Shutdown` still go through so an old daemon can be inspected
+/// and stopped.
+fn check_version(
+    req: &Request,
+    resp: Response,
+    daemon: DaemonVersion,
+) -> Result<Response, ClientError> {
+    match daemon {
+        DaemonVersion::Same => {
+            let _ = COMPATIBLE.set(true);
+            Ok(resp)
+        }
+        DaemonVersion::Unknown => Ok(resp),
+        DaemonVersion::Other(version) => {
+            if COMPATIBLE.set(false).is_ok() {
+                match version {
+                    Some(v) => eprintln!(
+                        "desktop-indexer: daemon speaks IPC protocol {v}, this client {IPC_VERSION}; using local mode (run `desktop-indexer daemon restart`)"
+                    ),
+                    None => eprintln!(
+                        "desktop-indexer: daemon predates IPC protocol {IPC_VERSION}; using local mode (run `desktop-indexer daemon restart`)"
+                    ),
+                }
+            }
+            if req.is_version_independent() {
+                Ok(resp)
+            } else {
+                Err(ClientError::Incompatible)
+            }
+        }
+    }
+}
+
+/// Whether an earlier response showed the daemon speaks another protocol.
+fn known_incompatible() -> bool {
+    COMPATIBLE.get() == Some(&false)
+}
+
+pub fn try_request(req: &Request, timeouts: DaemonTimeouts) -> Result<Response, ClientError> {
+    if known_incompatible() && !req.is_version_independent() {
+        return Err(ClientError::Incompatible);
+    }
+    let (resp, daemon) = send_request(&socket_path(), req, timeouts)?;
+    check_version(req, resp, daemon)
+}
+
+fn send_request(
+    socket: &Path,
+    req: &Request,
+    timeouts: DaemonTimeouts,
+) -> Result<(Response, DaemonVersion), ClientError> {
+    let line = request_line(req).map_err(ClientError::Protocol)?;
+
+    // A daemon that just bound its socket may accept the connection and then
+    // drop it before it is ready; retry those instead of falling back to local.
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    let resp_line = loop {
+        match exchange(socket, &line, timeouts) {
+            Ok(resp_line) => break resp_line,
+            Err(e)
+                if attempt < CONNECT_ATTEMPTS
+                    && matches!(
+                        e.kind(),
+                        io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset
+                    ) =>
+            {
+                if crate::commands::common::timing_enabled() {
+                    eprintln!(
+                        "desktop-indexer timing(client): retry attempt={} after {e} (backoff={backoff:?})",
+                        attempt + 1
+                    );
+                }
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    if resp_line.trim().is_empty() {
+        return Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    parse_response(&resp_line)
+}
 
-    let mut stream = stream;
-    let line = serde_json::to_string(req).ok()? + "\n";
-    stream.write_all(line.as_bytes()).ok()?;
-    stream.flush().ok()?;
+/// One connect/write/read round-trip; returns the raw response line.
+fn exchange(socket: &Path, line: &str, timeouts: DaemonTimeouts) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    timeouts.apply(&stream);
+
+    stream.write_all(line.as_bytes())?;
+    stream.flush()?;
 
     let mut reader = BufReader::new(stream);
     let mut resp_line = String::new();
-    reader.read_line(&mut resp_line).ok()?;
-    if resp_line.trim().is_empty() {
-        return None;
+    reader.read_line(&mut resp_line)?;
+    Ok(resp_line)
+}
+
+/// Whether a daemon answers on the socket (a `Ping` round-trip).
+pub fn ping() -> bool {
+    matches!(
+        try_request(&ping_request(), DaemonTimeouts::from_millis(250)),
+        Ok(Response::Pong { .. })
+    )
+}
+
+/// Send `reqs` as one `Request::Batch` over a single connection.
+/// Responses come back in request order.
+pub fn try_batch_request(
+    reqs: &[Request],
+    timeouts: DaemonTimeouts,
+) -> Result<Vec<Response>, ClientError> {
+    let batch = Request::Batch {
+        requests: reqs.to_vec(),
+    };
+    match try_request(&batch, timeouts)? {
+        Response::Batch { responses } if responses.len() == reqs.len() => Ok(responses),
+        _ => Err(ClientError::Protocol(serde::de::Error::custom(
+            "batch response does not match the request",
+        ))),
+    }
+}
+
+/// Connections idle longer than this are pinged before reuse.
+const STALE_AFTER: Duration = Duration::from_millis(500);
+
+/// A daemon connection reused across requests.
+///
+/// The daemon drops idle connections after a short timeout, so a connection
+/// that sat idle is checked with a `Ping` first, and a request that could not
+/// be written is retried once on a fresh connection.
+pub struct DaemonClient {
+    reader: BufReader<UnixStream>,
+    writer: BufWriter<UnixStream>,
+    timeouts: DaemonTimeouts,
+    last_used: Instant,
+}
+
+/// Why `DaemonClient::send` got no response.
+enum SendError {
+    /// The request never reached the daemon; safe to resend.
+    Unsent,
+    /// The daemon may have acted on the request.
+    Sent,
+}
+
+impl DaemonClient {
+    pub fn connect(timeouts: DaemonTimeouts) -> Option<Self> {
+        if known_incompatible() {
+            return None;
+        }
+        let stream = UnixStream::connect(socket_path()).ok()?;
+        timeouts.apply(&stream);
+
+        let writer = BufWriter::new(stream.try_clone().ok()?);
+        Some(Self {
+            reader: BufReader::new(stream),
+            writer,
+            timeouts,
+            last_used: Instant::now(),
+        })
+    }
+
+    pub fn request(&mut self, req: &Request) -> Option<Response> {
+        if self.last_used.elapsed() >= STALE_AFTER && !self.is_alive() {
+            *self = Self::connect(self.timeouts)?;
+        }
+
+        match self.send(req) {
+            Ok(resp) => Some(resp),
+            Err(SendError::Unsent) => {
+                *self = Self::connect(self.timeouts)?;
+                self.send(req).ok()
+            }
+            // Resending could run a launch twice. A late response would also
+            // answer the next request, so start over on a new connection.
+            Err(SendError::Sent) => {
+                if let Some(fresh) = Self::connect(self.timeouts) {
+                    *self = fresh;
+                }
+                None
+            }
+        }
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.send(&ping_request()), Ok(Response::Pong { .. }))
+    }
+
+    fn send(&mut self, req: &Request) -> Result<Response, SendError> {
+        self.last_used = Instant::now();
+        let line = request_line(req).map_err(|_| SendError::Unsent)?;
+        self.writer
+            .write_all(line.as_bytes())
+            .and_then(|()| self.writer.flush())
+            .map_err(|_| SendError::Unsent)?;
+
+        let mut resp_line = String::new();
+        self.reader
+            .read_line(&mut resp_line)
+            .map_err(|_| SendError::Sent)?;
+        if resp_line.trim().is_empty() {
+            return Err(SendError::Sent);
+        }
+
+        let (resp, daemon) = parse_response(&resp_line).map_err(|_| SendError::Sent)?;
+        check_version(req, resp, daemon).map_err(|_| SendError::Sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::thread::JoinHandle;
+
+    /// A one-shot daemon at a temp socket: answers the first request with
+    /// `reply` and hands back the request line it got.
+    fn mock_daemon(reply: &'static str) -> (tempfile::TempDir, PathBuf, JoinHandle<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            writeln!(reader.get_mut(), "{reply}").unwrap();
+            line
+        });
+        (dir, socket, server)
+    }
+
+    #[test]
+    fn request_carries_client_protocol_version() {
+        let (_dir, socket, server) = mock_daemon(r#"{"type":"launched-pids","pids":[]}"#);
+        send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        let sent: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(sent["cmd"], "list-pids");
+        assert_eq!(sent["protocol_version"], IPC_VERSION);
+    }
+
+    #[test]
+    fn old_daemon_version_is_reported() {
+        let (_dir, socket, server) =
+            mock_daemon(r#"{"type":"launched-pids","pids":[],"protocol_version":0}"#);
+        let (_, daemon) =
+            send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        server.join().unwrap();
+        assert_eq!(daemon, DaemonVersion::Other(Some(0)));
+    }
+
+    #[test]
+    fn unversioned_parse_error_means_daemon_predates_versioning() {
+        let (_dir, socket, server) = mock_daemon(
+            r#"{"type":"error","message":"invalid request: unknown variant `list-pids`"}"#,
+        );
+        let (_, daemon) =
+            send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        server.join().unwrap();
+        assert_eq!(daemon, DaemonVersion::Other(None));
+    }
+
+    #[test]
+    fn busy_daemon_says_nothing_about_compatibility() {
+        let (_dir, socket, server) = mock_daemon(r#"{"type":"error","message":"busy"}"#);
+        let (resp, daemon) =
+            send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        server.join().unwrap();
+        assert!(matches!(resp, Response::Error { message } if message == "busy"));
+        assert_eq!(daemon, DaemonVersion::Unknown);
     }
 
-    serde_json::from_str::<Response>(resp_line.trim()).ok()
+    #[test]
+    fn same_version_is_recognized() {
+        let (_dir, socket, server) =
+            mock_daemon(r#"{"type":"launched-pids","pids":[],"protocol_version":1}"#);
+        let (_, daemon) =
+            send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        server.join().unwrap();
+        assert_eq!(daemon, DaemonVersion::Same);
+    }
+
+    #[test]
+    fn missing_daemon_is_not_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = send_request(
+            &dir.path().join("daemon.sock"),
+            &Request::ListPids,
+            DaemonTimeouts::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ClientError::NotRunning));
+    }
+
+    #[test]
+    fn incompatible_daemon_still_answers_status() {
+        let other = DaemonVersion::Other(Some(0));
+        assert!(check_version(&Request::Status, Response::Ok, other).is_ok());
+        assert!(matches!(
+            check_version(&Request::ListPids, Response::Ok, other),
+            Err(ClientError::Incompatible)
+        ));
+    }
+
+    #[test]
+    fn dropped_first_connection_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            // Close the first connection with the request unread, like a
+            // daemon that is not ready yet: the client sees a reset.
+            let (first, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            drop(first);
+
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            writeln!(reader.get_mut(), r#"{{"type":"launched-pids","pids":[]}}"#).unwrap();
+            line
+        });
+
+        let (resp, _) =
+            send_request(&socket, &Request::ListPids, DaemonTimeouts::default()).unwrap();
+        assert!(matches!(resp, Response::LaunchedPids { .. }));
+        let sent: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(sent["cmd"], "list-pids");
+    }
+
+    #[test]
+    fn slow_daemon_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            // Answer well after the client's read timeout.
+            std::thread::sleep(Duration::from_millis(600));
+            let _ = writeln!(reader.get_mut(), r#"{{"type":"ok"}}"#);
+        });
+
+        let start = Instant::now();
+        let err = send_request(
+            &socket,
+            &Request::ListPids,
+            DaemonTimeouts::from_millis(100),
+        )
+        .unwrap_err();
+        let elapsed = start.elapsed();
+        server.join().unwrap();
+        assert!(matches!(err, ClientError::Timeout), "{err}");
+        assert!(!err.is_expected());
+        assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+    }
 }
@@ -1,11 +1,16 @@
 use crate::cache;
 use crate::models::{
-    DesktopActionOut, DesktopEntryIndexed, DesktopEntryOut, ParsedScanResult, ScanResult,
+    DesktopActionOut, DesktopEntryIndexed, DesktopEntryOut, ParsedScanResult, ScanOptions,
+    ScanProgress, ScanResult,
 };
+use crate::normalize::{is_cjk, normalize_unicode};
+use crate::scan_sources::EntrySource;
+use rayon::prelude::*;
 use std::{
     collections::{BTreeMap, HashSet},
-    fs,
+    fmt, fs, io,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
     time::{Duration, Instant},
 };
 use walkdir::WalkDir;
@@ -17,8 +22,12 @@ fn timing_enabled() -> bool {
     )
 }
 
-pub fn scan_desktop_files(scan_roots: &[PathBuf], limit: Option<usize>) -> ScanResult {
-    let (found_count, paths) = scan_desktop_paths(scan_roots, limit);
+pub fn scan_desktop_files(
+    scan_roots: &[PathBuf],
+    limit: Option<usize>,
+    options: &ScanOptions,
+) -> ScanResult {
+    let (found_count, paths) = scan_desktop_paths(scan_roots, limit, options);
     let files = paths
         .into_iter()
         .map(|(_root, p)| p.to_string_lossy().to_string())
@@ -34,13 +43,29 @@ pub fn scan_desktop_files(scan_roots: &[PathBuf], limit: Option<usize>) -> ScanR
     }
 }
 
+/// Set once the legacy JSON cache has been cleaned up in this process.
+static LEGACY_CACHE_MIGRATED: AtomicBool = AtomicBool::new(false);
+
 pub fn scan_and_parse_desktop_files(
     scan_roots: &[PathBuf],
     limit: Option<usize>,
-    respect_try_exec: bool,
+    options: &ScanOptions,
 ) -> ParsedScanResult {
+    scan_and_parse_desktop_files_with_progress(scan_roots, limit, options, None)
+}
+
+/// `scan_and_parse_desktop_files`, calling `progress` once per file (after id
+/// dedup) as it is parsed or taken from the cache. With `parallel` the calls
+/// come from worker threads, in no particular order.
+pub fn scan_and_parse_desktop_files_with_progress(
+    scan_roots: &[PathBuf],
+    limit: Option<usize>,
+    options: &ScanOptions,
+    progress: Option<&(dyn Fn(ScanProgress) + Sync)>,
+) -> ParsedScanResult {
+    let parallel = options.parallel;
     let t_scan = Instant::now();
-    let (found_count, paths) = scan_desktop_paths(scan_roots, limit);
+    let (found_count, paths) = scan_desktop_paths(scan_roots, limit, options);
     let dur_scan = t_scan.elapsed();
 
     let roots_key: Vec<String> = scan_roots
@@ -48,63 +73,105 @@ pub fn scan_and_parse_desktop_files(
         .map(|p| p.to_string_lossy().to_string())
         .collect();
 
+    let locale_prefs = locale_prefs(options.locale.as_deref());
+
+    let threads = if parallel {
+        rayon::current_num_threads()
+    } else {
+        1
+    };
+
+    // Resolve ids up front: the first root wins, which is inherently sequential.
+    // Roots come highest-priority first (see `xdg::build_scan_roots`), so a user
+    // copy shadows the system one, including a user copy with `Hidden=true`.
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    let jobs: Vec<(&Path, &Path, String)> = paths
+        .iter()
+        .filter_map(|(root, p)| {
+            let id = compute_desktop_id(root, p);
+            seen_ids
+                .insert(id.clone())
+                .then_some((root.as_path(), p.as_path(), id))
+        })
+        .collect();
+    let resolved_ids: Vec<(String, usize)> = jobs
+        .iter()
+        .map(|(root, _, id)| {
+            let pos = scan_roots.iter().position(|r| r == root).unwrap_or(0);
+            (id.clone(), pos)
+        })
+        .collect();
+
     // Cache only when we are building a full index.
-    if limit.is_none() {
+    if limit.is_none() && !options.no_cache {
+        if !LEGACY_CACHE_MIGRATED.swap(true, Ordering::Relaxed) {
+            cache::migrate_legacy(&roots_key);
+        }
+
         let t_load = Instant::now();
-        let cache_index = cache::load(&roots_key);
+        // Resolved names depend on the locales, so each preference list gets its
+        // own cache file.
+        let mut cache_key = roots_key.clone();
+        if !locale_prefs.is_empty() {
+            cache_key.push(format!("locale={}", locale_prefs.join(":")));
+        }
+        let cache_index = cache::load(&cache_key);
         let dur_load = t_load.elapsed();
-        let cache_path = cache::cache_file_path(&roots_key);
-
-        let mut entries: Vec<DesktopEntryIndexed> = Vec::with_capacity(paths.len());
-        let mut parse_failed: usize = 0;
-        let mut new_cache_entries: Vec<cache::CachedEntry> = Vec::with_capacity(paths.len());
-
-        let mut cache_hits: usize = 0;
-        let mut reparsed: usize = 0;
-        let mut meta_missing: usize = 0;
+        let cache_path = cache::cache_file_path(&cache_key);
 
         let t_work = Instant::now();
 
-        let mut seen_ids: HashSet<String> = HashSet::new();
-
-        for (root, p) in &paths {
-            let id = compute_desktop_id(root, p);
-            if !seen_ids.insert(id.clone()) {
-                continue;
+        // Workers only read the cache; results are merged below in path order.
+        let work = |(root, p, id): &(&Path, &Path, String)| {
+            let outcome = index_one(root, p, id.clone(), &cache_index, &locale_prefs);
+            if let Some(report) = progress {
+                let err = match &outcome {
+                    IndexOutcome::Uncached(Err(e)) | IndexOutcome::Failed(e) => Some(e),
+                    _ => None,
+                };
+                report_progress(report, p, id, err);
             }
+            outcome
+        };
+        let outcomes: Vec<IndexOutcome> = if parallel {
+            jobs.par_iter().map(work).collect()
+        } else {
+            jobs.iter().map(work).collect()
+        };
 
-            let Some((size, mtime_sec)) = cache::meta_for(p) else {
-                meta_missing += 1;
-                match parse_desktop_file_with_id(p, id) {
-                    Some(entry) => {
-                        entries.push(entry.clone());
-                        // No metadata => don't cache
-                    }
-                    None => parse_failed += 1,
-                }
-                continue;
-            };
+        let mut entries: Vec<DesktopEntryIndexed> = Vec::with_capacity(outcomes.len());
+        let mut parse_errors: Vec<String> = Vec::new();
+        let mut new_cache_entries: Vec<cache::CachedEntry> = Vec::with_capacity(outcomes.len());
 
-            let p_str = p.to_string_lossy().to_string();
-            if let Some(ce) = cache_index.by_path.get(&p_str)
-                && cache::is_fresh(ce, size, mtime_sec)
-            {
-                entries.push(ce.entry.clone());
-                new_cache_entries.push(ce.clone());
-                cache_hits += 1;
-                continue;
-            }
+        let mut cache_hits: usize = 0;
+        let mut reparsed: usize = 0;
+        let mut meta_missing: usize = 0;
 
-            match parse_desktop_file_with_id(p, id) {
-                Some(entry) => {
-                    entries.push(entry.clone());
-                    let ce = cache::cached_entry(p, entry, size, mtime_sec);
+        for ((_, p, _), outcome) in jobs.iter().zip(outcomes) {
+            match outcome {
+                IndexOutcome::CacheHit(ce) => {
+                    entries.push(ce.entry.clone());
+                    new_cache_entries.push(ce);
+                    cache_hits += 1;
+                }
+                IndexOutcome::Parsed(ce) => {
+                    entries.push(ce.entry.clone());
                     new_cache_entries.push(ce);
                     reparsed += 1;
                 }
-                None => parse_failed += 1,
+                IndexOutcome::Uncached(Ok(entry)) => {
+                    // No metadata => don't cache
+                    entries.push(entry);
+                    meta_missing += 1;
+                }
+                IndexOutcome::Uncached(Err(e)) => {
+                    meta_missing += 1;
+                    parse_errors.push(parse_error_line(p, &e));
+                }
+                IndexOutcome::Failed(e) => parse_errors.push(parse_error_line(p, &e)),
             }
         }
+        let parse_failed = parse_errors.len();
 
         let dur_work = t_work.elapsed();
 
@@ -116,21 +183,35 @@ pub fn scan_and_parse_desktop_files(
             || reparsed > 0
             || (meta_missing == 0 && parse_failed == 0 && prev_cached_paths != new_cached_paths);
 
-        let dur_save = if should_save_cache {
+        // A failed save (read-only cache dir, full disk) leaves the index
+        // intact; the timing line reports it instead of a duration.
+        let save_cache = if should_save_cache {
             let t_save = Instant::now();
-            cache::save(&roots_key, new_cache_entries);
-            t_save.elapsed()
+            match cache::save(&cache_key, new_cache_entries) {
+                Ok(()) => format!("{:?}", t_save.elapsed()),
+                Err(e) => {
+                    eprintln!(
+                        "desktop-indexer: failed to write cache {}: {e} (--no-cache skips it)",
+                        cache_path.display()
+                    );
+                    format!("err({})", e.kind())
+                }
+            }
         } else {
-            Duration::ZERO
+            format!("{:?}", Duration::ZERO)
         };
 
         if timing_enabled() {
+            for line in &parse_errors {
+                eprintln!("desktop-indexer timing: parse_error {line}");
+            }
             eprintln!(
-                "desktop-indexer timing: scan={:?} load_cache={:?} work={:?} save_cache={:?} paths={} found_count={} cache_hits={} reparsed={} meta_missing={} parse_failed={} cache_file={}",
+                "desktop-indexer timing: scan={:?} load_cache={:?} work={:?} save_cache={} threads={} paths={} found_count={} cache_hits={} reparsed={} meta_missing={} parse_failed={} cache_file={}",
                 dur_scan,
                 dur_load,
                 dur_work,
-                dur_save,
+                save_cache,
+                threads,
                 paths.len(),
                 found_count,
                 cache_hits,
@@ -141,85 +222,204 @@ pub fn scan_and_parse_desktop_files(
             );
         }
 
-        let entries = if respect_try_exec {
-            entries
-                .into_iter()
-                .filter(|e| {
-                    e.out
-                        .try_exec
-                        .as_deref()
-                        .map(is_try_exec_available)
-                        .unwrap_or(true)
-                })
-                .collect()
-        } else {
-            entries
-        };
+        let entries = filter_entries(entries, options.respect_try_exec, options.de_filter);
+        let entries = filter_ignored(entries, &options.ignore);
 
         return ParsedScanResult {
             scanned_roots: roots_key,
             found_count,
             parsed_count: entries.len(),
             parse_failed,
+            parse_errors,
             entries,
+            resolved_ids,
         };
     }
 
-    let mut entries: Vec<DesktopEntryIndexed> = Vec::new();
-    let mut parse_failed: usize = 0;
-
     let t_parse = Instant::now();
 
-    let mut seen_ids: HashSet<String> = HashSet::new();
-
-    for (root, p) in &paths {
-        let id = compute_desktop_id(root, p);
-        if !seen_ids.insert(id.clone()) {
-            continue;
+    let parse = |(root, p, id): &(&Path, &Path, String)| {
+        let entry = parse_desktop_file_with_id(p, id.clone(), root, &locale_prefs);
+        if let Some(report) = progress {
+            report_progress(report, p, id, entry.as_ref().err());
         }
+        let mut entry = entry.map_err(|e| parse_error_line(p, &e))?;
+        entry.out.mtime_sec = cache::meta_for(p).map(|m| m.mtime_sec);
+        Ok(entry)
+    };
+    let parsed: Vec<Result<DesktopEntryIndexed, String>> = if parallel {
+        jobs.par_iter().map(parse).collect()
+    } else {
+        jobs.iter().map(parse).collect()
+    };
 
-        match parse_desktop_file_with_id(p, id) {
-            Some(entry) => entries.push(entry),
-            None => parse_failed += 1,
+    let mut entries: Vec<DesktopEntryIndexed> = Vec::with_capacity(parsed.len());
+    let mut parse_errors: Vec<String> = Vec::new();
+    for r in parsed {
+        match r {
+            Ok(entry) => entries.push(entry),
+            Err(line) => parse_errors.push(line),
         }
     }
+    let parse_failed = parse_errors.len();
 
     if timing_enabled() {
+        for line in &parse_errors {
+            eprintln!("desktop-indexer timing: parse_error {line}");
+        }
         eprintln!(
-            "desktop-indexer timing: scan={:?} parse={:?} paths={} found_count={} parsed={} parse_failed={} (cache disabled due to limit)",
+            "desktop-indexer timing: scan={:?} parse={:?} threads={} paths={} found_count={} parsed={} parse_failed={} (cache disabled: {})",
             dur_scan,
             t_parse.elapsed(),
+            threads,
             paths.len(),
             found_count,
             entries.len(),
-            parse_failed
+            parse_failed,
+            if options.no_cache {
+                "--no-cache"
+            } else {
+                "limit"
+            }
         );
     }
 
-    let entries = if respect_try_exec {
-        entries
-            .into_iter()
-            .filter(|e| {
-                e.out
-                    .try_exec
-                    .as_deref()
-                    .map(is_try_exec_available)
-                    .unwrap_or(true)
-            })
-            .collect()
-    } else {
-        entries
-    };
+    let entries = filter_entries(entries, options.respect_try_exec, options.de_filter);
+    let entries = filter_ignored(entries, &options.ignore);
 
     ParsedScanResult {
         scanned_roots: roots_key,
         found_count,
         parsed_count: entries.len(),
         parse_failed,
+        parse_errors,
         entries,
+        resolved_ids,
+    }
+}
+
+fn report_progress(
+    report: &(dyn Fn(ScanProgress) + Sync),
+    path: &Path,
+    id: &str,
+    err: Option<&ParseError>,
+) {
+    report(match err {
+        None => ScanProgress::Ok { id: id.to_string() },
+        Some(e) => ScanProgress::Failed {
+            path: path.to_string_lossy().to_string(),
+            reason: e.to_string(),
+        },
+    });
+}
+
+fn parse_error_line(path: &Path, err: &ParseError) -> String {
+    format!("{}: {err}", path.display())
+}
+
+enum IndexOutcome {
+    /// Fresh cache entry reused as-is.
+    CacheHit(cache::CachedEntry),
+    /// File (re)parsed; the new cache entry replaces any stale one.
+    Parsed(cache::CachedEntry),
+    /// File metadata unavailable, so the result is not cacheable.
+    Uncached(Result<DesktopEntryIndexed, ParseError>),
+    Failed(ParseError),
+}
+
+fn index_one(
+    root: &Path,
+    p: &Path,
+    id: String,
+    cache_index: &cache::CacheIndex,
+    locale_prefs: &[String],
+) -> IndexOutcome {
+    let Some(meta) = cache::meta_for(p) else {
+        return IndexOutcome::Uncached(parse_desktop_file_with_id(p, id, root, locale_prefs));
+    };
+
+    let p_str = p.to_string_lossy();
+    if let Some(ce) = cache_index.by_path.get(p_str.as_ref())
+        && cache::is_fresh(ce, meta)
+    {
+        return IndexOutcome::CacheHit(ce.clone());
+    }
+
+    match parse_desktop_file_with_id(p, id, root, locale_prefs) {
+        Ok(entry) => IndexOutcome::Parsed(cache::cached_entry(p, entry, meta)),
+        Err(e) => IndexOutcome::Failed(e),
     }
 }
 
+fn filter_entries(
+    entries: Vec<DesktopEntryIndexed>,
+    respect_try_exec: bool,
+    de_filter: bool,
+) -> Vec<DesktopEntryIndexed> {
+    if !respect_try_exec && !de_filter {
+        return entries;
+    }
+
+    let desktops = if de_filter {
+        current_desktops()
+    } else {
+        Vec::new()
+    };
+
+    entries
+        .into_iter()
+        .filter(|e| {
+            !respect_try_exec
+                || e.out
+                    .try_exec
+                    .as_deref()
+                    .map(is_try_exec_available)
+                    .unwrap_or(true)
+        })
+        .filter(|e| !de_filter || is_shown_in(&e.out, &desktops))
+        .collect()
+}
+
+/// Drop entries whose desktop-id is in `ignore` (ids without `.desktop`).
+pub fn filter_ignored(
+    entries: Vec<DesktopEntryIndexed>,
+    ignore: &HashSet<String>,
+) -> Vec<DesktopEntryIndexed> {
+    if ignore.is_empty() {
+        return entries;
+    }
+    entries
+        .into_iter()
+        .filter(|e| !ignore.contains(&e.out.id))
+        .collect()
+}
+
+/// `XDG_CURRENT_DESKTOP` entries, as used by `--de-filter`. The variable is a
+/// colon-separated list, e.g. `GNOME:Unity`.
+pub fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn is_shown_in(out: &DesktopEntryOut, desktops: &[String]) -> bool {
+    let matches = |list: &[String]| {
+        list.iter()
+            .any(|de| desktops.iter().any(|d| d.eq_ignore_ascii_case(de)))
+    };
+
+    // Per spec, OnlyShowIn entries are hidden unless one of the current desktops is listed.
+    if !out.only_show_in.is_empty() && !matches(&out.only_show_in) {
+        return false;
+    }
+
+    !matches(&out.not_show_in)
+}
+
 fn is_try_exec_available(try_exec: &str) -> bool {
     // Spec says TryExec is an executable name/path; some files might still include
     // quoting or whitespace, so parse best-effort.
@@ -286,12 +486,86 @@ fn is_executable_file(path: &Path) -> bool {
     }
 }
 
+fn locale_prefs(override_locale: Option<&str>) -> Vec<String> {
+    match override_locale {
+        Some(locale) => preferred_locales_for(locale),
+        None => preferred_locales(),
+    }
+}
+
+/// Locale preference list from `DESKTOP_INDEXER_LOCALES`, else from
+/// `LC_ALL` > `LC_MESSAGES` > `LANG`.
+pub fn preferred_locales() -> Vec<String> {
+    if let Ok(list) = std::env::var("DESKTOP_INDEXER_LOCALES") {
+        let prefs = preferred_locales_from_list(&list);
+        if !prefs.is_empty() {
+            return prefs;
+        }
+    }
+
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|s| preferred_locales_for(&s))
+        .find(|prefs| !prefs.is_empty())
+        .unwrap_or_default()
+}
+
+/// Colon-separated locales, highest priority first: `fr_FR:de` =>
+/// `["fr_FR", "fr", "de"]`. Empty segments and segments with spaces are skipped.
+pub fn preferred_locales_from_list(list: &str) -> Vec<String> {
+    let mut ordered: Vec<String> = Vec::new();
+    for segment in list.split(':') {
+        if segment.is_empty() || segment.contains(char::is_whitespace) {
+            continue;
+        }
+        for loc in preferred_locales_for(segment) {
+            if !ordered.contains(&loc) {
+                ordered.push(loc);
+            }
+        }
+    }
+    ordered
+}
+
+/// Locale preference list for an explicit locale: `fr_FR.UTF-8` => `["fr_FR", "fr"]`.
+pub fn preferred_locales_for(locale: &str) -> Vec<String> {
+    let s = locale.trim();
+    // drop encoding and modifiers: fr_FR.UTF-8@euro => fr_FR
+    let s = s.split('.').next().unwrap_or(s);
+    let loc = s.split('@').next().unwrap_or(s);
+    if loc.is_empty() {
+        return Vec::new();
+    }
+
+    // Exact locale match first.
+    let mut ordered = vec![loc.to_string()];
+    // language part fallback: fr_FR -> fr, pt_BR -> pt; hyphen variant too: fr-FR -> fr
+    for sep in ['_', '-'] {
+        if let Some((lang, _)) = loc.split_once(sep)
+            && !lang.is_empty()
+            && !ordered.iter().any(|p| p == lang)
+        {
+            ordered.push(lang.to_string());
+        }
+    }
+    ordered
+}
+
 pub fn parse_desktop_file_using_roots(
     path: &Path,
     applications_roots: &[PathBuf],
-) -> Option<DesktopEntryIndexed> {
+    override_locale: Option<&str>,
+) -> Result<DesktopEntryIndexed, ParseError> {
     let id = desktop_file_id_using_roots(path, applications_roots);
-    parse_desktop_file_with_id(path, id)
+    let root = applications_roots
+        .iter()
+        .find(|root| path.starts_with(root))
+        .map(PathBuf::as_path)
+        .or_else(|| path.parent())
+        .unwrap_or(Path::new(""));
+    let locale_prefs = locale_prefs(override_locale);
+    parse_desktop_file_with_id(path, id, root, &locale_prefs)
 }
 
 pub fn desktop_file_id_using_roots(path: &Path, applications_roots: &[PathBuf]) -> String {
@@ -307,9 +581,205 @@ pub fn desktop_file_id_using_roots(path: &Path, applications_roots: &[PathBuf])
         .to_string()
 }
 
-fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryIndexed> {
+/// Parse a spec boolean (`true`/`false`, leniently also `1`/`0`/`yes`/`no`).
+pub fn parse_bool(v: &str) -> Option<bool> {
+    match v.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn split_list(v: &str) -> Vec<String> {
+    // Spec uses ';' separated lists, often ending with ';'. `\;` is a ';'
+    // inside an item.
+    let mut items: Vec<String> = Vec::new();
+    let mut item = String::new();
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => item.push(';'),
+                Some(next) => {
+                    item.push('\\');
+                    item.push(next);
+                }
+                None => item.push('\\'),
+            },
+            ';' => items.push(std::mem::take(&mut item)),
+            c => item.push(c),
+        }
+    }
+    items.push(item);
+    items
+        .iter()
+        .map(|s| unescape_value(s.trim()))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Decode the spec's string escapes (`\s`, `\n`, `\t`, `\r`, `\\`). Any
+/// other backslash is kept as written.
+fn unescape_value(v: &str) -> String {
+    if !v.contains('\\') {
+        return v.to_string();
+    }
+    let mut out = String::with_capacity(v.len());
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+pub fn split_key_locale(key: &str) -> (&str, Option<&str>) {
+    // "Name[fr_FR]" => ("Name", Some("fr_FR"))
+    let Some((base, rest)) = key.split_once('[') else {
+        return (key, None);
+    };
+    let locale = rest.strip_suffix(']');
+    match locale {
+        Some(loc) if !loc.is_empty() => (base, Some(loc)),
+        _ => (key, None),
+    }
+}
+
+/// Every `[Desktop Entry]` key with all of its values, keyed by locale
+/// (`None` for the untranslated value). Nothing is resolved or dropped.
+pub fn parse_desktop_file_all_locales(
+    path: &Path,
+) -> Option<BTreeMap<String, BTreeMap<Option<String>, String>>> {
     let data = fs::read_to_string(path).ok()?;
 
+    let mut fields: BTreeMap<String, BTreeMap<Option<String>, String>> = BTreeMap::new();
+    let mut in_entry = false;
+
+    for raw_line in data.lines() {
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+
+        if !in_entry {
+            continue;
+        }
+
+        let Some((key_raw, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key_raw = key_raw.trim();
+        if key_raw.is_empty() {
+            continue;
+        }
+
+        let (key, locale) = split_key_locale(key_raw);
+        fields
+            .entry(key.to_string())
+            .or_default()
+            .insert(locale.map(str::to_string), value.trim().to_string());
+    }
+
+    Some(fields)
+}
+
+/// Fields for a generated `Type=Application` entry (`desktop-indexer write`).
+#[derive(Debug, Clone, Default)]
+pub struct WriteOpts {
+    pub name: String,
+    pub exec: String,
+    pub categories: Vec<String>,
+    pub icon: Option<String>,
+    pub terminal: bool,
+}
+
+/// Render `opts` as a `.desktop` file. Values are escaped per the spec
+/// (`\\`, `\n`, `\t`, `\r`, and `\;` inside list items).
+pub fn write_desktop_entry(opts: &WriteOpts) -> String {
+    fn escape(v: &str) -> String {
+        let mut out = String::with_capacity(v.len());
+        for c in v.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    let mut out = String::from("[Desktop Entry]\nVersion=1.0\nType=Application\n");
+    out.push_str(&format!("Name={}\n", escape(&opts.name)));
+    out.push_str(&format!("Exec={}\n", escape(&opts.exec)));
+    if let Some(icon) = &opts.icon {
+        out.push_str(&format!("Icon={}\n", escape(icon)));
+    }
+    out.push_str(&format!("Terminal={}\n", opts.terminal));
+    if !opts.categories.is_empty() {
+        out.push_str("Categories=");
+        for c in &opts.categories {
+            out.push_str(&escape(c).replace(';', "\\;"));
+            out.push(';');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Why a `.desktop` file was left out of the index.
+#[derive(Debug)]
+pub enum ParseError {
+    /// Unreadable, or not UTF-8.
+    Io(io::Error),
+    NoDesktopEntrySection,
+    /// A key the entry's `Type` requires: `Name` (unless `GenericName` is set),
+    /// or `Exec` for applications.
+    MissingRequiredField(&'static str),
+    /// `Type=` other than Application, Link or Directory.
+    UnsupportedType(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{e}"),
+            ParseError::NoDesktopEntrySection => write!(f, "missing [Desktop Entry] group"),
+            ParseError::MissingRequiredField(key) => write!(f, "missing required {key} key"),
+            ParseError::UnsupportedType(t) => write!(f, "unsupported Type={t}"),
+        }
+    }
+}
+
+fn parse_desktop_file_with_id(
+    path: &Path,
+    id: String,
+    source_root: &Path,
+    locale_prefs: &[String],
+) -> Result<DesktopEntryIndexed, ParseError> {
+    let data = fs::read_to_string(path).map_err(ParseError::Io)?;
+
     #[derive(Default)]
     struct LocalizedField {
         default: Option<String>,
@@ -339,95 +809,6 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         }
     }
 
-    fn parse_bool(v: &str) -> Option<bool> {
-        match v.trim().to_ascii_lowercase().as_str() {
-            "true" | "1" | "yes" => Some(true),
-            "false" | "0" | "no" => Some(false),
-            _ => None,
-        }
-    }
-
-    fn split_list(v: &str) -> Vec<String> {
-        // Spec uses ';' separated lists, often ending with ';'
-        v.split(';')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect()
-    }
-
-    fn preferred_locales() -> Vec<String> {
-        // Prefer LC_ALL > LC_MESSAGES > LANG
-        fn clean_locale(s: &str) -> Option<String> {
-            let s = s.trim();
-            if s.is_empty() {
-                return None;
-            }
-            // drop encoding and modifiers: fr_FR.UTF-8@euro => fr_FR
-            let s = s.split('.').next().unwrap_or(s);
-            let s = s.split('@').next().unwrap_or(s);
-            if s.is_empty() {
-                None
-            } else {
-                Some(s.to_string())
-            }
-        }
-
-        let raw = std::env::var("LC_ALL")
-            .ok()
-            .and_then(|s| clean_locale(&s))
-            .or_else(|| {
-                std::env::var("LC_MESSAGES")
-                    .ok()
-                    .and_then(|s| clean_locale(&s))
-            })
-            .or_else(|| std::env::var("LANG").ok().and_then(|s| clean_locale(&s)));
-
-        let Some(loc) = raw else {
-            return Vec::new();
-        };
-
-        let mut prefs = Vec::new();
-        // Exact locale match first.
-        prefs.push(loc.clone());
-        // language part fallback: fr_FR -> fr, pt_BR -> pt
-        if let Some((lang, _)) = loc.split_once('_')
-            && !lang.is_empty()
-        {
-            prefs.push(lang.to_string());
-        }
-        // hyphen variant fallback too: fr-FR -> fr
-        if let Some((lang, _)) = loc.split_once('-')
-            && !lang.is_empty()
-        {
-            prefs.push(lang.to_string());
-        }
-
-        prefs.sort();
-        prefs.dedup();
-        // Keep determinism (sort+dedup) but ensure the exact match stays first.
-        let mut ordered = Vec::new();
-        ordered.push(loc);
-        for p in prefs {
-            if !ordered.contains(&p) {
-                ordered.push(p);
-            }
-        }
-        ordered
-    }
-
-    fn split_key_locale(key: &str) -> (&str, Option<&str>) {
-        // "Name[fr_FR]" => ("Name", Some("fr_FR"))
-        let Some((base, rest)) = key.split_once('[') else {
-            return (key, None);
-        };
-        let locale = rest.strip_suffix(']');
-        match locale {
-            Some(loc) if !loc.is_empty() => (base, Some(loc)),
-            _ => (key, None),
-        }
-    }
-
     enum Section {
         None,
         DesktopEntry,
@@ -435,9 +816,8 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         Other,
     }
 
-    let locale_prefs = preferred_locales();
-
     let mut section = Section::None;
+    let mut has_entry_section = false;
 
     let mut name = LocalizedField::default();
     let mut generic_name = LocalizedField::default();
@@ -455,6 +835,8 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
     let mut startup_notify: Option<bool> = None;
     let mut nodisplay: Option<bool> = None;
     let mut hidden: Option<bool> = None;
+    let mut dbus_activatable: Option<bool> = None;
+    let mut working_dir: Option<String> = None;
     let mut only_show_in: Vec<String> = Vec::new();
     let mut not_show_in: Vec<String> = Vec::new();
     let mut extra: BTreeMap<String, String> = BTreeMap::new();
@@ -479,6 +861,7 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         if line.starts_with('[') && line.ends_with(']') {
             if line == "[Desktop Entry]" {
                 section = Section::DesktopEntry;
+                has_entry_section = true;
             } else if let Some(rest) = line.strip_prefix("[Desktop Action ") {
                 if let Some(action_id) = rest.strip_suffix(']') {
                     section = Section::DesktopAction(action_id.trim().to_string());
@@ -501,19 +884,22 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         };
 
         let key_raw = key_raw.trim();
-        let value = value_raw.trim();
         if key_raw.is_empty() {
             continue;
         }
+        // Lists (and `Keywords`, split later) keep their escapes for `split_list`.
+        let list = value_raw.trim();
+        let unescaped = unescape_value(list);
+        let value = unescaped.as_str();
 
         let (key, locale) = split_key_locale(key_raw);
 
         match &mut section {
             Section::DesktopEntry => {
                 match key {
-                    "Name" => name.set(locale, value, &locale_prefs),
-                    "GenericName" => generic_name.set(locale, value, &locale_prefs),
-                    "Comment" => comment.set(locale, value, &locale_prefs),
+                    "Name" => name.set(locale, value, locale_prefs),
+                    "GenericName" => generic_name.set(locale, value, locale_prefs),
+                    "Comment" => comment.set(locale, value, locale_prefs),
                     "Icon" => {
                         if locale.is_none() {
                             icon = Some(value.to_string())
@@ -536,18 +922,18 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
                     }
                     "Categories" => {
                         if locale.is_none() {
-                            categories = split_list(value)
+                            categories = split_list(list)
                         }
                     }
-                    "Keywords" => keywords.set(locale, value, &locale_prefs),
+                    "Keywords" => keywords.set(locale, list, locale_prefs),
                     "MimeType" => {
                         if locale.is_none() {
-                            mime_types = split_list(value)
+                            mime_types = split_list(list)
                         }
                     }
                     "Actions" => {
                         if locale.is_none() {
-                            actions_list = split_list(value)
+                            actions_list = split_list(list)
                         }
                     }
                     "Type" => {
@@ -575,14 +961,24 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
                             hidden = parse_bool(value)
                         }
                     }
+                    "Path" => {
+                        if locale.is_none() && !value.is_empty() {
+                            working_dir = Some(value.to_string())
+                        }
+                    }
+                    "DBusActivatable" => {
+                        if locale.is_none() {
+                            dbus_activatable = parse_bool(value)
+                        }
+                    }
                     "OnlyShowIn" => {
                         if locale.is_none() {
-                            only_show_in = split_list(value)
+                            only_show_in = split_list(list)
                         }
                     }
                     "NotShowIn" => {
                         if locale.is_none() {
-                            not_show_in = split_list(value)
+                            not_show_in = split_list(list)
                         }
                     }
                     _ => {
@@ -600,7 +996,7 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
                     .or_insert_with(|| (LocalizedField::default(), None, None, BTreeMap::new()));
 
                 match key {
-                    "Name" => entry.0.set(locale, value, &locale_prefs),
+                    "Name" => entry.0.set(locale, value, locale_prefs),
                     "Icon" => {
                         if locale.is_none() {
                             entry.1 = Some(value.to_string());
@@ -625,6 +1021,27 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         }
     }
 
+    if !has_entry_section {
+        return Err(ParseError::NoDesktopEntrySection);
+    }
+    // A `Hidden=true` copy only has to shadow the entry with the same id, so
+    // it needs nothing else.
+    if hidden != Some(true) {
+        // `GenericName=` alone still gives the entry something to show (see
+        // `DesktopEntryOut::display_name`).
+        if name.resolve().is_none() && generic_name.resolve().is_none() {
+            return Err(ParseError::MissingRequiredField("Name"));
+        }
+        match type_.as_deref() {
+            // D-Bus activatable apps may omit Exec.
+            Some("Application") if exec.is_none() && dbus_activatable != Some(true) => {
+                return Err(ParseError::MissingRequiredField("Exec"));
+            }
+            None | Some("Application" | "Link" | "Directory") => {}
+            Some(other) => return Err(ParseError::UnsupportedType(other.to_string())),
+        }
+    }
+
     let resolved_keywords = keywords
         .resolve()
         .map(|s| split_list(&s))
@@ -635,18 +1052,19 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
     let mut seen = std::collections::BTreeSet::<String>::new();
 
     for aid in &actions_list {
-        if let Some((lname, aicon, aexec, _extra)) = actions.get(aid) {
+        if let Some((lname, aicon, aexec, aextra)) = actions.get(aid) {
             action_out.push(DesktopActionOut {
                 id: aid.clone(),
                 name: lname.resolve(),
                 icon: aicon.clone(),
                 exec: aexec.clone(),
+                extra: aextra.clone(),
             });
             seen.insert(aid.clone());
         }
     }
 
-    for (aid, (lname, aicon, aexec, _extra)) in &actions {
+    for (aid, (lname, aicon, aexec, aextra)) in &actions {
         if seen.contains(aid) {
             continue;
         }
@@ -655,9 +1073,12 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
             name: lname.resolve(),
             icon: aicon.clone(),
             exec: aexec.clone(),
+            extra: aextra.clone(),
         });
     }
 
+    let source = EntrySource::detect(exec.as_deref());
+
     let out = DesktopEntryOut {
         id,
         name: name.resolve(),
@@ -676,15 +1097,23 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
         startup_notify,
         nodisplay,
         hidden,
+        working_dir,
+        dbus_activatable,
         only_show_in,
         not_show_in,
+        path: absolute_path_string(path),
+        source_root: absolute_path_string(source_root),
+        source,
+        // Filled in from the file metadata by the scan.
+        mtime_sec: None,
+        extra,
     };
 
-    let id_lc = out.id.to_lowercase();
-    let name_lc = out.name.as_deref().map(|s| s.to_lowercase());
+    let id_lc = normalize_unicode(&out.id);
+    let name_lc = out.name_or_generic_name().map(normalize_unicode);
     let norm = make_norm(&out);
 
-    Some(DesktopEntryIndexed {
+    Ok(DesktopEntryIndexed {
         out,
         norm,
         id_lc,
@@ -692,7 +1121,14 @@ fn parse_desktop_file_with_id(path: &Path, id: String) -> Option<DesktopEntryInd
     })
 }
 
-fn is_desktop_file(path: &Path) -> bool {
+pub fn absolute_path_string(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn is_desktop_file(path: &Path) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
         .map(|ext| ext.eq_ignore_ascii_case("desktop"))
@@ -702,6 +1138,7 @@ fn is_desktop_file(path: &Path) -> bool {
 fn scan_desktop_paths(
     scan_roots: &[PathBuf],
     limit: Option<usize>,
+    options: &ScanOptions,
 ) -> (usize, Vec<(PathBuf, PathBuf)>) {
     let mut found_count: usize = 0;
     let mut paths: Vec<(PathBuf, PathBuf)> = Vec::new();
@@ -711,11 +1148,15 @@ fn scan_desktop_paths(
             continue;
         }
 
-        for entry in WalkDir::new(root)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
+        // Sorted so the order (and so which duplicate id wins) doesn't depend on
+        // the filesystem's directory order.
+        let mut walk = WalkDir::new(root)
+            .follow_links(options.follow_symlinks)
+            .sort_by_file_name();
+        if let Some(depth) = options.max_depth {
+            walk = walk.max_depth(depth);
+        }
+        for entry in walk.into_iter().filter_map(|e| e.ok()) {
             if !entry.file_type().is_file() {
                 continue;
             }
@@ -760,8 +1201,14 @@ fn make_norm(out: &DesktopEntryOut) -> String {
     push_norm(&mut s, out.name.as_deref());
     push_norm(&mut s, out.generic_name.as_deref());
     push_norm(&mut s, out.comment.as_deref());
-    push_norm(&mut s, out.exec.as_deref());
-    push_norm(&mut s, out.try_exec.as_deref());
+    push_norm(
+        &mut s,
+        out.exec.as_deref().map(clean_exec_for_norm).as_deref(),
+    );
+    push_norm(
+        &mut s,
+        out.try_exec.as_deref().map(clean_exec_for_norm).as_deref(),
+    );
     push_norm(&mut s, out.icon.as_deref());
 
     for c in &out.categories {
@@ -776,16 +1223,36 @@ fn make_norm(out: &DesktopEntryOut) -> String {
     for a in &out.actions {
         push_norm(&mut s, Some(&a.id));
         push_norm(&mut s, a.name.as_deref());
-        push_norm(&mut s, a.exec.as_deref());
+        push_norm(
+            &mut s,
+            a.exec.as_deref().map(clean_exec_for_norm).as_deref(),
+        );
         push_norm(&mut s, a.icon.as_deref());
     }
 
     push_norm(&mut s, out.type_.as_deref());
     push_norm(&mut s, out.startup_wm_class.as_deref());
 
+    // `path`/`source_root` stay out: directory names would match unrelated queries.
+    // So does `extra`: vendor keys are mostly IDs and flags.
     s
 }
 
+/// Path segments too common in `Exec=` lines to mean anything in a search.
+const EXEC_NOISE_SEGMENTS: &[&str] = &["usr", "bin", "lib", "local", "opt"];
+
+/// The searchable words of an `Exec=`/`TryExec=` value: flags (`--no-splash`),
+/// field codes (`%U`) and common path segments (`/usr/bin/`) are dropped, and the
+/// remaining path segments become separate words.
+fn clean_exec_for_norm(exec: &str) -> String {
+    exec.split_whitespace()
+        .filter(|arg| !arg.starts_with('-') && !arg.starts_with('%'))
+        .flat_map(|arg| arg.split('/'))
+        .filter(|seg| !seg.is_empty() && !EXEC_NOISE_SEGMENTS.contains(seg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn push_norm(dst: &mut String, v: Option<&str>) {
     let Some(x) = v else {
         return;
@@ -795,10 +1262,503 @@ fn push_norm(dst: &mut String, v: Option<&str>) {
         dst.push(' ');
     }
 
-    // Avoid allocating a temporary lowercased String.
-    for ch in x.chars() {
-        for lc in ch.to_lowercase() {
-            dst.push(lc);
+    if !x.chars().any(is_cjk) {
+        crate::normalize::push_normalized(dst, x);
+        return;
+    }
+
+    // Space every ideograph off from its neighbours so each one starts a word,
+    // like the single-character query tokens from `search::tokenize_cjk`.
+    let mut prev_cjk = false;
+    for ch in normalize_unicode(x).chars() {
+        let cjk = is_cjk(ch);
+        if (cjk || prev_cjk) && !dst.is_empty() && !dst.ends_with(' ') {
+            dst.push(' ');
+        }
+        dst.push(ch);
+        prev_cjk = cjk;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_app(dir: &Path, rel: &str, name: &str) {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            path,
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec={name}\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parallel_scan_yields_same_ids_as_sequential() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        for i in 0..40 {
+            write_app(
+                system.path(),
+                &format!("app{i:02}.desktop"),
+                &format!("App {i}"),
+            );
+        }
+        write_app(system.path(), "kde/dolphin.desktop", "Dolphin");
+        // Shadows the system copy.
+        write_app(user.path(), "app07.desktop", "User App");
+        fs::write(system.path().join("broken.desktop"), "Name=No group\n").unwrap();
+
+        let roots = [user.path().to_path_buf(), system.path().to_path_buf()];
+        let scan = |parallel| {
+            let options = ScanOptions {
+                parallel,
+                no_cache: true,
+                ..ScanOptions::default()
+            };
+            scan_and_parse_desktop_files(&roots, None, &options)
+        };
+        let sequential = scan(false);
+        let parallel = scan(true);
+
+        let ids = |r: &ParsedScanResult| -> Vec<String> {
+            r.entries.iter().map(|e| e.out.id.clone()).collect()
+        };
+        assert_eq!(ids(&sequential), ids(&parallel));
+        assert_eq!(sequential.entries.len(), 41);
+        assert!(ids(&sequential).contains(&"kde-dolphin".to_string()));
+        assert_eq!(sequential.parse_failed, parallel.parse_failed);
+
+        let app07 = |r: &ParsedScanResult| {
+            r.entries
+                .iter()
+                .find(|e| e.out.id == "app07")
+                .and_then(|e| e.out.name.clone())
+        };
+        assert_eq!(app07(&sequential).as_deref(), Some("User App"));
+        assert_eq!(app07(&parallel).as_deref(), Some("User App"));
+    }
+
+    #[test]
+    fn ignored_ids_are_left_out_of_the_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        write_app(dir.path(), "firefox.desktop", "Firefox");
+        write_app(dir.path(), "org.gnome.Totem.desktop", "Videos");
+
+        let options = ScanOptions {
+            ignore: HashSet::from(["org.gnome.Totem".to_string()]),
+            no_cache: true,
+            ..ScanOptions::default()
+        };
+        let result = scan_and_parse_desktop_files(&[dir.path().to_path_buf()], None, &options);
+        let ids: Vec<&str> = result.entries.iter().map(|e| e.out.id.as_str()).collect();
+        assert_eq!(ids, ["firefox"]);
+    }
+
+    fn parse(contents: &str) -> Result<DesktopEntryIndexed, ParseError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.desktop");
+        fs::write(&path, contents).unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C"))
+    }
+
+    #[test]
+    fn parse_error_io_for_missing_or_non_utf8_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.desktop");
+        let err = parse_desktop_file_using_roots(&missing, &[dir.path().to_path_buf()], None);
+        assert!(matches!(err, Err(ParseError::Io(_))));
+
+        let latin1 = dir.path().join("latin1.desktop");
+        fs::write(&latin1, b"[Desktop Entry]\nName=Caf\xe9\n").unwrap();
+        let err = parse_desktop_file_using_roots(&latin1, &[dir.path().to_path_buf()], None);
+        assert!(matches!(err, Err(ParseError::Io(_))));
+    }
+
+    #[test]
+    fn unknown_keys_land_in_extra() {
+        let e = parse(
+            "[Desktop Entry]\nType=Application\nName=Firefox\nExec=firefox %u\n\
+             Actions=private;\nX-GNOME-FullName=Firefox Web Browser\n\
+             X-GNOME-FullName[fr]=Navigateur Firefox\n\
+             [Desktop Action private]\nName=Private Window\nExec=firefox --private-window\n\
+             X-Flatpak-Id=org.mozilla.firefox\n",
+        )
+        .unwrap();
+
+        // Localized variants are dropped; only the base value is kept.
+        assert_eq!(
+            e.out.extra,
+            BTreeMap::from([(
+                "X-GNOME-FullName".to_string(),
+                "Firefox Web Browser".to_string()
+            )])
+        );
+        assert_eq!(
+            e.out.actions[0].extra,
+            BTreeMap::from([(
+                "X-Flatpak-Id".to_string(),
+                "org.mozilla.firefox".to_string()
+            )])
+        );
+        // Vendor keys stay out of the search text.
+        assert!(!e.norm.contains("web"), "{}", e.norm);
+        assert!(!e.norm.contains("mozilla"), "{}", e.norm);
+    }
+
+    #[test]
+    fn path_key_sets_the_working_dir() {
+        let with_path = |path: &str| {
+            parse(&format!(
+                "[Desktop Entry]\nType=Application\nName=Files\nExec=files\n{path}"
+            ))
+            .unwrap()
+            .out
+            .working_dir
+        };
+        assert_eq!(
+            with_path("Path=/srv/files\n").as_deref(),
+            Some("/srv/files")
+        );
+        assert_eq!(
+            with_path("Path=/srv/My\\sFiles\n").as_deref(),
+            Some("/srv/My Files")
+        );
+        // Empty or localized values don't count, and `Path` never lands in `extra`.
+        assert_eq!(with_path("Path=\n"), None);
+        assert_eq!(with_path("Path[fr]=/srv/fichiers\n"), None);
+        assert_eq!(with_path(""), None);
+
+        let e = parse("[Desktop Entry]\nType=Application\nName=Files\nExec=files\nPath=/srv\n")
+            .unwrap();
+        assert!(e.out.extra.is_empty());
+        // Directory names stay out of the search text.
+        assert!(!e.norm.contains("srv"), "{}", e.norm);
+    }
+
+    #[test]
+    fn parse_error_without_desktop_entry_group() {
+        let err = parse("[Desktop Action new]\nName=New\nExec=app\n");
+        assert!(matches!(err, Err(ParseError::NoDesktopEntrySection)));
+    }
+
+    #[test]
+    fn parse_error_for_missing_required_fields() {
+        let err = parse("[Desktop Entry]\nType=Application\nExec=app\n");
+        assert!(matches!(err, Err(ParseError::MissingRequiredField("Name"))));
+
+        let err = parse("[Desktop Entry]\nType=Application\nName=App\n");
+        assert!(matches!(err, Err(ParseError::MissingRequiredField("Exec"))));
+
+        // D-Bus activatable apps may omit Exec.
+        let ok = parse("[Desktop Entry]\nType=Application\nName=App\nDBusActivatable=true\n");
+        assert!(ok.is_ok());
+    }
+
+    #[test]
+    fn parse_error_for_unsupported_type() {
+        let err = parse("[Desktop Entry]\nType=Service\nName=Daemon\nExec=daemon\n");
+        assert!(matches!(err, Err(ParseError::UnsupportedType(t)) if t == "Service"));
+    }
+
+    #[test]
+    fn generic_name_stands_in_for_missing_name() {
+        let e = parse("[Desktop Entry]\nType=Application\nGenericName=Web Browser\nExec=browser\n")
+            .unwrap();
+        assert_eq!(e.out.name, None);
+        assert_eq!(e.name_lc.as_deref(), Some("web browser"));
+    }
+
+    #[test]
+    fn hidden_entry_needs_no_other_keys() {
+        let e = parse("[Desktop Entry]\nHidden=true\n").unwrap();
+        assert_eq!(e.out.hidden, Some(true));
+    }
+
+    #[test]
+    fn written_entry_parses_back_to_the_same_fields() {
+        let opts = WriteOpts {
+            name: "Notes \\ Two\nLines".into(),
+            exec: "notes --title \"a\tb\" %F".into(),
+            categories: vec!["Office".into(), "A;B".into(), "C\\D".into()],
+            icon: Some("notes-icon".into()),
+            terminal: true,
+        };
+        let e = parse(&write_desktop_entry(&opts)).unwrap();
+        assert_eq!(e.out.name.as_deref(), Some(opts.name.as_str()));
+        assert_eq!(e.out.exec.as_deref(), Some(opts.exec.as_str()));
+        assert_eq!(e.out.icon, opts.icon);
+        assert_eq!(e.out.categories, opts.categories);
+        assert!(e.out.terminal);
+        assert_eq!(e.out.type_.as_deref(), Some("Application"));
+
+        let plain = WriteOpts {
+            name: "Plain".into(),
+            exec: "plain".into(),
+            ..WriteOpts::default()
+        };
+        let e = parse(&write_desktop_entry(&plain)).unwrap();
+        assert_eq!(e.out.name.as_deref(), Some("Plain"));
+        assert_eq!(e.out.icon, None);
+        assert!(e.out.categories.is_empty());
+        assert!(!e.out.terminal);
+    }
+
+    #[test]
+    fn list_items_and_values_are_unescaped() {
+        assert_eq!(split_list(r"A\;B;C\\;D\sE;"), ["A;B", "C\\", "D E"]);
+        assert_eq!(unescape_value(r"a\nb\tc\\d\qe"), "a\nb\tc\\d\\qe");
+    }
+
+    #[test]
+    fn exec_flags_and_path_noise_stay_out_of_the_norm() {
+        assert_eq!(
+            clean_exec_for_norm("/usr/bin/vlc --no-qt-splash --started-from-file %U"),
+            "vlc"
+        );
+        assert_eq!(
+            clean_exec_for_norm("/opt/Tools/bin/tool -v %f extra"),
+            "Tools tool extra"
+        );
+        let e = parse(
+            "[Desktop Entry]\nType=Application\nName=VLC\nExec=/usr/bin/vlc --no-splash %U\n",
+        )
+        .unwrap();
+        assert!(!e.norm.contains("splash"));
+        assert!(!e.norm.contains("usr"));
+    }
+
+    #[test]
+    fn only_show_in_and_not_show_in_follow_the_current_desktops() {
+        let kde_only =
+            parse("[Desktop Entry]\nType=Application\nName=K\nExec=k\nOnlyShowIn=KDE;\n")
+                .unwrap()
+                .out;
+        let not_gnome =
+            parse("[Desktop Entry]\nType=Application\nName=N\nExec=n\nNotShowIn=GNOME;\n")
+                .unwrap()
+                .out;
+        let anywhere = parse("[Desktop Entry]\nType=Application\nName=A\nExec=a\n")
+            .unwrap()
+            .out;
+        let desktops = |list: &[&str]| list.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+
+        let gnome = desktops(&["GNOME"]);
+        assert!(!is_shown_in(&kde_only, &gnome));
+        assert!(!is_shown_in(&not_gnome, &gnome));
+        assert!(is_shown_in(&anywhere, &gnome));
+
+        // Any desktop of a colon-separated list counts, case-insensitively.
+        assert!(is_shown_in(&kde_only, &desktops(&["Unity", "kde"])));
+        assert!(!is_shown_in(&not_gnome, &desktops(&["Unity", "gnome"])));
+        assert!(is_shown_in(&not_gnome, &desktops(&["KDE"])));
+    }
+
+    const BILINGUAL: &str = "[Desktop Entry]\nType=Application\nName=Files\nName[fr]=Fichiers\n\
+                             Name[de_DE]=Dateien\nExec=files\n";
+
+    /// Parse `contents` with an explicit locale, as `--locale` does.
+    fn parse_in(contents: &str, locale: &str) -> DesktopEntryIndexed {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.desktop");
+        fs::write(&path, contents).unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some(locale)).unwrap()
+    }
+
+    #[test]
+    fn explicit_locale_falls_back_to_its_language() {
+        assert_eq!(preferred_locales_for("fr_FR.UTF-8@euro"), ["fr_FR", "fr"]);
+        assert_eq!(preferred_locales_for("pt-BR"), ["pt-BR", "pt"]);
+        assert_eq!(preferred_locales_for("fr"), ["fr"]);
+        assert!(preferred_locales_for(" ").is_empty());
+
+        assert_eq!(
+            parse_in(BILINGUAL, "fr").out.name.as_deref(),
+            Some("Fichiers")
+        );
+        assert_eq!(
+            parse_in(BILINGUAL, "fr_CA").out.name.as_deref(),
+            Some("Fichiers")
+        );
+        assert_eq!(parse_in(BILINGUAL, "es").out.name.as_deref(), Some("Files"));
+    }
+
+    #[test]
+    fn locale_list_keeps_priority_and_skips_bad_segments() {
+        assert_eq!(preferred_locales_from_list("de_DE"), ["de_DE", "de"]);
+        assert_eq!(
+            preferred_locales_from_list("fr_FR:fr:en"),
+            ["fr_FR", "fr", "en"]
+        );
+        assert_eq!(
+            preferred_locales_from_list(":de_DE::fr FR:de:en_US.UTF-8"),
+            ["de_DE", "de", "en_US", "en"]
+        );
+        assert!(preferred_locales_from_list("").is_empty());
+        assert!(preferred_locales_from_list(": :").is_empty());
+    }
+
+    #[test]
+    fn first_root_wins_for_a_shared_id() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        write_app(user.path(), "editor.desktop", "User Editor");
+        write_app(system.path(), "editor.desktop", "System Editor");
+        // Deeper in the system root, but the same desktop-id.
+        write_app(system.path(), "org/viewer.desktop", "System Viewer");
+        write_app(user.path(), "org-viewer.desktop", "User Viewer");
+
+        let names = |roots: &[PathBuf]| -> Vec<(String, String)> {
+            let options = ScanOptions {
+                no_cache: true,
+                ..ScanOptions::default()
+            };
+            let mut found: Vec<(String, String)> =
+                scan_and_parse_desktop_files(roots, None, &options)
+                    .entries
+                    .into_iter()
+                    .map(|e| (e.out.id, e.out.name.unwrap_or_default()))
+                    .collect();
+            found.sort();
+            found
+        };
+        let pair = |id: &str, name: &str| (id.to_string(), name.to_string());
+
+        let user_first = [user.path().to_path_buf(), system.path().to_path_buf()];
+        assert_eq!(
+            names(&user_first),
+            [
+                pair("editor", "User Editor"),
+                pair("org-viewer", "User Viewer")
+            ]
+        );
+        let system_first = [system.path().to_path_buf(), user.path().to_path_buf()];
+        assert_eq!(
+            names(&system_first),
+            [
+                pair("editor", "System Editor"),
+                pair("org-viewer", "System Viewer")
+            ]
+        );
+    }
+
+    #[test]
+    fn max_depth_stops_the_walk_early() {
+        let root = tempfile::tempdir().unwrap();
+        // One app per level: level1.desktop, a/level2.desktop, ..., a/b/c/d/level5.desktop.
+        let mut dir = String::new();
+        for (level, sub) in ["", "a/", "b/", "c/", "d/"].iter().enumerate() {
+            dir.push_str(sub);
+            write_app(
+                root.path(),
+                &format!("{dir}level{}.desktop", level + 1),
+                &format!("Level {}", level + 1),
+            );
+        }
+
+        let found = |max_depth| {
+            let options = ScanOptions {
+                max_depth,
+                ..ScanOptions::default()
+            };
+            let (count, paths) = scan_desktop_paths(&[root.path().to_path_buf()], None, &options);
+            assert_eq!(count, paths.len());
+            let mut names: Vec<String> = paths
+                .into_iter()
+                .map(|(_, p)| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(found(Some(1)), ["level1.desktop"]);
+        assert_eq!(found(Some(2)), ["level1.desktop", "level2.desktop"]);
+        assert_eq!(found(Some(5)).len(), 5);
+        assert_eq!(found(None).len(), 5);
+    }
+
+    #[test]
+    fn symlinks_are_followed_only_when_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        write_app(root.path(), "plain.desktop", "Plain");
+        write_app(elsewhere.path(), "target.desktop", "Linked File");
+        write_app(elsewhere.path(), "sub/inner.desktop", "Linked Dir");
+        std::os::unix::fs::symlink(
+            elsewhere.path().join("target.desktop"),
+            root.path().join("linked.desktop"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(elsewhere.path().join("sub"), root.path().join("dir")).unwrap();
+        // A loop back to the root must not hang the walk.
+        std::os::unix::fs::symlink(root.path(), root.path().join("loop")).unwrap();
+
+        let ids = |follow_symlinks| {
+            let options = ScanOptions {
+                follow_symlinks,
+                no_cache: true,
+                ..ScanOptions::default()
+            };
+            let mut ids: Vec<String> =
+                scan_and_parse_desktop_files(&[root.path().to_path_buf()], None, &options)
+                    .entries
+                    .into_iter()
+                    .map(|e| e.out.id)
+                    .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids(false), ["plain"]);
+        assert_eq!(ids(true), ["dir-inner", "linked", "plain"]);
+    }
+
+    #[test]
+    fn progress_reports_each_indexed_file_once() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        write_app(user.path(), "editor.desktop", "Editor");
+        write_app(user.path(), "org/viewer.desktop", "Viewer");
+        fs::write(
+            user.path().join("broken.desktop"),
+            "[Desktop Entry]\nType=Application\nName=Broken\n",
+        )
+        .unwrap();
+        // Shadowed by the user copy, so never parsed or reported.
+        write_app(system.path(), "editor.desktop", "System Editor");
+        write_app(system.path(), "player.desktop", "Player");
+        let roots = [user.path().to_path_buf(), system.path().to_path_buf()];
+
+        for parallel in [false, true] {
+            let reports = std::sync::Mutex::new(Vec::new());
+            let record = |p: ScanProgress| reports.lock().unwrap().push(p);
+            let options = ScanOptions {
+                parallel,
+                no_cache: true,
+                ..ScanOptions::default()
+            };
+            let result =
+                scan_and_parse_desktop_files_with_progress(&roots, None, &options, Some(&record));
+            assert_eq!(result.entries.len(), 3);
+
+            let mut ok: Vec<String> = Vec::new();
+            let mut failed: Vec<(String, String)> = Vec::new();
+            for report in reports.into_inner().unwrap() {
+                match report {
+                    ScanProgress::Ok { id } => ok.push(id),
+                    ScanProgress::Failed { path, reason } => failed.push((path, reason)),
+                }
+            }
+            ok.sort();
+            assert_eq!(
+                ok,
+                ["editor", "org-viewer", "player"],
+                "parallel={parallel}"
+            );
+            assert_eq!(failed.len(), 1, "parallel={parallel}");
+            assert!(failed[0].0.ends_with("broken.desktop"));
+            assert!(failed[0].1.contains("Exec"), "{}", failed[0].1);
         }
     }
 }
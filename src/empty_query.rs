@@ -8,4 +8,10 @@ pub enum EmptyQueryMode {
     Recency,
     #[value(name = "frequency")]
     Frequency,
+    /// Frequency weighted by recency of last use.
+    #[value(name = "frecency")]
+    Frecency,
+    /// Every entry, A–Z by name (then id); ignores usage data.
+    #[value(name = "all-apps")]
+    AllApps,
 }
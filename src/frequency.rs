@@ -9,13 +9,22 @@ use std::{
 
 const FREQ_VERSION: u32 = 2;
 
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Usage {
     pub freq: u32,
     /// Unix timestamp (seconds). 0 means unknown.
     pub last_used: u64,
 }
 
+/// One app in the JSON export format (`frequency export` / `frequency import`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub id: String,
+    pub freq: u32,
+    #[serde(default)]
+    pub last_used: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct FrequencyFile {
     version: u32,
@@ -63,10 +72,68 @@ impl FrequencyStore {
         v.freq
     }
 
+    /// Overwrite one entry's count and last use.
+    pub fn set(&mut self, id: &str, usage: Usage) {
+        self.map.insert(id.to_string(), usage);
+        self.dirty = true;
+    }
+
+    /// Forget one entry. Returns whether it existed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let removed = self.map.remove(id).is_some();
+        self.dirty |= removed;
+        removed
+    }
+
+    /// Forget everything. Returns how many entries were removed.
+    pub fn clear(&mut self) -> usize {
+        let n = self.map.len();
+        self.map.clear();
+        self.dirty |= n > 0;
+        n
+    }
+
     pub fn map(&self) -> &HashMap<String, Usage> {
         &self.map
     }
 
+    /// Every entry as export records, sorted by id.
+    pub fn export(&self) -> Vec<UsageRecord> {
+        let mut out: Vec<UsageRecord> = self
+            .map
+            .iter()
+            .map(|(id, u)| UsageRecord {
+                id: id.clone(),
+                freq: u.freq,
+                last_used: u.last_used,
+            })
+            .collect();
+        out.sort_by(|a, b| a.id.cmp(&b.id));
+        out
+    }
+
+    /// Merge imported records: the higher `freq` and the more recent
+    /// `last_used` win. Returns how many entries changed.
+    pub fn merge(&mut self, records: &[UsageRecord]) -> usize {
+        let mut changed = 0;
+        for r in records {
+            let v = self
+                .map
+                .entry(r.id.trim_end_matches(".desktop").to_string())
+                .or_default();
+            let merged = Usage {
+                freq: v.freq.max(r.freq),
+                last_used: v.last_used.max(r.last_used),
+            };
+            if merged.freq != v.freq || merged.last_used != v.last_used {
+                *v = merged;
+                changed += 1;
+            }
+        }
+        self.dirty |= changed > 0;
+        changed
+    }
+
     pub fn flush(&mut self) {
         if !self.dirty {
             return;
@@ -97,7 +164,7 @@ impl FrequencyStore {
     }
 }
 
-fn frequency_path() -> PathBuf {
+pub fn frequency_path() -> PathBuf {
     xdg::data_dir().join(format!("frequencies.v{FREQ_VERSION}.bin"))
 }
 
@@ -107,3 +174,117 @@ pub fn unix_seconds_now() -> u64 {
         .map(|d| d.as_secs())
         .unwrap_or(0)
 }
+
+/// Format a unix timestamp (seconds) as ISO 8601 UTC, e.g. `2024-05-01T12:30:00Z`.
+pub fn format_unix_ts(sec: u64) -> String {
+    let days = (sec / 86_400) as i64;
+    let rem = sec % 86_400;
+    let (h, m, s) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // Civil-from-days (Howard Hinnant), valid for the whole u64 seconds range we care about.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}T{h:02}:{m:02}:{s:02}Z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(freq: u32, last_used: u64) -> Usage {
+        Usage { freq, last_used }
+    }
+
+    #[test]
+    fn export_clear_import_restores_the_map() {
+        let mut store = FrequencyStore::default();
+        store.set("firefox", usage(12, 1_700_000_000));
+        store.set("org.gnome.Nautilus", usage(3, 1_690_000_000));
+        store.set("never-launched", usage(1, 0));
+        let before = store.map().clone();
+
+        let json = serde_json::to_string(&store.export()).unwrap();
+        assert_eq!(store.clear(), 3);
+        assert!(store.map().is_empty());
+
+        let records: Vec<UsageRecord> = serde_json::from_str(&json).unwrap();
+        assert_eq!(store.merge(&records), 3);
+        assert_eq!(store.map(), &before);
+    }
+
+    #[test]
+    fn merge_keeps_the_larger_freq_and_the_newer_last_used() {
+        let mut store = FrequencyStore::default();
+        store.set("firefox", usage(10, 100));
+        store.set("vlc", usage(2, 500));
+        store.set("gimp", usage(4, 400));
+
+        let changed = store.merge(&[
+            // More launches elsewhere, but longer ago.
+            UsageRecord {
+                id: "firefox".to_string(),
+                freq: 20,
+                last_used: 50,
+            },
+            // Fewer launches, but more recent.
+            UsageRecord {
+                id: "vlc.desktop".to_string(),
+                freq: 1,
+                last_used: 900,
+            },
+            // Nothing newer: unchanged.
+            UsageRecord {
+                id: "gimp".to_string(),
+                freq: 4,
+                last_used: 300,
+            },
+            UsageRecord {
+                id: "new-app".to_string(),
+                freq: 1,
+                last_used: 0,
+            },
+        ]);
+
+        assert_eq!(changed, 3);
+        assert_eq!(store.get("firefox"), usage(20, 100));
+        assert_eq!(store.get("vlc"), usage(2, 900));
+        assert_eq!(store.get("gimp"), usage(4, 400));
+        assert_eq!(store.get("new-app"), usage(1, 0));
+    }
+
+    #[test]
+    fn remove_and_clear_report_what_they_dropped() {
+        let mut store = FrequencyStore::default();
+        store.set("firefox", usage(3, 10));
+        store.set("vlc", usage(1, 20));
+        store.dirty = false;
+
+        assert!(!store.remove("gimp"));
+        assert!(!store.dirty);
+        assert!(store.remove("firefox"));
+        assert!(store.dirty);
+        assert_eq!(store.get("firefox"), Usage::default());
+
+        assert_eq!(store.clear(), 1);
+        assert!(store.map().is_empty());
+        assert_eq!(store.clear(), 0);
+    }
+
+    #[test]
+    fn export_is_sorted_by_id() {
+        let mut store = FrequencyStore::default();
+        store.set("zed", usage(1, 1));
+        store.set("alacritty", usage(2, 2));
+
+        let ids: Vec<String> = store.export().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["alacritty", "zed"]);
+    }
+}
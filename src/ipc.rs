@@ -1,8 +1,18 @@
+use crate::commands::list::SortKey;
 use crate::empty_query::EmptyQueryMode;
-use crate::models::DesktopEntryOut;
+use crate::launch::Terminal;
+use crate::launched::LaunchedPid;
+use crate::models::{DesktopActionOut, DesktopEntryOut};
+use crate::scan_sources::EntrySource;
+use crate::search::SearchWeights;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Version of the request/response schema. Clients send it as
+/// `protocol_version` with each request and the daemon with each response.
+/// Bump it on any change an older daemon would misread or ignore.
+pub const IPC_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "cmd", rename_all = "kebab-case")]
 pub enum Request {
     Search {
@@ -15,6 +25,72 @@ pub enum Request {
         /// If true, filter out entries whose TryExec is present but not available.
         #[serde(default)]
         respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+
+        /// If true, fall back to typo-tolerant matching when a token has no exact match.
+        #[serde(default)]
+        fuzzy: bool,
+        /// Relevance penalty per edit of a fuzzy-only token match (default: 30).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fuzzy_penalty: Option<i32>,
+        /// Edits a fuzzy token match may need, 1 to 3 (default: 1).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fuzzy_distance: Option<u8>,
+
+        /// Ranking weights; missing keys use the built-in defaults.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weights: Option<SearchWeights>,
+
+        /// Desktop ids listed first (in this order) whenever they are in the result set;
+        /// on an empty query they are always included.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pinned: Vec<String>,
+
+        /// Only return entries listing this category (case-insensitive).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+
+        /// Only return entries from this packaging source (`native`, `flatpak`, `snap`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<EntrySource>,
+
+        /// Only return entries whose name is exactly this (case-insensitive), with
+        /// no fuzzy fallback. `query` may then be empty.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exact_name: Option<String>,
+
+        /// Only return entries with this `Type=` (`Application`, `Link`, `Directory`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        type_filter: Option<String>,
+
+        /// Only return entries whose `.desktop` file was modified at or after
+        /// this time (unix seconds).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        modified_since: Option<u64>,
+
+        /// Only return entries last launched at or after this time (unix
+        /// seconds), per the frequency store.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        used_since: Option<u64>,
+
+        /// Include entries with Hidden=true (excluded by default).
+        #[serde(default)]
+        show_hidden: bool,
+        /// Include entries with NoDisplay=true (excluded by default).
+        #[serde(default)]
+        show_nodisplay: bool,
+
+        /// Reject entries matching any of these tokens. `!word`s in `query` are
+        /// excluded as well.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclude: Vec<String>,
+
+        /// Drop matches whose relevance score is below this.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_score: Option<i32>,
     },
     /// Build (or ensure) the in-memory index for the given roots.
     Warmup {
@@ -23,6 +99,22 @@ pub enum Request {
         /// If true, filter out entries whose TryExec is present but not available.
         #[serde(default)]
         respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+    },
+    /// Drop and synchronously rebuild the in-memory index for the given roots.
+    Reload {
+        roots: Vec<String>,
+
+        /// If true, filter out entries whose TryExec is present but not available.
+        #[serde(default)]
+        respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
     },
     List {
         roots: Vec<String>,
@@ -30,6 +122,89 @@ pub enum Request {
         /// If true, filter out entries whose TryExec is present but not available.
         #[serde(default)]
         respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+
+        /// Include entries with Hidden=true (excluded by default).
+        #[serde(default)]
+        show_hidden: bool,
+        /// Include entries with NoDisplay=true (excluded by default).
+        #[serde(default)]
+        show_nodisplay: bool,
+
+        /// Sort keys, applied in order for tie-breaking. Empty means `["name"]`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sort_keys: Vec<SortKey>,
+
+        /// Only return entries from this packaging source (`native`, `flatpak`, `snap`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<EntrySource>,
+
+        /// Only return entries listing this category (case-insensitive).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter_category: Option<String>,
+
+        /// Only return entries listing this MIME type (case-insensitive).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter_mime: Option<String>,
+
+        /// Rank all returned entries by their search score for this query
+        /// (without filtering); `sort_keys` only breaks ties.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        score_by: Option<String>,
+
+        /// Ranking weights for `score_by`; missing keys use the built-in defaults.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weights: Option<SearchWeights>,
+    },
+    /// The entry with exactly this desktop-id (zero or one entries, no scoring).
+    GetEntry {
+        roots: Vec<String>,
+        id: String,
+
+        /// If true, filter out entries whose TryExec is present but not available.
+        #[serde(default)]
+        respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+    },
+    /// The `[Desktop Action]` groups of the entry with this desktop-id, in file
+    /// order (`Response::Actions`).
+    GetActions {
+        roots: Vec<String>,
+        desktop_id: String,
+
+        /// If true, filter out entries whose TryExec is present but not available.
+        #[serde(default)]
+        respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+    },
+    /// Entries that declare the given MIME type, most used first.
+    ByMime {
+        roots: Vec<String>,
+        mime_type: String,
+
+        /// If true, filter out entries whose TryExec is present but not available.
+        #[serde(default)]
+        respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+
+        /// Include entries with Hidden=true (excluded by default).
+        #[serde(default)]
+        show_hidden: bool,
+        /// Include entries with NoDisplay=true (excluded by default).
+        #[serde(default)]
+        show_nodisplay: bool,
     },
     Launch {
         roots: Vec<String>,
@@ -39,17 +214,160 @@ pub enum Request {
         /// If true, filter out entries whose TryExec is present but not available.
         #[serde(default)]
         respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+
+        /// Terminal to try first for Terminal=true apps (falls back to auto-detection).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        terminal: Option<Terminal>,
+
+        /// Files substituted for %f/%F in Exec=.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        files: Vec<String>,
+        /// URLs substituted for %u/%U in Exec=.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        urls: Vec<String>,
+
+        /// Absolute directory to run the app in; overrides the entry's `Path=`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<String>,
+
+        /// Extra `KEY=VALUE` environment for the app.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        env: Vec<String>,
+    },
+    /// Launch the most used app declaring the MIME type of `file_path`
+    /// (sniffed by the daemon) with that file; answered with `Response::Ok`.
+    Open {
+        roots: Vec<String>,
+        /// Absolute path of the file to open.
+        file_path: String,
+
+        /// If true, filter out entries whose TryExec is present but not available.
+        #[serde(default)]
+        respect_try_exec: bool,
+
+        /// If true, hide entries not meant for $XDG_CURRENT_DESKTOP (OnlyShowIn/NotShowIn).
+        #[serde(default)]
+        de_filter: bool,
+
+        /// Terminal to try first for Terminal=true apps (falls back to auto-detection).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        terminal: Option<Terminal>,
     },
     Status,
+    /// Cheapest possible round-trip; answered with `Response::Pong`.
+    Ping {
+        /// The client's `IPC_VERSION` (0 from clients that predate it).
+        #[serde(default)]
+        protocol_version: u32,
+    },
+    /// Like `Status`, with one `IndexInfo` per loaded index.
+    StatusVerbose,
+
+    /// Forget usage data for one id, or for everything when `id` is None.
+    ResetFrequency {
+        #[serde(default)]
+        id: Option<String>,
+    },
+
+    /// Record a launch that happened outside this tool (e.g. seen by a compositor).
+    IncrementFrequency {
+        id: String,
+    },
+
+    /// Overwrite one app's usage data.
+    SetFrequency {
+        id: String,
+        freq: u32,
+        /// Unix timestamp (seconds); 0 means unknown.
+        #[serde(default)]
+        last_used: u64,
+    },
+
+    /// Re-read the frequency file, after it was changed outside the daemon
+    /// (e.g. `frequency import`).
+    ReloadFrequency,
+
+    /// Processes started by `launch` that are still running; answered with
+    /// `Response::LaunchedPids`. Dead ones are pruned from the file.
+    ListPids,
 
     Shutdown,
+
+    /// Several requests over one connection; answered with `Response::Batch` in
+    /// the same order. Batches cannot be nested.
+    Batch {
+        requests: Vec<Request>,
+    },
+}
+
+/// Append `"protocol_version":IPC_VERSION` to a serialized request or
+/// response. Both are JSON objects with at least their tag, so the field goes
+/// last, after a comma.
+pub fn with_protocol_version(mut json: String) -> String {
+    if json.ends_with('}') {
+        json.pop();
+        json.push_str(&format!(",\"protocol_version\":{IPC_VERSION}}}"));
+    }
+    json
+}
+
+impl Request {
+    /// Requests any daemon version handles the same way, so an incompatible
+    /// daemon can still be inspected and stopped.
+    pub fn is_version_independent(&self) -> bool {
+        matches!(
+            self,
+            Request::Ping { .. } | Request::Status | Request::Shutdown
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum Response {
     Ok,
-    Error { message: String },
-    Entries { entries: Vec<DesktopEntryOut> },
-    Status { has_index_count: usize },
+    Pong {
+        /// The daemon's `IPC_VERSION` (0 from daemons that predate it).
+        #[serde(default)]
+        protocol_version: u32,
+    },
+    Error {
+        message: String,
+    },
+    Entries {
+        entries: Vec<DesktopEntryOut>,
+    },
+    Actions {
+        actions: Vec<DesktopActionOut>,
+    },
+    Status {
+        has_index_count: usize,
+    },
+    StatusVerbose {
+        indexes: Vec<IndexInfo>,
+        /// Connections queued behind the one being served.
+        #[serde(default)]
+        pending: usize,
+    },
+    Batch {
+        responses: Vec<Response>,
+    },
+    LaunchedPids {
+        pids: Vec<LaunchedPid>,
+    },
+}
+
+/// One loaded daemon index, as reported by `StatusVerbose`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexInfo {
+    pub roots: Vec<String>,
+    pub respect_try_exec: bool,
+    pub de_filter: bool,
+    pub entry_count: usize,
+    /// Unix timestamp (seconds) of the last (re)build.
+    pub last_built_sec: u64,
 }
@@ -1,56 +1,201 @@
-use std::{env, path::Path};
+use serde::{Deserialize, Serialize};
+use std::{env, path::Path, process::Command};
 
-#[derive(Debug, Clone, Copy)]
+// `GnomeTerminal` is the program's actual name.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Terminal {
+    #[serde(rename = "foot")]
     Foot,
+    #[serde(rename = "kitty")]
     Kitty,
+    #[serde(rename = "alacritty")]
     Alacritty,
+    #[serde(rename = "wezterm")]
     WezTerm,
+    #[serde(rename = "gnome-terminal")]
+    GnomeTerminal,
+    #[serde(rename = "konsole")]
+    Konsole,
+    #[serde(rename = "xterm")]
+    Xterm,
+    #[serde(rename = "urxvt")]
+    Urxvt,
+    #[serde(rename = "st")]
+    St,
 }
 
-pub fn pick_terminal() -> Option<Terminal> {
-    // Keep this deterministic and simple.
-    if is_executable_in_path("foot") {
-        return Some(Terminal::Foot);
+impl Terminal {
+    /// Auto-detection order.
+    pub const ALL: [Terminal; 9] = [
+        Terminal::Foot,
+        Terminal::Kitty,
+        Terminal::Alacritty,
+        Terminal::WezTerm,
+        Terminal::GnomeTerminal,
+        Terminal::Konsole,
+        Terminal::Xterm,
+        Terminal::Urxvt,
+        Terminal::St,
+    ];
+
+    pub fn binary(self) -> &'static str {
+        match self {
+            Terminal::Foot => "foot",
+            Terminal::Kitty => "kitty",
+            Terminal::Alacritty => "alacritty",
+            Terminal::WezTerm => "wezterm",
+            Terminal::GnomeTerminal => "gnome-terminal",
+            Terminal::Konsole => "konsole",
+            Terminal::Xterm => "xterm",
+            Terminal::Urxvt => "urxvt",
+            Terminal::St => "st",
+        }
+    }
+
+    /// Arguments placed between the terminal binary and the wrapped command.
+    fn exec_prefix(self) -> &'static [&'static str] {
+        match self {
+            Terminal::Kitty => &[],
+            Terminal::WezTerm => &["start", "--"],
+            Terminal::GnomeTerminal => &["--"],
+            Terminal::Foot
+            | Terminal::Alacritty
+            | Terminal::Konsole
+            | Terminal::Xterm
+            | Terminal::Urxvt
+            | Terminal::St => &["-e"],
+        }
+    }
+
+    /// Full argv that runs `argv` inside this terminal.
+    pub fn wrap(self, argv: &[String]) -> Vec<String> {
+        std::iter::once(self.binary())
+            .chain(self.exec_prefix().iter().copied())
+            .map(str::to_string)
+            .chain(argv.iter().cloned())
+            .collect()
     }
-    if is_executable_in_path("kitty") {
-        return Some(Terminal::Kitty);
+}
+
+/// Comma-separated list of supported terminals, for error messages.
+pub fn known_terminals() -> String {
+    Terminal::ALL
+        .iter()
+        .map(|t| t.binary())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn pick_terminal(preferred: Option<Terminal>) -> Option<Terminal> {
+    // A configured terminal wins when installed; otherwise keep this deterministic and simple.
+    if let Some(t) = preferred
+        && is_executable_in_path(t.binary())
+    {
+        return Some(t);
     }
-    if is_executable_in_path("alacritty") {
-        return Some(Terminal::Alacritty);
+
+    Terminal::ALL
+        .into_iter()
+        .find(|t| is_executable_in_path(t.binary()))
+}
+
+/// Where, and with which extra environment, a launched app runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LaunchEnv<'a> {
+    pub cwd: Option<&'a str>,
+    /// `KEY=VALUE` assignments, already checked with `parse_env_assignment`.
+    pub vars: &'a [String],
+}
+
+impl LaunchEnv<'_> {
+    pub fn apply<'c>(&self, cmd: &'c mut Command) -> &'c mut Command {
+        if let Some(dir) = self.cwd {
+            cmd.current_dir(dir);
+        }
+        for var in self.vars {
+            if let Ok((key, value)) = parse_env_assignment(var) {
+                cmd.env(key, value);
+            }
+        }
+        cmd
     }
-    if is_executable_in_path("wezterm") {
-        return Some(Terminal::WezTerm);
+}
+
+/// Split `KEY=VALUE` at the first `=` (the value may contain more).
+pub fn parse_env_assignment(s: &str) -> Result<(&str, &str), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !key.contains('\0') => Ok((key, value)),
+        _ => Err(format!(
+            "invalid environment assignment '{s}' (expected KEY=VALUE)"
+        )),
     }
+}
 
-    None
+/// The desktop-id as a D-Bus well-known name (`org.example.App`), if it is
+/// one. D-Bus activatable apps must use such ids.
+pub fn dbus_service_name(id: &str) -> Option<&str> {
+    let valid_element = |e: &str| {
+        !e.is_empty()
+            && !e.starts_with(|c: char| c.is_ascii_digit())
+            && e.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+
+    (id.len() <= 255 && id.contains('.') && id.split('.').all(valid_element)).then_some(id)
+}
+
+/// `gdbus call` argv invoking `org.freedesktop.Application.Activate` on `service`.
+///
+/// The object path is the service name with `.` turned into `/` and `-` into
+/// `_`, as the Desktop Entry spec requires. `gdbus call` waits for the reply,
+/// so its exit status tells whether the app accepted the call.
+pub fn dbus_activate_argv(service: &str) -> Vec<String> {
+    let object_path = format!("/{}", service.replace('.', "/").replace('-', "_"));
+    vec![
+        "gdbus".to_string(),
+        "call".to_string(),
+        "--session".to_string(),
+        "--dest".to_string(),
+        service.to_string(),
+        "--object-path".to_string(),
+        object_path,
+        "--method".to_string(),
+        "org.freedesktop.Application.Activate".to_string(),
+        // platform_data: an empty a{sv}, typed so no introspection is needed.
+        "@a{sv} {}".to_string(),
+    ]
 }
 
-pub fn exec_to_argv(exec_line: &str) -> Vec<String> {
-    // Desktop Entry spec allows field codes like %u, %U, %f, %F, etc.
-    // For now we drop them (we're launching without file/url args).
+/// Split an Exec= line and expand file/URL field codes with `files`.
+///
+/// `%f`/`%u` take the first argument, `%F`/`%U` take all of them; every other
+/// field code (and any code with nothing to substitute) is dropped.
+pub fn exec_to_argv_with_args(exec_line: &str, files: &[&str]) -> Vec<String> {
     let Some(tokens) = shlex::split(exec_line) else {
         return Vec::new();
     };
 
-    tokens
-        .into_iter()
-        .filter_map(|t| {
-            // Remove known field codes
-            if is_field_code_token(&t) {
-                return None;
-            }
+    let mut argv: Vec<String> = Vec::with_capacity(tokens.len() + files.len());
+    for t in tokens {
+        match t.as_str() {
+            "%f" | "%u" => argv.extend(files.first().map(|f| f.to_string())),
+            "%F" | "%U" => argv.extend(files.iter().map(|f| f.to_string())),
+            _ if is_field_code_token(&t) => {}
+            // Best-effort: expand field codes embedded in an arg
+            // Example: "--foo=%u" -> "--foo=<first file>"
+            _ if t.contains('%') => argv.push(expand_field_codes(&t, files.first().copied())),
+            _ => argv.push(t),
+        }
+    }
 
-            // Best-effort: strip field codes embedded in an arg
-            // Example: "--foo=%u" -> "--foo="
-            if t.contains('%') {
-                return Some(strip_field_codes(&t));
-            }
+    argv.retain(|t| !t.is_empty());
+    argv
+}
 
-            Some(t)
-        })
-        .filter(|t| !t.is_empty())
-        .collect()
+/// Arguments for field-code expansion: files first, then URLs.
+pub fn launch_args<'a>(files: &'a [String], urls: &'a [String]) -> Vec<&'a str> {
+    files.iter().chain(urls).map(String::as_str).collect()
 }
 
 fn is_field_code_token(t: &str) -> bool {
@@ -60,18 +205,21 @@ fn is_field_code_token(t: &str) -> bool {
     )
 }
 
-fn strip_field_codes(s: &str) -> String {
-    // Minimal: remove any occurrences of %<char>.
+fn expand_field_codes(s: &str, first: Option<&str>) -> String {
+    // Minimal: "%%" is a literal '%', "%f"/"%u" become `first`, other %<char> are removed.
     let mut out = String::with_capacity(s.len());
     let mut chars = s.chars().peekable();
 
     while let Some(ch) = chars.next() {
         if ch == '%' {
-            // Skip next char if present (the code), or keep '%' if it's the end.
-            if chars.peek().is_some() {
-                chars.next();
-                continue;
+            // Consume the code if present, or keep '%' if it's the end.
+            match chars.next() {
+                Some('%') => out.push('%'),
+                Some('f' | 'u') => out.push_str(first.unwrap_or("")),
+                Some(_) => {}
+                None => out.push('%'),
             }
+            continue;
         }
         out.push(ch);
     }
@@ -79,7 +227,7 @@ fn strip_field_codes(s: &str) -> String {
     out
 }
 
-fn is_executable_in_path(name: &str) -> bool {
+pub fn is_executable_in_path(name: &str) -> bool {
     if name.is_empty() {
         return false;
     }
@@ -124,3 +272,146 @@ fn is_executable_file(path: &Path) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dbus_activate_argv_maps_the_service_to_an_object_path() {
+        let argv = dbus_activate_argv("org.example.Text-Editor");
+        assert_eq!(
+            argv,
+            [
+                "gdbus",
+                "call",
+                "--session",
+                "--dest",
+                "org.example.Text-Editor",
+                "--object-path",
+                "/org/example/Text_Editor",
+                "--method",
+                "org.freedesktop.Application.Activate",
+                "@a{sv} {}",
+            ]
+        );
+    }
+
+    #[test]
+    fn dbus_service_name_needs_a_reverse_dns_id() {
+        assert_eq!(
+            dbus_service_name("org.gnome.Nautilus"),
+            Some("org.gnome.Nautilus")
+        );
+        assert_eq!(dbus_service_name("firefox"), None);
+        assert_eq!(dbus_service_name("org.2048.Game"), None);
+        assert_eq!(dbus_service_name("org..Empty"), None);
+    }
+
+    #[test]
+    fn env_assignments_split_at_the_first_equals() {
+        assert_eq!(parse_env_assignment("FOO=bar"), Ok(("FOO", "bar")));
+        assert_eq!(parse_env_assignment("FOO="), Ok(("FOO", "")));
+        assert_eq!(parse_env_assignment("OPTS=a=b"), Ok(("OPTS", "a=b")));
+        for bad in ["FOO", "=bar", ""] {
+            let err = parse_env_assignment(bad).unwrap_err();
+            assert!(err.contains("expected KEY=VALUE"), "{err}");
+        }
+    }
+
+    #[test]
+    fn launch_env_reaches_the_spawned_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().canonicalize().unwrap();
+        let vars = [
+            "DI_TEST_FOO=bar".to_string(),
+            "DI_TEST_OPTS=a=b c".to_string(),
+        ];
+        let launch_env = LaunchEnv {
+            cwd: cwd.to_str(),
+            vars: &vars,
+        };
+
+        let output = launch_env
+            .apply(Command::new("sh").args(["-c", "env; pwd"]))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert!(lines.contains(&"DI_TEST_FOO=bar"), "{stdout}");
+        assert!(lines.contains(&"DI_TEST_OPTS=a=b c"), "{stdout}");
+        assert_eq!(lines.last(), cwd.to_str().as_ref());
+    }
+
+    fn argv(exec: &str, files: &[&str]) -> Vec<String> {
+        exec_to_argv_with_args(exec, files)
+    }
+
+    #[test]
+    fn single_file_codes_take_the_first_arg_only() {
+        for code in ["%f", "%u"] {
+            let exec = format!("app {code}");
+            assert_eq!(argv(&exec, &[]), ["app"]);
+            assert_eq!(argv(&exec, &["a.txt"]), ["app", "a.txt"]);
+            assert_eq!(argv(&exec, &["a.txt", "b.txt"]), ["app", "a.txt"]);
+        }
+    }
+
+    #[test]
+    fn list_codes_take_every_arg() {
+        for code in ["%F", "%U"] {
+            let exec = format!("app --new {code} --end");
+            assert_eq!(argv(&exec, &[]), ["app", "--new", "--end"]);
+            assert_eq!(argv(&exec, &["a.txt"]), ["app", "--new", "a.txt", "--end"]);
+            assert_eq!(
+                argv(&exec, &["a.txt", "b c.txt", "d.txt"]),
+                ["app", "--new", "a.txt", "b c.txt", "d.txt", "--end"]
+            );
+        }
+    }
+
+    #[test]
+    fn embedded_codes_expand_to_the_first_arg() {
+        assert_eq!(
+            argv("app --foo=%u", &["https://example.org", "x"]),
+            ["app", "--foo=https://example.org"]
+        );
+        assert_eq!(argv("app --foo=%u", &[]), ["app", "--foo="]);
+        assert_eq!(argv("app --file=%f%i", &["a.txt"]), ["app", "--file=a.txt"]);
+    }
+
+    #[test]
+    fn double_percent_is_a_literal_percent() {
+        assert_eq!(argv("printf 100%%", &[]), ["printf", "100%"]);
+        assert_eq!(argv("app %% %f", &["a.txt"]), ["app", "%", "a.txt"]);
+    }
+
+    #[test]
+    fn other_field_codes_are_dropped() {
+        assert_eq!(argv("app %i %c %k %U", &["a.txt"]), ["app", "a.txt"]);
+        assert_eq!(argv("app \"unterminated", &["a.txt"]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn each_terminal_wraps_the_command_its_own_way() {
+        let cmd = vec!["htop".to_string(), "-d".to_string(), "10".to_string()];
+        let expected: [(Terminal, &[&str]); 9] = [
+            (Terminal::Foot, &["foot", "-e"]),
+            (Terminal::Kitty, &["kitty"]),
+            (Terminal::Alacritty, &["alacritty", "-e"]),
+            (Terminal::WezTerm, &["wezterm", "start", "--"]),
+            (Terminal::GnomeTerminal, &["gnome-terminal", "--"]),
+            (Terminal::Konsole, &["konsole", "-e"]),
+            (Terminal::Xterm, &["xterm", "-e"]),
+            (Terminal::Urxvt, &["urxvt", "-e"]),
+            (Terminal::St, &["st", "-e"]),
+        ];
+        assert_eq!(expected.len(), Terminal::ALL.len());
+        for (terminal, prefix) in expected {
+            let mut want: Vec<&str> = prefix.to_vec();
+            want.extend(["htop", "-d", "10"]);
+            assert_eq!(terminal.wrap(&cmd), want, "{terminal:?}");
+        }
+    }
+}
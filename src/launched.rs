@@ -0,0 +1,163 @@
+use crate::frequency::unix_seconds_now;
+use crate::xdg;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+const LAUNCHED_VERSION: u32 = 1;
+
+/// A process started by `launch` (daemon or local).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchedPid {
+    pub desktop_id: String,
+    pub pid: u32,
+    /// Unix timestamp (seconds) of the launch.
+    pub launched_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LaunchedFile {
+    version: u32,
+    pids: Vec<LaunchedPid>,
+}
+
+#[derive(Debug, Default)]
+pub struct LaunchedPidStore {
+    pids: Vec<LaunchedPid>,
+    dirty: bool,
+    path: PathBuf,
+}
+
+impl LaunchedPidStore {
+    pub fn load() -> Self {
+        let path = launched_path();
+
+        let mut store = Self {
+            pids: Vec::new(),
+            dirty: false,
+            path,
+        };
+
+        if let Ok(data) = fs::read(&store.path)
+            && let Ok(file) = postcard::from_bytes::<LaunchedFile>(&data)
+            && file.version == LAUNCHED_VERSION
+        {
+            store.pids = file.pids;
+        }
+
+        store
+    }
+
+    pub fn record(&mut self, desktop_id: &str, pid: u32) {
+        // A recycled pid replaces the stale record.
+        self.pids.retain(|p| p.pid != pid);
+        self.pids.push(LaunchedPid {
+            desktop_id: desktop_id.to_string(),
+            pid,
+            launched_at: unix_seconds_now(),
+        });
+        self.dirty = true;
+    }
+
+    /// Drop records whose process has exited. Returns how many were removed.
+    pub fn prune_dead(&mut self) -> usize {
+        let before = self.pids.len();
+        self.pids.retain(|p| is_alive(p.pid));
+        let removed = before - self.pids.len();
+        self.dirty |= removed > 0;
+        removed
+    }
+
+    pub fn pids(&self) -> &[LaunchedPid] {
+        &self.pids
+    }
+
+    pub fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        let Some(dir) = self.path.parent() else {
+            return;
+        };
+        if fs::create_dir_all(dir).is_err() {
+            return;
+        }
+
+        let file = LaunchedFile {
+            version: LAUNCHED_VERSION,
+            pids: self.pids.clone(),
+        };
+
+        let Ok(data) = postcard::to_stdvec(&file) else {
+            return;
+        };
+
+        // Best-effort atomic-ish write; the pid keeps concurrent writers apart.
+        let tmp = self
+            .path
+            .with_extension(format!("bin.{}.tmp", std::process::id()));
+        if fs::write(&tmp, data).is_ok() {
+            let _ = fs::rename(tmp, &self.path);
+            self.dirty = false;
+        }
+    }
+}
+
+/// Record a spawned process (best-effort).
+pub fn record_launch(desktop_id: &str, pid: u32) {
+    update_launched(|store| {
+        store.prune_dead();
+        store.record(desktop_id, pid);
+    });
+}
+
+/// Records of processes still running; dead ones are pruned from the file.
+pub fn running_launched() -> Vec<LaunchedPid> {
+    update_launched(|store| {
+        store.prune_dead();
+        store.pids().to_vec()
+    })
+}
+
+/// Load the store, apply `f` and save it, holding an exclusive lock on
+/// `launched_pids.bin.lock` so concurrent launches (CLI and daemon) don't drop
+/// each other's records. Without the lock (e.g. unwritable data dir) it still
+/// runs, unguarded.
+fn update_launched<R>(f: impl FnOnce(&mut LaunchedPidStore) -> R) -> R {
+    let _lock = lock_launched();
+    let mut store = LaunchedPidStore::load();
+    let out = f(&mut store);
+    store.flush();
+    out
+}
+
+/// The lock `update_launched` holds; released when the file is dropped.
+fn lock_launched() -> Option<fs::File> {
+    let path = launched_path().with_extension("bin.lock");
+    fs::create_dir_all(path.parent()?).ok()?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .ok()?;
+    file.lock().ok()?;
+    Some(file)
+}
+
+pub fn launched_path() -> PathBuf {
+    xdg::data_dir().join("launched_pids.bin")
+}
+
+/// Whether `pid` is a live process. Zombies (exited, not yet reaped by their
+/// parent) count as dead.
+fn is_alive(pid: u32) -> bool {
+    let Ok(stat) = fs::read_to_string(format!("/proc/{pid}/stat")) else {
+        return false;
+    };
+    // `pid (comm) state ...`; comm may contain spaces or parens.
+    let state = stat
+        .rsplit_once(')')
+        .and_then(|(_, rest)| rest.split_whitespace().next());
+    !matches!(state, Some("Z") | Some("X") | None)
+}
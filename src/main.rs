@@ -2,6 +2,7 @@ mod app;
 mod cache;
 mod cli;
 mod commands;
+mod config;
 mod daemon;
 mod daemon_client;
 mod desktop;
@@ -9,18 +10,20 @@ mod empty_query;
 mod frequency;
 mod ipc;
 mod launch;
+mod launched;
+mod mime;
 mod models;
+mod normalize;
 mod output;
+mod scan_sources;
 mod search;
+mod time_parse;
+mod trigram;
+mod validate;
 mod xdg;
 
-use clap::Parser;
-use cli::Cli;
-
 fn main() {
-    let cli = Cli::parse();
-
-    let code = app::run(cli);
+    let code = app::run(std::env::args_os().collect());
     if code != 0 {
         std::process::exit(code);
     }
@@ -0,0 +1,80 @@
+use std::{fs::File, io::Read, path::Path, process::Command};
+
+/// MIME type of the file at `path`: `file --mime-type -b` when available,
+/// otherwise a few well-known magic numbers.
+pub fn sniff_mime(path: &Path) -> Option<String> {
+    if let Ok(out) = Command::new("file")
+        .arg("--mime-type")
+        .arg("-b")
+        .arg("--")
+        .arg(path)
+        .output()
+        && out.status.success()
+    {
+        let mime = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        // `file` prints e.g. "cannot open ..." with status 0 for missing files.
+        if mime.contains('/') && !mime.contains(' ') {
+            return Some(mime);
+        }
+    }
+
+    let mut head = [0u8; 16];
+    let n = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    sniff_magic(&head[..n]).map(str::to_string)
+}
+
+/// MIME type from the first bytes of a file, for a handful of common formats.
+pub fn sniff_magic(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if head.starts_with(b"ID3")
+        || (head.len() >= 2 && head[0] == 0xFF && matches!(head[1], 0xFB | 0xF3 | 0xF2))
+    {
+        // ID3 tag, or a bare MPEG-1/2 layer III frame header.
+        Some("audio/mpeg")
+    } else if head.get(4..8) == Some(b"ftyp") {
+        Some("video/mp4")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_bytes_of_common_formats() {
+        assert_eq!(sniff_magic(b"%PDF-1.7\n%\xe2\xe3"), Some("application/pdf"));
+        assert_eq!(
+            sniff_magic(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(sniff_magic(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(sniff_magic(b"ID3\x04\0\0"), Some("audio/mpeg"));
+        assert_eq!(sniff_magic(&[0xFF, 0xFB, 0x90, 0x64]), Some("audio/mpeg"));
+        assert_eq!(sniff_magic(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+    }
+
+    #[test]
+    fn unknown_or_short_input_has_no_magic() {
+        assert_eq!(sniff_magic(b""), None);
+        assert_eq!(sniff_magic(b"hello world"), None);
+        assert_eq!(sniff_magic(b"%PD"), None);
+        assert_eq!(sniff_magic(&[0xFF]), None);
+        assert_eq!(sniff_magic(b"\0\0\0\x20ftu"), None);
+    }
+
+    #[test]
+    fn sniff_mime_reads_the_file_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report");
+        std::fs::write(&path, b"%PDF-1.4\n1 0 obj\n").unwrap();
+        assert_eq!(sniff_mime(&path).as_deref(), Some("application/pdf"));
+        assert_eq!(sniff_mime(&dir.path().join("missing")), None);
+    }
+}
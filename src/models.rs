@@ -1,4 +1,6 @@
+use crate::scan_sources::EntrySource;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DesktopEntryOut {
@@ -19,8 +21,40 @@ pub struct DesktopEntryOut {
     pub startup_notify: Option<bool>,
     pub nodisplay: Option<bool>,
     pub hidden: Option<bool>,
+    /// `Path=`: directory to run the program in.
+    pub working_dir: Option<String>,
+    /// `DBusActivatable=`: the app can be started over D-Bus, as the service
+    /// named by its desktop-id.
+    pub dbus_activatable: Option<bool>,
     pub only_show_in: Vec<String>,
     pub not_show_in: Vec<String>,
+    /// Absolute path of the `.desktop` file.
+    pub path: String,
+    /// Scan root the file was found under.
+    pub source_root: String,
+    /// Packaging (native, Flatpak, Snap), detected from `Exec=`.
+    pub source: EntrySource,
+    /// Modification time of the `.desktop` file (unix seconds), when known.
+    pub mtime_sec: Option<u64>,
+    /// Non-standard keys (e.g. `X-GNOME-FullName`), untranslated values only.
+    /// Not searched.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl DesktopEntryOut {
+    /// Non-empty `Name=`, else non-empty `GenericName=`.
+    pub fn name_or_generic_name(&self) -> Option<&str> {
+        [&self.name, &self.generic_name]
+            .into_iter()
+            .filter_map(|v| v.as_deref())
+            .find(|v| !v.is_empty())
+    }
+
+    /// Name to show and sort by: `name_or_generic_name`, else the desktop-id.
+    pub fn display_name(&self) -> &str {
+        self.name_or_generic_name().unwrap_or(&self.id)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +63,39 @@ pub struct DesktopActionOut {
     pub name: Option<String>,
     pub icon: Option<String>,
     pub exec: Option<String>,
+    /// Non-standard keys of the `[Desktop Action]` group.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// How the scan roots are walked, parsed and filtered.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Parse .desktop files on all CPU cores.
+    pub parallel: bool,
+    /// Deepest directory level to descend into below each root (1 = only files
+    /// directly in the root); `None` for unlimited.
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked directories and index symlinked files
+    /// (`WalkDir` skips symlink loops).
+    pub follow_symlinks: bool,
+    /// Drop entries whose `TryExec` binary is missing.
+    pub respect_try_exec: bool,
+    /// Drop entries hidden from the current desktop (`OnlyShowIn`/`NotShowIn`).
+    pub de_filter: bool,
+    /// Resolve localized keys for this locale instead of the environment's.
+    pub locale: Option<String>,
+    /// Desktop ids (without `.desktop`) left out of the result.
+    pub ignore: HashSet<String>,
+    /// Neither read nor write the on-disk cache; every file is parsed.
+    pub no_cache: bool,
+}
+
+/// Per-file outcome reported while `scan_and_parse_desktop_files_with_progress` runs.
+#[derive(Debug, Clone)]
+pub enum ScanProgress {
+    Ok { id: String },
+    Failed { path: String, reason: String },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,7 +111,14 @@ pub struct ParsedScanResult {
     pub found_count: usize,
     pub parsed_count: usize,
     pub parse_failed: usize,
+    /// `<path>: <reason>` for each file counted in `parse_failed`, in path order.
+    pub parse_errors: Vec<String>,
     pub entries: Vec<DesktopEntryIndexed>,
+    /// `(id, position in scanned_roots)` for each id a file claimed, before
+    /// parse failures and filters drop entries; the daemon merges indexes
+    /// with it.
+    #[serde(skip)]
+    pub resolved_ids: Vec<(String, usize)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,3 +128,48 @@ pub struct DesktopEntryIndexed {
     pub id_lc: String,
     pub name_lc: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn out(id: &str, name: Option<&str>, generic_name: Option<&str>) -> DesktopEntryOut {
+        DesktopEntryOut {
+            id: id.to_string(),
+            name: name.map(str::to_string),
+            generic_name: generic_name.map(str::to_string),
+            comment: None,
+            icon: None,
+            exec: None,
+            try_exec: None,
+            terminal: false,
+            categories: Vec::new(),
+            keywords: Vec::new(),
+            mime_types: Vec::new(),
+            actions: Vec::new(),
+            type_: None,
+            startup_wm_class: None,
+            startup_notify: None,
+            nodisplay: None,
+            hidden: None,
+            working_dir: None,
+            dbus_activatable: None,
+            only_show_in: Vec::new(),
+            not_show_in: Vec::new(),
+            path: String::new(),
+            source_root: String::new(),
+            source: EntrySource::default(),
+            mtime_sec: None,
+            extra: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn display_name_falls_back_to_generic_name_then_id() {
+        assert_eq!(out("app", Some("App"), Some("Tool")).display_name(), "App");
+        assert_eq!(out("app", Some(""), Some("Tool")).display_name(), "Tool");
+        assert_eq!(out("app", None, Some("Tool")).display_name(), "Tool");
+        assert_eq!(out("app", None, Some("")).display_name(), "app");
+        assert_eq!(out("app", None, None).display_name(), "app");
+    }
+}
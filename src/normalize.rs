@@ -0,0 +1,54 @@
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// Lowercase `s` and strip diacritics, so "Café" and "cafe" compare equal.
+///
+/// Characters are decomposed (NFD) and the combining marks dropped; letters
+/// without a decomposition (e.g. "ß", "ø") are kept as-is.
+pub fn normalize_unicode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    push_normalized(&mut out, s);
+    out
+}
+
+/// CJK Unified Ideographs (U+4E00..=U+9FFF). Each one is a searchable word of
+/// its own, since these scripts don't separate words with spaces.
+pub fn is_cjk(ch: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&ch)
+}
+
+/// `normalize_unicode`, appending to `dst` instead of allocating.
+pub fn push_normalized(dst: &mut String, s: &str) {
+    if s.is_ascii() {
+        // Fast path: nothing to decompose.
+        dst.extend(s.chars().map(|c| c.to_ascii_lowercase()));
+        return;
+    }
+
+    for ch in s.nfd().filter(|c| !is_combining_mark(*c)) {
+        dst.extend(ch.to_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diacritics_and_case_are_folded() {
+        assert_eq!(normalize_unicode("Café"), "cafe");
+        assert_eq!(normalize_unicode("Über"), "uber");
+        assert_eq!(normalize_unicode("naïve"), "naive");
+        assert_eq!(normalize_unicode("Ångström"), "angstrom");
+        // Already decomposed input folds the same way.
+        assert_eq!(normalize_unicode("Cafe\u{301}"), "cafe");
+        // No decomposition: kept, only lowercased.
+        assert_eq!(normalize_unicode("Straße Ø"), "straße ø");
+    }
+
+    #[test]
+    fn push_normalized_appends() {
+        let mut dst = String::from("word ");
+        push_normalized(&mut dst, "NAÏVE");
+        assert_eq!(dst, "word naive");
+    }
+}
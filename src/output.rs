@@ -1,6 +1,350 @@
+use crate::models::DesktopEntryOut;
+use clap::ValueEnum;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// A `DesktopEntryOut` JSON key selectable with `--output-fields`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum OutputField {
+    Id,
+    Name,
+    GenericName,
+    Comment,
+    Icon,
+    Exec,
+    TryExec,
+    Terminal,
+    Categories,
+    Keywords,
+    MimeTypes,
+    Actions,
+    #[value(name = "type")]
+    Type,
+    StartupWmClass,
+    StartupNotify,
+    Nodisplay,
+    Hidden,
+    WorkingDir,
+    DbusActivatable,
+    OnlyShowIn,
+    NotShowIn,
+    Path,
+    SourceRoot,
+    Source,
+    MtimeSec,
+    Extra,
+}
+
+impl OutputField {
+    /// Key in the serialized `DesktopEntryOut`.
+    fn key(self) -> &'static str {
+        match self {
+            OutputField::Id => "id",
+            OutputField::Name => "name",
+            OutputField::GenericName => "generic_name",
+            OutputField::Comment => "comment",
+            OutputField::Icon => "icon",
+            OutputField::Exec => "exec",
+            OutputField::TryExec => "try_exec",
+            OutputField::Terminal => "terminal",
+            OutputField::Categories => "categories",
+            OutputField::Keywords => "keywords",
+            OutputField::MimeTypes => "mime_types",
+            OutputField::Actions => "actions",
+            OutputField::Type => "type_",
+            OutputField::StartupWmClass => "startup_wm_class",
+            OutputField::StartupNotify => "startup_notify",
+            OutputField::Nodisplay => "nodisplay",
+            OutputField::Hidden => "hidden",
+            OutputField::WorkingDir => "working_dir",
+            OutputField::DbusActivatable => "dbus_activatable",
+            OutputField::OnlyShowIn => "only_show_in",
+            OutputField::NotShowIn => "not_show_in",
+            OutputField::Path => "path",
+            OutputField::SourceRoot => "source_root",
+            OutputField::Source => "source",
+            OutputField::MtimeSec => "mtime_sec",
+            OutputField::Extra => "extra",
+        }
+    }
+}
+
+/// `--output-format` values for commands that print entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Desktop-id per line (e.g. to pipe into `launch`).
+    IdOnly,
+    /// Name per line, or the id when there is none (e.g. for dmenu/rofi).
+    NameOnly,
+    /// `<id>\t<name>` per line.
+    #[default]
+    IdTabName,
+    /// Pretty-printed JSON array.
+    Json,
+}
+
+pub fn print_entries(entries: &[DesktopEntryOut], fmt: OutputFormat) {
+    match fmt {
+        OutputFormat::IdOnly => {
+            for e in entries {
+                println!("{}", e.id);
+            }
+        }
+        OutputFormat::NameOnly => {
+            for e in entries {
+                println!("{}", e.display_name());
+            }
+        }
+        OutputFormat::IdTabName => {
+            for e in entries {
+                println!("{}\t{}", e.id, e.display_name());
+            }
+        }
+        OutputFormat::Json => print_json(&entries),
+    }
+}
+
+/// Empty the non-standard `extra` keys of `entries` and their actions
+/// (`--no-extra`).
+pub fn strip_extra(entries: &mut [DesktopEntryOut]) {
+    for e in entries {
+        e.extra.clear();
+        for a in &mut e.actions {
+            a.extra.clear();
+        }
+    }
+}
 
 pub fn print_json<T: Serialize>(value: &T) {
     let s = serde_json::to_string_pretty(value).unwrap();
     println!("{s}");
 }
+
+/// `<id>\0` per entry, for `xargs -0`.
+pub fn print_ids_nul(entries: &[DesktopEntryOut]) {
+    let mut out = std::io::stdout().lock();
+    let _ = write_ids_nul(&mut out, entries);
+    let _ = out.flush();
+}
+
+/// `print_ids_nul` into any writer.
+pub fn write_ids_nul(out: &mut impl Write, entries: &[DesktopEntryOut]) -> std::io::Result<()> {
+    for e in entries {
+        out.write_all(e.id.as_bytes())?;
+        out.write_all(b"\0")?;
+    }
+    Ok(())
+}
+
+const CSV_HEADER: &str = "id,name,generic_name,comment,icon,exec,categories,keywords";
+
+/// RFC 4180 CSV (CRLF line endings, header row). Categories and keywords are
+/// joined with `|`.
+pub fn print_csv(entries: &[DesktopEntryOut]) {
+    let mut out = std::io::stdout().lock();
+    let _ = write_csv(&mut out, entries);
+    let _ = out.flush();
+}
+
+/// `print_csv` into any writer.
+pub fn write_csv(out: &mut impl Write, entries: &[DesktopEntryOut]) -> std::io::Result<()> {
+    write!(out, "{CSV_HEADER}\r\n")?;
+    for e in entries {
+        let fields = [
+            e.id.as_str(),
+            e.name.as_deref().unwrap_or(""),
+            e.generic_name.as_deref().unwrap_or(""),
+            e.comment.as_deref().unwrap_or(""),
+            e.icon.as_deref().unwrap_or(""),
+            e.exec.as_deref().unwrap_or(""),
+            &e.categories.join("|"),
+            &e.keywords.join("|"),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        write!(out, "{}\r\n", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quote a field when it contains a delimiter, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Bucket entries by each of their categories (entries without any go under
+/// "Other"). Input order, i.e. score rank, is kept within each group.
+pub fn group_by_category(entries: Vec<DesktopEntryOut>) -> BTreeMap<String, Vec<DesktopEntryOut>> {
+    let mut groups: BTreeMap<String, Vec<DesktopEntryOut>> = BTreeMap::new();
+    for e in entries {
+        if e.categories.is_empty() {
+            groups.entry("Other".to_string()).or_default().push(e);
+            continue;
+        }
+        for c in &e.categories {
+            groups.entry(c.clone()).or_default().push(e.clone());
+        }
+    }
+    groups
+}
+
+/// `entry` as a JSON object holding only `fields`.
+pub fn filter_fields(entry: &DesktopEntryOut, fields: &[OutputField]) -> serde_json::Value {
+    let serde_json::Value::Object(mut full) = serde_json::to_value(entry).unwrap() else {
+        unreachable!("DesktopEntryOut serializes to an object");
+    };
+
+    let mut out = serde_json::Map::new();
+    for f in fields {
+        if let Some(v) = full.remove(f.key()) {
+            out.insert(f.key().to_string(), v);
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
+/// An entry in JSON output, either complete or cut down by `--output-fields`.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum EntryJson<'a> {
+    Full(&'a DesktopEntryOut),
+    Fields(serde_json::Value),
+}
+
+/// Entries for JSON output: full objects, or only `fields` when any are given.
+pub fn entries_json<'a>(
+    entries: &'a [DesktopEntryOut],
+    fields: &[OutputField],
+) -> Vec<EntryJson<'a>> {
+    entries
+        .iter()
+        .map(|e| {
+            if fields.is_empty() {
+                EntryJson::Full(e)
+            } else {
+                EntryJson::Fields(filter_fields(e, fields))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::parse_desktop_file_using_roots;
+
+    /// Parse `[Desktop Entry]` `keys` as `<id>.desktop`.
+    fn entry(id: &str, keys: &str) -> DesktopEntryOut {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{id}.desktop"));
+        std::fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\n{keys}\n"),
+        )
+        .unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C"))
+            .unwrap()
+            .out
+    }
+
+    fn csv(entries: &[DesktopEntryOut]) -> String {
+        let mut buf = Vec::new();
+        write_csv(&mut buf, entries).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_header_and_one_row_per_entry() {
+        let entries = [
+            entry(
+                "editor",
+                "Name=Editor\nIcon=editor\nExec=editor %F\nCategories=Utility;TextEditor;\nKeywords=text;code;",
+            ),
+            entry("bare", "Name=Bare\nExec=bare"),
+        ];
+        assert_eq!(
+            csv(&entries),
+            "id,name,generic_name,comment,icon,exec,categories,keywords\r\n\
+             editor,Editor,,,editor,editor %F,Utility|TextEditor,text|code\r\n\
+             bare,Bare,,,,bare,,\r\n"
+        );
+        assert_eq!(csv(&[]), format!("{CSV_HEADER}\r\n"));
+    }
+
+    #[test]
+    fn csv_quotes_commas_quotes_and_line_breaks() {
+        let entries = [entry(
+            "quoted",
+            "Name=Cut, Copy\nComment=Say \"hi\"\\nthen leave\nExec=app",
+        )];
+        assert_eq!(
+            csv(&entries),
+            format!(
+                "{CSV_HEADER}\r\nquoted,\"Cut, Copy\",,\"Say \"\"hi\"\"\nthen leave\",,app,,\r\n"
+            )
+        );
+        assert_eq!(csv_field("plain"), "plain");
+    }
+
+    fn ids(entries: &[DesktopEntryOut]) -> Vec<&str> {
+        entries.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    #[test]
+    fn groups_by_each_category_in_rank_order() {
+        let entries = vec![
+            entry("mpv", "Name=mpv\nExec=mpv\nCategories=AudioVideo;Player;"),
+            entry("calc", "Name=Calc\nExec=calc\nCategories=Utility;"),
+            entry("notes", "Name=Notes\nExec=notes"),
+            entry("vlc", "Name=VLC\nExec=vlc\nCategories=AudioVideo;Utility;"),
+        ];
+        let groups = group_by_category(entries);
+
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            ["AudioVideo", "Other", "Player", "Utility"]
+        );
+        assert_eq!(ids(&groups["AudioVideo"]), ["mpv", "vlc"]);
+        assert_eq!(ids(&groups["Player"]), ["mpv"]);
+        assert_eq!(ids(&groups["Utility"]), ["calc", "vlc"]);
+        assert_eq!(ids(&groups["Other"]), ["notes"]);
+    }
+
+    #[test]
+    fn filter_fields_keeps_only_the_requested_keys() {
+        let e = entry(
+            "editor",
+            "Name=Editor\nIcon=editor\nExec=editor %F\nCategories=Utility;",
+        );
+        let value = filter_fields(&e, &[OutputField::Id, OutputField::Name, OutputField::Exec]);
+        assert_eq!(
+            value,
+            serde_json::json!({"id": "editor", "name": "Editor", "exec": "editor %F"})
+        );
+
+        // A requested field the entry doesn't have is still there, as null.
+        let value = filter_fields(&e, &[OutputField::Id, OutputField::Comment]);
+        assert_eq!(value, serde_json::json!({"id": "editor", "comment": null}));
+    }
+
+    #[test]
+    fn nul_output_is_one_id_per_nul_without_newlines() {
+        let entries = [
+            entry("org.gnome.Files", "Name=Files\nExec=nautilus"),
+            entry("my app", "Name=My App\nExec=app"),
+        ];
+        let mut buf = Vec::new();
+        write_ids_nul(&mut buf, &entries).unwrap();
+
+        assert!(!buf.contains(&b'\n'));
+        let out = String::from_utf8(buf).unwrap();
+        let fields: Vec<&str> = out.split('\0').collect();
+        // Each id ends with a NUL, so the last field is empty.
+        assert_eq!(fields, ["org.gnome.Files", "my app", ""]);
+    }
+}
@@ -0,0 +1,257 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// How an app was installed, detected from its `Exec=` line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntrySource {
+    #[default]
+    Native,
+    Flatpak,
+    Snap,
+}
+
+impl EntrySource {
+    pub fn detect(exec: Option<&str>) -> Self {
+        // Basename of a path argument: `/usr/bin/flatpak` => `flatpak`.
+        fn program(arg: &str) -> &str {
+            arg.rsplit('/').next().unwrap_or(arg)
+        }
+
+        let argv: Vec<&str> = exec.unwrap_or("").split_whitespace().collect();
+
+        if argv.first().is_some_and(|a| program(a) == "flatpak") && argv.get(1) == Some(&"run") {
+            return EntrySource::Flatpak;
+        }
+
+        // Snap wrappers often sit behind `env VAR=... /snap/bin/app`.
+        let is_snap = argv
+            .windows(2)
+            .any(|w| program(w[0]) == "snap" && w[1] == "run")
+            || argv.iter().any(|a| a.starts_with("/snap/bin/"));
+        if is_snap {
+            return EntrySource::Snap;
+        }
+
+        EntrySource::Native
+    }
+}
+
+/// `--source` values: one `EntrySource`, or `all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceFilter {
+    All,
+    Native,
+    Flatpak,
+    Snap,
+}
+
+impl SourceFilter {
+    pub fn source(self) -> Option<EntrySource> {
+        match self {
+            SourceFilter::All => None,
+            SourceFilter::Native => Some(EntrySource::Native),
+            SourceFilter::Flatpak => Some(EntrySource::Flatpak),
+            SourceFilter::Snap => Some(EntrySource::Snap),
+        }
+    }
+}
+
+/// Application roots that package managers export outside the default
+/// `XDG_DATA_DIRS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanSource {
+    Flatpak,
+    Snap,
+    Nix,
+    Guix,
+}
+
+impl ScanSource {
+    pub const ALL: [ScanSource; 4] = [
+        ScanSource::Flatpak,
+        ScanSource::Snap,
+        ScanSource::Nix,
+        ScanSource::Guix,
+    ];
+
+    /// Candidate roots for this source, whether or not they exist.
+    pub fn roots(self) -> Vec<PathBuf> {
+        match self {
+            ScanSource::Flatpak => flatpak_scan_roots(),
+            ScanSource::Snap => snap_scan_roots(),
+            ScanSource::Nix => nix_extra_roots(),
+            ScanSource::Guix => guix_extra_roots(),
+        }
+    }
+}
+
+/// Per-user then system-wide Flatpak exports.
+pub fn flatpak_scan_roots() -> Vec<PathBuf> {
+    // XDG_DATA_HOME (default ~/.local/share)
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = env::var_os("HOME").unwrap_or_default();
+            PathBuf::from(home).join(".local/share")
+        });
+
+    flatpak_scan_roots_in(&data_home, Path::new("/"))
+}
+
+/// `flatpak_scan_roots` for an explicit data home, with the system-wide export
+/// under `root`.
+fn flatpak_scan_roots_in(data_home: &Path, root: &Path) -> Vec<PathBuf> {
+    vec![
+        data_home.join("flatpak/exports/share/applications"),
+        root.join("var/lib/flatpak/exports/share/applications"),
+    ]
+}
+
+/// Where snapd exports the `.desktop` files of installed snaps.
+pub fn snap_scan_roots() -> Vec<PathBuf> {
+    snap_scan_roots_in(Path::new("/"))
+}
+
+/// `snap_scan_roots` under `root`.
+fn snap_scan_roots_in(root: &Path) -> Vec<PathBuf> {
+    vec![root.join("var/lib/snapd/desktop/applications")]
+}
+
+/// Nix profiles: the user's, the NixOS system profile and the default profile,
+/// plus anything listed in `NIX_PROFILES`.
+pub fn nix_extra_roots() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let mut profiles: Vec<PathBuf> = Vec::new();
+    profiles.extend(home.map(|h| h.join(".nix-profile")));
+    profiles.push(PathBuf::from("/run/current-system/sw"));
+    profiles.push(PathBuf::from("/nix/var/nix/profiles/default"));
+    // Space-separated, lowest precedence first (as set by nix's profile script).
+    if let Ok(nix_profiles) = env::var("NIX_PROFILES") {
+        profiles.extend(nix_profiles.split_whitespace().rev().map(PathBuf::from));
+    }
+
+    profile_applications(profiles)
+}
+
+/// Guix profiles: the user's and the Guix System profile.
+pub fn guix_extra_roots() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let mut profiles: Vec<PathBuf> = Vec::new();
+    profiles.extend(home.map(|h| h.join(".guix-profile")));
+    profiles.push(PathBuf::from("/run/current-system/profile"));
+
+    profile_applications(profiles)
+}
+
+/// `<profile>/share/applications` for each profile, without duplicates.
+fn profile_applications(profiles: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut out: Vec<PathBuf> = Vec::with_capacity(profiles.len());
+    for p in profiles {
+        let apps = p.join("share/applications");
+        if !out.contains(&apps) {
+            out.push(apps);
+        }
+    }
+    out
+}
+
+/// Existing roots from every `ScanSource`, in `ScanSource::ALL` order.
+pub fn discovered_roots() -> Vec<PathBuf> {
+    existing_dirs(ScanSource::ALL.into_iter().flat_map(ScanSource::roots))
+}
+
+/// The candidates that are directories, in order.
+fn existing_dirs(candidates: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    candidates.into_iter().filter(|p| p.is_dir()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_source_from_exec() {
+        let detect = |exec| EntrySource::detect(Some(exec));
+        assert_eq!(
+            detect("flatpak run org.mozilla.firefox"),
+            EntrySource::Flatpak
+        );
+        assert_eq!(
+            detect("/usr/bin/flatpak run --branch=stable --command=gimp org.gimp.GIMP %U"),
+            EntrySource::Flatpak
+        );
+        // Managing Flatpak is not running an app from it.
+        assert_eq!(detect("flatpak update"), EntrySource::Native);
+        assert_eq!(detect("firefox %u"), EntrySource::Native);
+        assert_eq!(EntrySource::detect(None), EntrySource::Native);
+    }
+
+    #[test]
+    fn flatpak_roots_are_per_user_then_system() {
+        assert_eq!(
+            flatpak_scan_roots_in(Path::new("/home/u/.local/share"), Path::new("/")),
+            [
+                PathBuf::from("/home/u/.local/share/flatpak/exports/share/applications"),
+                PathBuf::from("/var/lib/flatpak/exports/share/applications"),
+            ]
+        );
+        assert_eq!(
+            flatpak_scan_roots_in(Path::new("/data"), Path::new("/sysroot")),
+            [
+                PathBuf::from("/data/flatpak/exports/share/applications"),
+                PathBuf::from("/sysroot/var/lib/flatpak/exports/share/applications"),
+            ]
+        );
+    }
+
+    #[test]
+    fn snap_root_is_used_once_it_exists() {
+        let root = tempfile::tempdir().unwrap();
+        let snap = root.path().join("var/lib/snapd/desktop/applications");
+        assert_eq!(snap_scan_roots_in(root.path()), std::slice::from_ref(&snap));
+        assert!(existing_dirs(snap_scan_roots_in(root.path())).is_empty());
+
+        std::fs::create_dir_all(&snap).unwrap();
+        assert_eq!(existing_dirs(snap_scan_roots_in(root.path())), [snap]);
+        assert_eq!(
+            snap_scan_roots(),
+            [PathBuf::from("/var/lib/snapd/desktop/applications")]
+        );
+    }
+
+    #[test]
+    fn detect_snap_wrappers() {
+        let detect = |exec| EntrySource::detect(Some(exec));
+        assert_eq!(detect("/usr/bin/snap run spotify %U"), EntrySource::Snap);
+        assert_eq!(
+            detect(
+                "env BAMF_DESKTOP_FILE_HINT=/var/lib/snapd/desktop/applications/code_code.desktop /snap/bin/code --force-user-env %F"
+            ),
+            EntrySource::Snap
+        );
+        assert_eq!(detect("snap install spotify"), EntrySource::Native);
+    }
+
+    #[test]
+    fn profile_applications_skips_repeated_profiles() {
+        let roots = profile_applications(vec![
+            PathBuf::from("/home/u/.nix-profile"),
+            PathBuf::from("/run/current-system/sw"),
+            PathBuf::from("/home/u/.nix-profile"),
+        ]);
+        assert_eq!(
+            roots,
+            [
+                PathBuf::from("/home/u/.nix-profile/share/applications"),
+                PathBuf::from("/run/current-system/sw/share/applications"),
+            ]
+        );
+    }
+}
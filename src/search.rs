@@ -1,32 +1,91 @@
+use crate::desktop::absolute_path_string;
 use crate::empty_query::EmptyQueryMode;
 use crate::frequency::Usage;
 use crate::models::{DesktopEntryIndexed, DesktopEntryOut};
-use std::collections::HashMap;
+use crate::normalize::{is_cjk, normalize_unicode};
+use crate::scan_sources::EntrySource;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{cmp::Reverse, collections::BinaryHeap};
 
+/// Include tokens of `query`; `!`-prefixed words are left to `exclude_tokens`.
 pub fn normalize_query(query: &str) -> Vec<String> {
     let mut tokens: Vec<String> = Vec::new();
+    for word in query.split_whitespace() {
+        if !word.starts_with('!') {
+            push_tokens(&mut tokens, word);
+        }
+    }
+
+    // Most selective first => fail faster.
+    tokens.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    tokens.dedup();
+
+    tokens
+}
+
+/// Tokens of the `!`-prefixed words in `query` ("editor !vim" => ["vim"]).
+pub fn exclude_tokens(query: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for word in query.split_whitespace() {
+        if let Some(word) = word.strip_prefix('!') {
+            push_tokens(&mut tokens, word);
+        }
+    }
+
+    tokens.sort();
+    tokens.dedup();
 
+    tokens
+}
+
+/// Normalized alphanumeric runs of `text`, appended to `tokens`. Runs with CJK
+/// ideographs are split further by `tokenize_cjk`.
+fn push_tokens(tokens: &mut Vec<String>, text: &str) {
+    let mut runs: Vec<String> = Vec::new();
     let mut buf = String::new();
-    for ch in query.trim().chars() {
+    for ch in normalize_unicode(text).chars() {
         if ch.is_alphanumeric() {
-            for lc in ch.to_lowercase() {
-                buf.push(lc);
-            }
+            buf.push(ch);
         } else if !buf.is_empty() {
-            tokens.push(std::mem::take(&mut buf));
+            runs.push(std::mem::take(&mut buf));
         }
     }
     if !buf.is_empty() {
-        tokens.push(buf);
+        runs.push(buf);
     }
 
-    // Most selective first => fail faster.
-    tokens.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
-    tokens.dedup();
+    for run in runs {
+        if run.chars().any(is_cjk) {
+            tokens.extend(tokenize_cjk(&run));
+        } else {
+            tokens.push(run);
+        }
+    }
+}
 
-    tokens
+/// Split `s` into single CJK ideographs and the runs of other characters
+/// between them: "qq音乐" => ["qq", "音", "乐"]. Matches how `make_norm` spaces
+/// ideographs apart.
+pub fn tokenize_cjk(s: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut buf = String::new();
+    for ch in s.chars() {
+        if is_cjk(ch) {
+            if !buf.is_empty() {
+                out.push(std::mem::take(&mut buf));
+            }
+            out.push(ch.to_string());
+        } else {
+            buf.push(ch);
+        }
+    }
+    if !buf.is_empty() {
+        out.push(buf);
+    }
+    out
 }
 
 pub fn norm_has_token_prefix(norm: &str, token: &str) -> bool {
@@ -48,12 +107,251 @@ pub fn norm_has_token_prefix(norm: &str, token: &str) -> bool {
     false
 }
 
+/// Tokens shorter than this never use fuzzy matching (too many false positives).
+pub const FUZZY_MIN_TOKEN_LEN: usize = 4;
+pub const DEFAULT_FUZZY_MAX_DISTANCE: usize = 1;
+/// Largest `--fuzzy-distance`; more edits match nearly anything.
+pub const MAX_FUZZY_DISTANCE: u8 = 3;
+pub const DEFAULT_FUZZY_PENALTY: i32 = 30;
+/// Result count when a search request has no `limit`.
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FuzzyOptions {
+    pub max_distance: usize,
+    /// Subtracted from relevance for each edit a fuzzy-only token needed.
+    pub penalty: i32,
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        Self {
+            max_distance: DEFAULT_FUZZY_MAX_DISTANCE,
+            penalty: DEFAULT_FUZZY_PENALTY,
+        }
+    }
+}
+
+/// Relevance weights used by `score_entry`. Configurable via `[weights]` in
+/// config.toml or `--weight-*` flags; `Default` is the built-in ranking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchWeights {
+    /// Token at a word boundary of the name (minus its position).
+    pub name_boundary: i32,
+    /// Token anywhere else in the name (minus its position).
+    pub name_substring: i32,
+    /// Token at a `-`/`_`/`.` boundary of the desktop-id (minus its position).
+    pub id_boundary: i32,
+    /// Token anywhere else in the desktop-id (minus its position).
+    pub id_substring: i32,
+    /// Bonus when every token matches the name at a boundary.
+    pub all_tokens_in_name: i32,
+    /// Upper bound of the shorter-name bonus (one point per char under this).
+    pub short_name: i32,
+    /// Points per recorded launch (launch count is capped at 20).
+    pub freq: i32,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            name_boundary: 140,
+            name_substring: 80,
+            id_boundary: 110,
+            id_substring: 60,
+            all_tokens_in_name: 120,
+            short_name: 30,
+            freq: 2,
+        }
+    }
+}
+
+/// How queries are matched and ranked. `fuzzy` and `weights` only affect
+/// non-empty queries; `pins` apply to both.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scoring<'a> {
+    pub fuzzy: Option<FuzzyOptions>,
+    pub weights: SearchWeights,
+    /// Desktop ids always listed first, in this order.
+    pub pins: &'a [String],
+    /// Matches scoring below this are dropped (non-empty queries only).
+    pub min_score: Option<i32>,
+}
+
+/// Stable-sort `entries` by `score_entry` against `query`, best first, without
+/// dropping anything. Entries whose name and id match no token (or that are
+/// missing from `indexed`) follow in their current order: their scores would
+/// only reflect name length and usage.
+pub fn sort_by_relevance(
+    entries: &mut [DesktopEntryOut],
+    indexed: &[DesktopEntryIndexed],
+    query: &str,
+    usage: &HashMap<String, Usage>,
+    scoring: &Scoring,
+) {
+    let tokens = normalize_query(query);
+    let now_sec = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let by_id: HashMap<&str, &DesktopEntryIndexed> =
+        indexed.iter().map(|e| (e.out.id.as_str(), e)).collect();
+
+    entries.sort_by_cached_key(|e| {
+        let score = by_id.get(e.id.as_str()).and_then(|ie| {
+            let u = usage.get(&e.id).copied().unwrap_or_default();
+            let b = score_breakdown(ie, &tokens, u, now_sec, scoring);
+            (b.name_match + b.id_match > 0).then(|| b.total())
+        });
+        Reverse(score)
+    });
+}
+
+/// Whether desktop id `id` is listed in `pins` (with or without `.desktop`).
+pub fn is_pinned(id: &str, pins: &[String]) -> bool {
+    pins.iter().any(|p| p.trim_end_matches(".desktop") == id)
+}
+
+/// Move pinned entries to the front (in `pins` order); the rest keep their order.
+pub fn apply_pins(entries: &mut [DesktopEntryOut], pins: &[String]) {
+    if pins.is_empty() {
+        return;
+    }
+    entries.sort_by_key(|e| {
+        pins.iter()
+            .position(|p| p.trim_end_matches(".desktop") == e.id)
+            .unwrap_or(pins.len())
+    });
+}
+
+/// Keep one entry per case-insensitive name: the one found under the earliest
+/// of `roots` (scan priority), e.g. the native package over its Flatpak twin.
+/// Survivors keep their position; entries without a name are all kept.
+pub fn dedup_by_name(entries: &mut Vec<DesktopEntryOut>, roots: &[String]) {
+    let roots: Vec<String> = roots
+        .iter()
+        .map(|r| absolute_path_string(Path::new(r)))
+        .collect();
+    let rank = |e: &DesktopEntryOut| {
+        roots
+            .iter()
+            .position(|r| *r == e.source_root)
+            .unwrap_or(usize::MAX)
+    };
+
+    // name => (root rank, index) of the entry to keep
+    let mut best: HashMap<String, (usize, usize)> = HashMap::new();
+    for (idx, e) in entries.iter().enumerate() {
+        let Some(name) = &e.name else {
+            continue;
+        };
+        let candidate = (rank(e), idx);
+        best.entry(name.to_lowercase())
+            .and_modify(|b| {
+                if candidate.0 < b.0 {
+                    *b = candidate;
+                }
+            })
+            .or_insert(candidate);
+    }
+
+    let keep: HashSet<usize> = best.values().map(|&(_, idx)| idx).collect();
+    let mut idx = 0;
+    entries.retain(|e| {
+        let kept = e.name.is_none() || keep.contains(&idx);
+        idx += 1;
+        kept
+    });
+}
+
+/// Typo-tolerant variant of `norm_has_token_prefix`: true if some word of `norm`
+/// starts with a string within `max_distance` edits of `token`.
+///
+/// Edits are insertions, deletions, substitutions and adjacent transpositions,
+/// so "fierfox" still finds "firefox".
+pub fn fuzzy_has_token_prefix(norm: &str, token: &str, max_distance: usize) -> bool {
+    norm_has_token_prefix(norm, token) || fuzzy_token_distance(norm, token, max_distance).is_some()
+}
+
+/// Fewest edits (at most `max_distance`) turning `token` into a prefix of
+/// some word of `norm`; `None` past that or for tokens too short to fuzz.
+pub fn fuzzy_token_distance(norm: &str, token: &str, max_distance: usize) -> Option<usize> {
+    let n = token.chars().count();
+    if n < FUZZY_MIN_TOKEN_LEN || max_distance == 0 {
+        return None;
+    }
+
+    // Prefixes more than `max_distance` characters shorter or longer than the
+    // token can't be close enough, so only those lengths are compared.
+    let shortest = n.saturating_sub(max_distance).max(1);
+    let longest = n + max_distance;
+    norm.split(' ')
+        .filter(|w| !w.is_empty())
+        .flat_map(|w| {
+            w.char_indices()
+                .map(|(i, _)| i)
+                .skip(1)
+                .chain([w.len()])
+                .zip(1..)
+                .filter(move |&(_, len)| (shortest..=longest).contains(&len))
+                .map(move |(end, _)| &w[..end])
+        })
+        .map(|prefix| levenshtein(token, prefix))
+        .min()
+        .filter(|&d| d <= max_distance)
+}
+
+/// Edit distance between `a` and `b`: insertions, deletions and substitutions,
+/// with an adjacent transposition counted as one edit (optimal string alignment).
+///
+/// Uses one flat `(a + 1) * (b + 1)` table; query tokens and words are short.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let width = b.len() + 1;
+    let mut d = vec![0; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        d[i * width] = i;
+    }
+    for (j, cell) in d.iter_mut().enumerate().take(width) {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut v = (d[(i - 1) * width + j] + 1)
+                .min(d[i * width + j - 1] + 1)
+                .min(d[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                v = v.min(d[(i - 2) * width + j - 2] + 1);
+            }
+            d[i * width + j] = v;
+        }
+    }
+
+    d[a.len() * width + b.len()]
+}
+
+/// Whether every token matches `norm` (exactly, or fuzzily when enabled).
+pub fn norm_matches_tokens(norm: &str, tokens: &[String], fuzzy: Option<FuzzyOptions>) -> bool {
+    match fuzzy {
+        None => tokens.iter().all(|t| norm_has_token_prefix(norm, t)),
+        Some(f) => tokens
+            .iter()
+            .all(|t| fuzzy_has_token_prefix(norm, t, f.max_distance)),
+    }
+}
+
 pub fn search_entries_with_usage_map_and_empty_mode(
     entries: &[DesktopEntryIndexed],
     query: &str,
     limit: usize,
     usage: &HashMap<String, Usage>,
     empty_mode: EmptyQueryMode,
+    scoring: &Scoring,
+    filter: &EntryFilter,
 ) -> Vec<DesktopEntryOut> {
     if limit == 0 {
         return Vec::new();
@@ -61,27 +359,60 @@ pub fn search_entries_with_usage_map_and_empty_mode(
 
     let tokens = normalize_query(query);
     if tokens.is_empty() {
-        return empty_query_entries(entries, limit, usage, empty_mode);
+        // With an exact name, the name already picks the entries; recency etc.
+        // would only hide some.
+        let empty_mode = if filter.exact_name.is_some() {
+            EmptyQueryMode::AllApps
+        } else {
+            empty_mode
+        };
+        let mut out = empty_query_entries(entries, limit, usage, empty_mode, filter);
+        // Pinned apps show up even if they were never launched.
+        for pin in scoring.pins {
+            let id = pin.trim_end_matches(".desktop");
+            if !out.iter().any(|e| e.id == id)
+                && let Some(e) = entries.iter().find(|e| {
+                    e.out.id == id
+                        && filter.accepts_indexed(e)
+                        && filter.accepts_usage(usage, &e.out.id)
+                })
+            {
+                out.push(e.out.clone());
+            }
+        }
+        apply_pins(&mut out, scoring.pins);
+        out.truncate(limit);
+        return out;
     }
 
-    // Keep only top-K scored candidates.
+    // Keep only top-K scored candidates; pinned matches are kept aside so a low
+    // score can't push them out of the heap.
     let mut heap: BinaryHeap<Reverse<(i32, usize)>> = BinaryHeap::new();
+    let mut pinned: Vec<(i32, usize)> = Vec::new();
 
     let now_sec = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
         .unwrap_or(0);
 
-    'outer: for (idx, e) in entries.iter().enumerate() {
-        for t in &tokens {
-            if !norm_has_token_prefix(&e.norm, t) {
-                continue 'outer;
-            }
+    for (idx, e) in entries.iter().enumerate() {
+        if !filter.accepts_indexed(e)
+            || !filter.accepts_usage(usage, &e.out.id)
+            || !norm_matches_tokens(&e.norm, &tokens, scoring.fuzzy)
+        {
+            continue;
         }
 
         let u = usage.get(&e.out.id).copied().unwrap_or_default();
-        let score = score_entry(e, &tokens, u, now_sec);
+        let score = score_entry(e, &tokens, u, now_sec, scoring);
+        if scoring.min_score.is_some_and(|min| score < min) {
+            continue;
+        }
 
+        if is_pinned(&e.out.id, scoring.pins) {
+            pinned.push((score, idx));
+            continue;
+        }
         heap.push(Reverse((score, idx)));
         if heap.len() > limit {
             heap.pop();
@@ -90,12 +421,16 @@ pub fn search_entries_with_usage_map_and_empty_mode(
 
     // heap is min-heap via Reverse; drain then sort by score desc.
     let mut picked: Vec<(i32, usize)> = heap.into_iter().map(|Reverse(x)| x).collect();
-    picked.sort_by(|a, b| b.0.cmp(&a.0));
+    picked.extend(pinned);
+    picked.sort_by_key(|p| Reverse(p.0));
 
-    picked
+    let mut out: Vec<DesktopEntryOut> = picked
         .into_iter()
         .map(|(_, idx)| entries[idx].out.clone())
-        .collect()
+        .collect();
+    apply_pins(&mut out, scoring.pins);
+    out.truncate(limit);
+    out
 }
 
 fn empty_query_entries(
@@ -103,25 +438,47 @@ fn empty_query_entries(
     limit: usize,
     usage: &HashMap<String, Usage>,
     empty_mode: EmptyQueryMode,
+    filter: &EntryFilter,
 ) -> Vec<DesktopEntryOut> {
+    if empty_mode == EmptyQueryMode::AllApps {
+        let mut all: Vec<&DesktopEntryIndexed> = entries
+            .iter()
+            .filter(|e| filter.accepts_indexed(e) && filter.accepts_usage(usage, &e.out.id))
+            .collect();
+        // Normalized names so the order is case- and accent-insensitive.
+        all.sort_by(|a, b| {
+            let a_name = a.name_lc.as_deref().unwrap_or("");
+            let b_name = b.name_lc.as_deref().unwrap_or("");
+            a_name.cmp(b_name).then_with(|| a.out.id.cmp(&b.out.id))
+        });
+        return all.into_iter().take(limit).map(|e| e.out.clone()).collect();
+    }
+
     let mut picked: Vec<(usize, Usage)> = entries
         .iter()
         .enumerate()
+        .filter(|(_idx, e)| filter.accepts_indexed(e) && filter.accepts_usage(usage, &e.out.id))
         .filter_map(|(idx, e)| usage.get(&e.out.id).copied().map(|u| (idx, u)))
         .filter(|(_idx, u)| match empty_mode {
             EmptyQueryMode::Recency => u.last_used != 0,
-            EmptyQueryMode::Frequency => u.freq != 0,
+            EmptyQueryMode::Frequency | EmptyQueryMode::Frecency => u.freq != 0,
+            EmptyQueryMode::AllApps => unreachable!("all-apps returns early"),
         })
         .collect();
 
+    let now_sec = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
     picked.sort_by(|(a_idx, a_u), (b_idx, b_u)| match empty_mode {
         EmptyQueryMode::Recency => b_u
             .last_used
             .cmp(&a_u.last_used)
             .then_with(|| b_u.freq.cmp(&a_u.freq))
             .then_with(|| {
-                let a_name = entries[*a_idx].out.name.as_deref().unwrap_or("");
-                let b_name = entries[*b_idx].out.name.as_deref().unwrap_or("");
+                let a_name = entries[*a_idx].out.display_name();
+                let b_name = entries[*b_idx].out.display_name();
                 a_name.cmp(b_name)
             })
             .then_with(|| entries[*a_idx].out.id.cmp(&entries[*b_idx].out.id)),
@@ -129,11 +486,21 @@ fn empty_query_entries(
             .cmp(&a_u.freq)
             .then_with(|| b_u.last_used.cmp(&a_u.last_used))
             .then_with(|| {
-                let a_name = entries[*a_idx].out.name.as_deref().unwrap_or("");
-                let b_name = entries[*b_idx].out.name.as_deref().unwrap_or("");
+                let a_name = entries[*a_idx].out.display_name();
+                let b_name = entries[*b_idx].out.display_name();
+                a_name.cmp(b_name)
+            })
+            .then_with(|| entries[*a_idx].out.id.cmp(&entries[*b_idx].out.id)),
+        EmptyQueryMode::Frecency => frecency(*b_u, now_sec)
+            .cmp(&frecency(*a_u, now_sec))
+            .then_with(|| b_u.last_used.cmp(&a_u.last_used))
+            .then_with(|| {
+                let a_name = entries[*a_idx].out.display_name();
+                let b_name = entries[*b_idx].out.display_name();
                 a_name.cmp(b_name)
             })
             .then_with(|| entries[*a_idx].out.id.cmp(&entries[*b_idx].out.id)),
+        EmptyQueryMode::AllApps => unreachable!("all-apps returns early"),
     });
 
     picked
@@ -143,36 +510,239 @@ fn empty_query_entries(
         .collect()
 }
 
-pub fn score_entry(e: &DesktopEntryIndexed, tokens: &[String], usage: Usage, now_sec: u64) -> i32 {
-    let mut score: i32 = 0;
+/// Entries declaring `mime_type` in `MimeType=` that `filter` accepts, most
+/// frequently launched first, then by name.
+pub fn entries_for_mime(
+    entries: &[DesktopEntryIndexed],
+    mime_type: &str,
+    usage: &HashMap<String, Usage>,
+    filter: &EntryFilter,
+) -> Vec<DesktopEntryOut> {
+    let mut picked: Vec<(&DesktopEntryOut, u32)> = entries
+        .iter()
+        .filter(|e| {
+            e.out
+                .mime_types
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(mime_type))
+                && filter.accepts(&e.out)
+        })
+        .map(|e| {
+            let freq = usage.get(&e.out.id).map(|u| u.freq).unwrap_or(0);
+            (&e.out, freq)
+        })
+        .collect();
 
-    // Primary: textual relevance. This should dominate over usage for non-empty queries.
-    let mut relevance: i32 = 0;
+    picked.sort_by(|(a, a_freq), (b, b_freq)| {
+        b_freq
+            .cmp(a_freq)
+            .then_with(|| a.display_name().cmp(b.display_name()))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    picked.into_iter().map(|(e, _)| e.clone()).collect()
+}
+
+/// Per-query entry filters, applied before scoring.
+///
+/// Checked at query time (not index build time) so one daemon index can serve
+/// requests with different filters.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter<'a> {
+    /// Only entries listing this category (case-insensitive).
+    pub category: Option<&'a str>,
+    /// Include entries with `Hidden=true`.
+    pub show_hidden: bool,
+    /// Include entries with `NoDisplay=true`.
+    pub show_nodisplay: bool,
+    /// Reject entries matching any of these tokens (see `exclude_tokens`).
+    pub exclude: &'a [String],
+    /// Only entries from this packaging source.
+    pub source: Option<EntrySource>,
+    /// Only entries listing this MIME type (case-insensitive).
+    pub mime_type: Option<&'a str>,
+    /// Only entries whose `Name` is exactly this (case-insensitive).
+    pub exact_name: Option<&'a str>,
+    /// Only entries with this `Type=` (`Application`, `Link` or `Directory`).
+    pub type_filter: Option<&'a str>,
+    /// Only entries whose file was modified at or after this time (unix seconds).
+    pub modified_since: Option<u64>,
+    /// Only entries last launched at or after this time (unix seconds); see
+    /// `accepts_usage`.
+    pub used_since: Option<u64>,
+}
+
+/// Candidates for `open`: never `Hidden=true` (deleted) apps, but `NoDisplay=true`
+/// ones count, since the spec uses it for MIME handlers without a menu entry.
+pub const OPEN_HANDLER_FILTER: EntryFilter<'static> = EntryFilter {
+    category: None,
+    show_hidden: false,
+    show_nodisplay: true,
+    exclude: &[],
+    source: None,
+    mime_type: None,
+    exact_name: None,
+    type_filter: None,
+    modified_since: None,
+    used_since: None,
+};
+
+impl EntryFilter<'_> {
+    pub fn accepts(&self, e: &DesktopEntryOut) -> bool {
+        if !self.show_hidden && e.hidden == Some(true) {
+            return false;
+        }
+        if !self.show_nodisplay && e.nodisplay == Some(true) {
+            return false;
+        }
+        if self.source.is_some_and(|s| s != e.source) {
+            return false;
+        }
+        if let Some(mime) = self.mime_type
+            && !e.mime_types.iter().any(|m| m.eq_ignore_ascii_case(mime))
+        {
+            return false;
+        }
+        if let Some(since) = self.modified_since
+            && e.mtime_sec.is_none_or(|m| m < since)
+        {
+            return false;
+        }
+        if self
+            .type_filter
+            .is_some_and(|t| e.type_.as_deref() != Some(t))
+        {
+            return false;
+        }
+        if let Some(name) = self.exact_name
+            && !e.name.as_deref().is_some_and(|n| eq_lowercase(n, name))
+        {
+            return false;
+        }
+        entry_in_category(e, self.category)
+    }
+
+    /// The `used_since` check, which needs the launch history.
+    pub fn accepts_usage(&self, usage: &HashMap<String, Usage>, id: &str) -> bool {
+        self.used_since
+            .is_none_or(|since| usage.get(id).is_some_and(|u| u.last_used >= since))
+    }
+
+    /// `accepts` plus the token exclusions, which need the normalized text.
+    pub fn accepts_indexed(&self, e: &DesktopEntryIndexed) -> bool {
+        self.accepts(&e.out)
+            && !self
+                .exclude
+                .iter()
+                .any(|t| norm_has_token_prefix(&e.norm, t))
+    }
+}
+
+/// Unicode-aware `a.to_lowercase() == b.to_lowercase()`, without allocating.
+fn eq_lowercase(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Case-insensitive `Categories=` membership check; `None` accepts everything.
+fn entry_in_category(e: &DesktopEntryOut, category: Option<&str>) -> bool {
+    let Some(category) = category else {
+        return true;
+    };
+
+    e.categories
+        .iter()
+        .any(|c| c.eq_ignore_ascii_case(category))
+}
+
+pub fn score_entry(
+    e: &DesktopEntryIndexed,
+    tokens: &[String],
+    usage: Usage,
+    now_sec: u64,
+    scoring: &Scoring,
+) -> i32 {
+    score_breakdown(e, tokens, usage, now_sec, scoring).total()
+}
+
+/// The parts `score_entry` adds up, as shown by `search --explain`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScoreBreakdown {
+    /// Textual part: every field below except usage and `base`.
+    pub relevance: i32,
+    /// Tokens whose best match is in the name (boundary or substring, minus position).
+    pub name_match: i32,
+    /// Tokens whose best match is in the desktop-id.
+    pub id_match: i32,
+    /// Penalty for tokens that only matched fuzzily (zero or negative).
+    pub fuzzy_penalty: i32,
+    pub all_tokens_in_name_bonus: i32,
+    pub short_name_bonus: i32,
+    pub freq_bonus: i32,
+    pub recency_bonus: i32,
+    /// Constant so scores stay positive.
+    pub base: i32,
+}
 
+impl ScoreBreakdown {
+    pub fn total(&self) -> i32 {
+        self.relevance + self.freq_bonus + self.recency_bonus + self.base
+    }
+}
+
+pub fn score_breakdown(
+    e: &DesktopEntryIndexed,
+    tokens: &[String],
+    usage: Usage,
+    now_sec: u64,
+    scoring: &Scoring,
+) -> ScoreBreakdown {
+    let w = &scoring.weights;
+    let mut b = ScoreBreakdown {
+        base: 10,
+        ..ScoreBreakdown::default()
+    };
+
+    // Primary: textual relevance. This should dominate over usage for non-empty queries.
     let name_lc = e.name_lc.as_deref().unwrap_or("");
     let id_lc = e.id_lc.as_str();
 
     // Per-token relevance: prefer boundary matches, and prefer earlier matches.
     // This helps "browser" rank "Zen Browser" above e.g. "Avahi SSH Server Browser".
     for t in tokens {
-        let mut best: i32 = 0;
+        let mut name_best: i32 = 0;
+        let mut id_best: i32 = 0;
 
         if !name_lc.is_empty() {
             if let Some(pos) = find_boundary_match(name_lc, t, b" ") {
-                best = best.max(140 - (pos.min(80) as i32));
+                name_best = w.name_boundary - (pos.min(80) as i32);
             } else if let Some(pos) = name_lc.find(t) {
-                best = best.max(80 - (pos.min(80) as i32));
+                name_best = w.name_substring - (pos.min(80) as i32);
             }
         }
 
         // Desktop IDs tend to have separators; treat them as boundaries.
         if let Some(pos) = find_boundary_match(id_lc, t, b"-_.") {
-            best = best.max(110 - (pos.min(80) as i32));
+            id_best = w.id_boundary - (pos.min(80) as i32);
         } else if let Some(pos) = id_lc.find(t) {
-            best = best.max(60 - (pos.min(80) as i32));
+            id_best = w.id_substring - (pos.min(80) as i32);
+        }
+
+        if id_best > name_best.max(0) {
+            b.id_match += id_best;
+        } else {
+            b.name_match += name_best.max(0);
         }
 
-        relevance += best;
+        // Fuzzy-only hits rank below any exact match of the same token, and
+        // further down the more edits they needed.
+        if let Some(f) = scoring.fuzzy
+            && !norm_has_token_prefix(&e.norm, t)
+        {
+            let edits = fuzzy_token_distance(&e.norm, t, f.max_distance).unwrap_or(1);
+            b.fuzzy_penalty -= f.penalty * edits as i32;
+        }
     }
 
     // Bonus if all tokens match name at a boundary (strong signal).
@@ -181,23 +751,35 @@ pub fn score_entry(e: &DesktopEntryIndexed, tokens: &[String], usage: Usage, now
             .iter()
             .all(|t| find_boundary_match(name_lc, t, b" ").is_some())
     {
-        relevance += 120;
+        b.all_tokens_in_name_bonus = w.all_tokens_in_name;
     }
 
     // Small preference for shorter names when otherwise equal.
     if !name_lc.is_empty() {
-        relevance += (30 - (name_lc.len().min(30) as i32)).max(0);
+        let cap = w.short_name.max(0);
+        b.short_name_bonus = cap - name_lc.len().min(cap as usize) as i32;
     }
 
-    score += relevance;
+    b.relevance = b.name_match
+        + b.id_match
+        + b.fuzzy_penalty
+        + b.all_tokens_in_name_bonus
+        + b.short_name_bonus;
 
     // Secondary: usage (bounded, tie-breaker-ish).
     // Keep this smaller than relevance so frequent but weak matches don't dominate.
-    score += (usage.freq.min(20) as i32) * 2;
-    score += recency_bonus(usage.last_used, now_sec);
+    b.freq_bonus = (usage.freq.min(20) as i32) * w.freq;
+    b.recency_bonus = recency_bonus(usage.last_used, now_sec);
 
-    // Base constant so scores remain positive-ish and stable.
-    score + 10
+    b
+}
+
+/// Frequency weighted by how recently the entry was used.
+///
+/// Reuses the `recency_bonus` steps as a multiplier, so an app launched 50 times
+/// but not in the last month scores 0 and falls behind anything used recently.
+fn frecency(usage: Usage, now_sec: u64) -> u64 {
+    u64::from(usage.freq) * recency_bonus(usage.last_used, now_sec) as u64
 }
 
 fn recency_bonus(last_used: u64, now_sec: u64) -> i32 {
@@ -248,3 +830,699 @@ fn find_boundary_match(haystack: &str, needle: &str, boundary_bytes: &[u8]) -> O
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::parse_desktop_file_using_roots;
+
+    /// Parse `[Desktop Entry]` `keys` as `<id>.desktop`.
+    fn entry(id: &str, keys: &str) -> DesktopEntryIndexed {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{id}.desktop"));
+        std::fs::write(
+            &path,
+            format!("[Desktop Entry]\nType=Application\n{keys}\n"),
+        )
+        .unwrap();
+        parse_desktop_file_using_roots(&path, &[dir.path().to_path_buf()], Some("C")).unwrap()
+    }
+
+    /// Ids matching `query` with default scoring and filters.
+    fn find(entries: &[DesktopEntryIndexed], query: &str) -> Vec<String> {
+        search_entries_with_usage_map_and_empty_mode(
+            entries,
+            query,
+            10,
+            &HashMap::new(),
+            EmptyQueryMode::AllApps,
+            &Scoring::default(),
+            &EntryFilter::default(),
+        )
+        .into_iter()
+        .map(|e| e.id)
+        .collect()
+    }
+
+    /// Ids matching `query` through `filter`, best first, at most `limit`.
+    fn find_with(
+        entries: &[DesktopEntryIndexed],
+        query: &str,
+        limit: usize,
+        filter: &EntryFilter,
+    ) -> Vec<String> {
+        search_entries_with_usage_map_and_empty_mode(
+            entries,
+            query,
+            limit,
+            &HashMap::new(),
+            EmptyQueryMode::Recency,
+            &Scoring::default(),
+            filter,
+        )
+        .into_iter()
+        .map(|e| e.id)
+        .collect()
+    }
+
+    #[test]
+    fn accented_names_match_plain_queries_and_back() {
+        let entries = [
+            entry(
+                "cafe",
+                "Name=Café Finder
+Exec=cafe",
+            ),
+            entry(
+                "uber",
+                "Name=Über Tool
+Exec=uber",
+            ),
+            entry(
+                "naive",
+                "Name=Naïve Bayes
+Exec=naive",
+            ),
+            entry(
+                "units",
+                "Name=Ångström Units
+Exec=units",
+            ),
+        ];
+        assert_eq!(find(&entries, "cafe"), ["cafe"]);
+        assert_eq!(find(&entries, "CAFÉ"), ["cafe"]);
+        assert_eq!(find(&entries, "uber"), ["uber"]);
+        assert_eq!(find(&entries, "naive bayes"), ["naive"]);
+        assert_eq!(find(&entries, "angstrom"), ["units"]);
+        assert_eq!(find(&entries, "ångström"), ["units"]);
+    }
+
+    #[test]
+    fn fuzzy_prefix_matches_common_misspellings() {
+        assert!(fuzzy_has_token_prefix("firefox web browser", "fierfox", 1));
+        assert!(fuzzy_has_token_prefix(
+            "google chrome web browser",
+            "chorme",
+            1
+        ));
+        assert!(fuzzy_has_token_prefix("vlc media player", "plyer", 1));
+        assert!(
+            normalize_query("vlc plyer")
+                .iter()
+                .all(|t| fuzzy_has_token_prefix("vlc media player", t, 1))
+        );
+    }
+
+    #[test]
+    fn fuzzy_prefix_keeps_exact_prefix_matches() {
+        assert!(fuzzy_has_token_prefix("firefox web browser", "fire", 0));
+        assert!(fuzzy_has_token_prefix("vlc media player", "vlc", 1));
+    }
+
+    #[test]
+    fn fuzzy_prefix_rejects_distant_or_short_tokens() {
+        assert!(!fuzzy_has_token_prefix("firefox web browser", "fierfox", 0));
+        assert!(!fuzzy_has_token_prefix(
+            "firefox web browser",
+            "thunderbird",
+            1
+        ));
+        // Three characters is below FUZZY_MIN_TOKEN_LEN: exact matching only.
+        assert!(!fuzzy_has_token_prefix("vlc media player", "vcl", 1));
+    }
+
+    #[test]
+    fn levenshtein_known_pairs() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("firefox", "firefox"), 0);
+        // An adjacent transposition is a single edit.
+        assert_eq!(levenshtein("fierfox", "firefox"), 1);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn fuzzy_distance_two_finds_two_typos() {
+        let entries = [
+            entry("firefox", "Name=Firefox\nExec=firefox %u"),
+            entry("thunderbird", "Name=Thunderbird\nExec=thunderbird"),
+        ];
+        let search = |max_distance| {
+            let scoring = Scoring {
+                fuzzy: Some(FuzzyOptions {
+                    max_distance,
+                    ..FuzzyOptions::default()
+                }),
+                ..Scoring::default()
+            };
+            search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                "fyrefax",
+                10,
+                &HashMap::new(),
+                EmptyQueryMode::AllApps,
+                &scoring,
+                &EntryFilter::default(),
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+        };
+
+        assert!(search(1).is_empty());
+        assert_eq!(search(2), ["firefox"]);
+        assert_eq!(fuzzy_token_distance("firefox", "fyrefax", 2), Some(2));
+    }
+
+    #[test]
+    fn pinned_low_score_entry_beats_high_score_entries() {
+        let entries = [
+            entry("edit", "Name=Edit\nExec=edit"),
+            entry("editor", "Name=Editor\nExec=editor"),
+            entry("notes", "Name=Notes\nComment=A note editor\nExec=notes"),
+        ];
+        let pins = ["notes.desktop".to_string()];
+        let search = |limit| {
+            search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                "edit",
+                limit,
+                &HashMap::new(),
+                EmptyQueryMode::AllApps,
+                &Scoring {
+                    pins: &pins,
+                    ..Scoring::default()
+                },
+                &EntryFilter::default(),
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search(1), ["notes"]);
+        assert_eq!(search(10), ["notes", "edit", "editor"]);
+    }
+
+    #[test]
+    fn entries_for_mime_skips_hidden_entries() {
+        let entries = [
+            entry(
+                "evince",
+                "Name=Evince\nExec=evince %U\nMimeType=application/pdf;",
+            ),
+            entry(
+                "okular",
+                "Name=Okular\nExec=okular %U\nMimeType=application/pdf;\nHidden=true",
+            ),
+            entry(
+                "pdf-helper",
+                "Name=PDF Helper\nExec=pdf-helper %f\nMimeType=application/pdf;\nNoDisplay=true",
+            ),
+        ];
+        let ids = |filter: &EntryFilter| -> Vec<String> {
+            entries_for_mime(&entries, "application/pdf", &HashMap::new(), filter)
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+
+        assert_eq!(ids(&EntryFilter::default()), ["evince"]);
+        assert_eq!(ids(&OPEN_HANDLER_FILTER), ["evince", "pdf-helper"]);
+        let all = EntryFilter {
+            show_hidden: true,
+            show_nodisplay: true,
+            ..EntryFilter::default()
+        };
+        assert_eq!(ids(&all), ["evince", "okular", "pdf-helper"]);
+    }
+
+    #[test]
+    fn entry_without_name_sorts_and_displays_by_generic_name() {
+        let entries = [
+            entry("zebra", "Name=Zebra\nExec=zebra"),
+            entry("aaa-browser", "GenericName=Web Browser\nExec=browser %u"),
+            entry("mail", "Name=Mail\nExec=mail"),
+        ];
+        let all = search_entries_with_usage_map_and_empty_mode(
+            &entries,
+            "",
+            10,
+            &HashMap::new(),
+            EmptyQueryMode::AllApps,
+            &Scoring::default(),
+            &EntryFilter::default(),
+        );
+        let names: Vec<&str> = all.iter().map(|e| e.display_name()).collect();
+        assert_eq!(names, ["Mail", "Web Browser", "Zebra"]);
+
+        let found = search_entries_with_usage_map_and_empty_mode(
+            &entries,
+            "web",
+            10,
+            &HashMap::new(),
+            EmptyQueryMode::AllApps,
+            &Scoring::default(),
+            &EntryFilter::default(),
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].display_name(), "Web Browser");
+    }
+
+    #[test]
+    fn cjk_runs_split_into_single_characters() {
+        assert_eq!(tokenize_cjk("qq音乐"), ["qq", "音", "乐"]);
+        assert_eq!(
+            tokenize_cjk("网易云音乐player"),
+            ["网", "易", "云", "音", "乐", "player"]
+        );
+        let mut query = normalize_query("QQ音乐");
+        query.sort();
+        assert_eq!(query, ["qq", "乐", "音"]);
+        assert_eq!(normalize_query("music"), ["music"]);
+    }
+
+    #[test]
+    fn chinese_names_match_by_character() {
+        let entries = [
+            entry("qqmusic", "Name=QQ音乐\nExec=qqmusic"),
+            entry("netease", "Name=网易云音乐\nExec=netease-cloud-music"),
+            entry("wps", "Name=WPS 文字\nExec=wps"),
+        ];
+        assert!(entries[0].norm.contains("qq 音 乐"));
+        assert_eq!(find(&entries, "qq"), ["qqmusic"]);
+        assert_eq!(find(&entries, "文字"), ["wps"]);
+        assert_eq!(find(&entries, "网易"), ["netease"]);
+        let mut both = find(&entries, "音乐");
+        both.sort();
+        assert_eq!(both, ["netease", "qqmusic"]);
+    }
+
+    #[test]
+    fn exec_flags_do_not_match_queries() {
+        let entries = [entry(
+            "vlc",
+            "Name=VLC media player\nExec=vlc --no-splash %U",
+        )];
+        assert!(find(&entries, "splash").is_empty());
+        assert!(find(&entries, "usr").is_empty());
+        assert_eq!(find(&entries, "vlc"), ["vlc"]);
+    }
+
+    #[test]
+    fn frecency_prefers_recent_rare_use_over_stale_heavy_use() {
+        let entries = [
+            entry("stale", "Name=Stale Editor\nExec=stale"),
+            entry("fresh", "Name=Fresh Editor\nExec=fresh"),
+            entry("unused", "Name=Unused Editor\nExec=unused"),
+        ];
+        let now = crate::frequency::unix_seconds_now();
+        let usage = HashMap::from([
+            (
+                "stale".to_string(),
+                Usage {
+                    freq: 50,
+                    last_used: now - 60 * 24 * 60 * 60,
+                },
+            ),
+            (
+                "fresh".to_string(),
+                Usage {
+                    freq: 2,
+                    last_used: now - 60,
+                },
+            ),
+        ]);
+        let empty = |mode| {
+            search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                "",
+                10,
+                &usage,
+                mode,
+                &Scoring::default(),
+                &EntryFilter::default(),
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(empty(EmptyQueryMode::Frequency), ["stale", "fresh"]);
+        assert_eq!(empty(EmptyQueryMode::Frecency), ["fresh", "stale"]);
+        assert_eq!(frecency(usage["stale"], now), 0);
+        assert_eq!(frecency(usage["fresh"], now), 20);
+    }
+
+    #[test]
+    fn score_breakdown_adds_up_to_the_score() {
+        let entries = [
+            entry(
+                "org.mozilla.firefox",
+                "Name=Firefox Web Browser\nExec=firefox %u",
+            ),
+            entry("zen-browser", "Name=Zen Browser\nExec=zen"),
+            entry("ssh-browser", "Name=Avahi SSH Server Browser\nExec=bssh"),
+        ];
+        let now = crate::frequency::unix_seconds_now();
+        let usage = Usage {
+            freq: 7,
+            last_used: now - 3 * 60 * 60,
+        };
+        let fuzzy = Scoring {
+            fuzzy: Some(FuzzyOptions::default()),
+            ..Scoring::default()
+        };
+
+        for scoring in [&Scoring::default(), &fuzzy] {
+            for query in ["browser", "fire web", "zen", "browsr", "ssh brow"] {
+                let tokens = normalize_query(query);
+                for e in &entries {
+                    let b = score_breakdown(e, &tokens, usage, now, scoring);
+                    assert_eq!(
+                        b.relevance,
+                        b.name_match
+                            + b.id_match
+                            + b.fuzzy_penalty
+                            + b.all_tokens_in_name_bonus
+                            + b.short_name_bonus,
+                        "{query:?} {}",
+                        e.out.id
+                    );
+                    assert_eq!(
+                        b.relevance + b.freq_bonus + b.recency_bonus + b.base,
+                        score_entry(e, &tokens, usage, now, scoring),
+                        "{query:?} {}",
+                        e.out.id
+                    );
+                }
+            }
+        }
+        let b = score_breakdown(&entries[1], &normalize_query("zen"), usage, now, &fuzzy);
+        assert!(b.freq_bonus > 0 && b.recency_bonus > 0 && b.name_match > 0);
+    }
+
+    #[test]
+    fn min_score_drops_weak_matches_only() {
+        let entries = [
+            entry("vlc", "Name=VLC media player\nExec=vlc %U"),
+            entry(
+                "codecs",
+                "Name=Codec Pack\nComment=Extra formats for vlc\nExec=codecs",
+            ),
+        ];
+        let search = |min_score| {
+            search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                "vlc",
+                10,
+                &HashMap::new(),
+                EmptyQueryMode::AllApps,
+                &Scoring {
+                    min_score,
+                    ..Scoring::default()
+                },
+                &EntryFilter::default(),
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+        };
+
+        let tokens = normalize_query("vlc");
+        let score = |e| score_entry(e, &tokens, Usage::default(), 0, &Scoring::default());
+        assert!(score(&entries[0]) >= 100);
+        assert!(score(&entries[1]) < 100);
+        assert_eq!(search(None), ["vlc", "codecs"]);
+        assert_eq!(search(Some(100)), ["vlc"]);
+        assert!(search(Some(i32::MAX)).is_empty());
+    }
+
+    #[test]
+    fn used_since_keeps_only_recently_launched_entries() {
+        let entries = [
+            entry("old-editor", "Name=Old Editor\nExec=old"),
+            entry("new-editor", "Name=New Editor\nExec=new"),
+            entry("never-editor", "Name=Never Editor\nExec=never"),
+        ];
+        let now = 10_000_000;
+        let day = 24 * 60 * 60;
+        let usage = HashMap::from([
+            (
+                "old-editor".to_string(),
+                Usage {
+                    freq: 9,
+                    last_used: now - 30 * day,
+                },
+            ),
+            (
+                "new-editor".to_string(),
+                Usage {
+                    freq: 1,
+                    last_used: now - day,
+                },
+            ),
+        ]);
+        let filter = EntryFilter {
+            used_since: Some(now - 7 * day),
+            ..EntryFilter::default()
+        };
+
+        assert!(filter.accepts_usage(&usage, "new-editor"));
+        assert!(!filter.accepts_usage(&usage, "old-editor"));
+        assert!(!filter.accepts_usage(&usage, "never-editor"));
+        assert!(EntryFilter::default().accepts_usage(&usage, "never-editor"));
+
+        for query in ["editor", ""] {
+            let ids: Vec<String> = search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                query,
+                10,
+                &usage,
+                EmptyQueryMode::AllApps,
+                &Scoring::default(),
+                &filter,
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+            assert_eq!(ids, ["new-editor"], "{query:?}");
+        }
+    }
+
+    #[test]
+    fn dedup_by_name_keeps_the_first_root_entry() {
+        let flatpak = tempfile::tempdir().unwrap();
+        let native = tempfile::tempdir().unwrap();
+        let in_root = |root: &Path, id: &str, name: &str| {
+            let path = root.join(format!("{id}.desktop"));
+            std::fs::write(
+                &path,
+                format!("[Desktop Entry]\nType=Application\nName={name}\nExec={id}\n"),
+            )
+            .unwrap();
+            parse_desktop_file_using_roots(&path, &[root.to_path_buf()], Some("C"))
+                .unwrap()
+                .out
+        };
+        let roots = [
+            flatpak.path().to_string_lossy().into_owned(),
+            native.path().to_string_lossy().into_owned(),
+        ];
+
+        // The lower-priority copy comes first, as it may after scoring.
+        let mut entries = vec![
+            in_root(native.path(), "gimp", "GIMP"),
+            in_root(native.path(), "inkscape", "Inkscape"),
+            in_root(flatpak.path(), "org.gimp.GIMP", "gimp"),
+        ];
+        dedup_by_name(&mut entries, &roots);
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["inkscape", "org.gimp.GIMP"]);
+
+        // Reversed root priority keeps the other copy.
+        let mut entries = vec![
+            in_root(native.path(), "gimp", "GIMP"),
+            in_root(flatpak.path(), "org.gimp.GIMP", "gimp"),
+        ];
+        dedup_by_name(&mut entries, &[roots[1].clone(), roots[0].clone()]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "gimp");
+    }
+
+    #[test]
+    fn sort_by_relevance_puts_matches_first_and_keeps_the_rest_in_order() {
+        let indexed = [
+            entry("archive", "Name=Archive Manager\nExec=archive"),
+            entry("calc", "Name=Calculator\nExec=calc"),
+            entry("zim", "Name=Zim\nComment=A terminal notebook\nExec=zim"),
+            entry("term", "Name=Terminal\nExec=term"),
+            entry("browser", "Name=Browser\nExec=browser"),
+        ];
+        let mut entries: Vec<DesktopEntryOut> = indexed.iter().map(|e| e.out.clone()).collect();
+        sort_by_relevance(
+            &mut entries,
+            &indexed,
+            "terminal",
+            &HashMap::new(),
+            &Scoring::default(),
+        );
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        // `zim` only matches in its comment, so it stays with the non-matches.
+        assert_eq!(ids, ["term", "archive", "calc", "zim", "browser"]);
+    }
+
+    #[test]
+    fn nodisplay_and_hidden_entries_are_left_out_by_default() {
+        let entries = [
+            entry("viewer", "Name=Image Viewer\nExec=viewer"),
+            entry("helper", "Name=Image Helper\nExec=helper\nNoDisplay=true"),
+            entry("removed", "Name=Image Remover\nExec=removed\nHidden=true"),
+        ];
+        let search = |filter: &EntryFilter| {
+            let mut ids: Vec<String> = search_entries_with_usage_map_and_empty_mode(
+                &entries,
+                "image",
+                10,
+                &HashMap::new(),
+                EmptyQueryMode::AllApps,
+                &Scoring::default(),
+                filter,
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(search(&EntryFilter::default()), ["viewer"]);
+        let nodisplay = EntryFilter {
+            show_nodisplay: true,
+            ..EntryFilter::default()
+        };
+        assert_eq!(search(&nodisplay), ["helper", "viewer"]);
+        let hidden = EntryFilter {
+            show_hidden: true,
+            ..EntryFilter::default()
+        };
+        assert_eq!(search(&hidden), ["removed", "viewer"]);
+    }
+
+    #[test]
+    fn exact_name_returns_only_that_entry() {
+        let entries = [
+            entry(
+                "gnome-terminal",
+                "Name=GNOME Terminal\nGenericName=Terminal\nKeywords=terminal;shell;\nExec=gnome-terminal",
+            ),
+            entry(
+                "xterm",
+                "Name=XTerm\nGenericName=Terminal\nComment=Terminal emulator\nExec=xterm",
+            ),
+            entry("terminal", "Name=terminal\nExec=term"),
+        ];
+        let filter = EntryFilter {
+            exact_name: Some("Terminal"),
+            ..EntryFilter::default()
+        };
+
+        // Even with one slot and better-scoring neighbours.
+        assert_eq!(find_with(&entries, "terminal", 1, &filter), ["terminal"]);
+        // No query needed, and the recency mode doesn't hide unused entries.
+        assert_eq!(find_with(&entries, "", 10, &filter), ["terminal"]);
+
+        // No near-miss fallback.
+        let typo = EntryFilter {
+            exact_name: Some("Termnal"),
+            ..EntryFilter::default()
+        };
+        assert!(find_with(&entries, "", 10, &typo).is_empty());
+    }
+
+    #[test]
+    fn type_filter_keeps_only_link_entries() {
+        let entries = [
+            entry("docs-app", "Name=Docs\nExec=docs"),
+            entry(
+                "docs-link",
+                "Type=Link\nName=Docs Website\nURL=https://docs.example.org",
+            ),
+            entry("docs-dir", "Type=Directory\nName=Docs Folder"),
+        ];
+        let links = EntryFilter {
+            type_filter: Some("Link"),
+            ..EntryFilter::default()
+        };
+        assert_eq!(find_with(&entries, "docs", 10, &links), ["docs-link"]);
+
+        let mut all = find(&entries, "docs");
+        all.sort();
+        assert_eq!(all, ["docs-app", "docs-dir", "docs-link"]);
+    }
+
+    #[test]
+    fn bang_words_become_exclude_tokens() {
+        assert_eq!(normalize_query("editor !vim"), ["editor"]);
+        assert_eq!(exclude_tokens("editor !vim"), ["vim"]);
+        assert_eq!(exclude_tokens("!Émacs !vim editor !vim"), ["emacs", "vim"]);
+        assert!(exclude_tokens("editor vim!").is_empty());
+        // A lone `!` excludes nothing.
+        assert!(exclude_tokens("editor !").is_empty());
+        assert_eq!(normalize_query("!"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn excluded_tokens_drop_matching_entries() {
+        let entries = [
+            entry("vim", "Name=Vim\nGenericName=Text Editor\nExec=vim"),
+            entry("gvim", "Name=GVim\nGenericName=Text Editor\nExec=gvim"),
+            entry("kate", "Name=Kate\nGenericName=Text Editor\nExec=kate"),
+        ];
+        let query = "editor !vim";
+        let exclude = exclude_tokens(query);
+        let filter = EntryFilter {
+            exclude: &exclude,
+            ..EntryFilter::default()
+        };
+        let ids: Vec<String> = search_entries_with_usage_map_and_empty_mode(
+            &entries,
+            query,
+            10,
+            &HashMap::new(),
+            EmptyQueryMode::AllApps,
+            &Scoring::default(),
+            &filter,
+        )
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+        // `gvim` matches only inside a word, so the `vim` prefix keeps it.
+        assert_eq!(ids, ["gvim", "kate"]);
+    }
+
+    #[test]
+    fn default_weights_are_self_consistent() {
+        let w = SearchWeights::default();
+        // Word-boundary hits beat mid-word hits, and the name beats the id.
+        assert!(w.name_boundary > w.name_substring);
+        assert!(w.id_boundary > w.id_substring);
+        assert!(w.name_boundary > w.id_boundary);
+        assert!(w.name_substring > w.id_substring);
+        // A hit stays positive even at the largest position penalty (80).
+        assert!(w.id_substring > 0 && w.name_substring >= 80);
+        // Tie-breakers stay smaller than the gaps they break ties within.
+        assert!(w.short_name < w.name_boundary - w.name_substring);
+        assert!(w.short_name < w.id_boundary - w.id_substring);
+        // Usage (launch count capped at 20, plus at most 10 for recency) must
+        // not outweigh a whole-name match.
+        assert!(20 * w.freq + recency_bonus(1, 1) < w.all_tokens_in_name);
+        assert!(w.freq > 0 && w.short_name > 0);
+
+        // An empty `[weights]` table keeps every default.
+        let parsed: SearchWeights = toml::from_str("").unwrap();
+        assert_eq!(parsed, w);
+    }
+}
@@ -0,0 +1,75 @@
+/// Seconds in a relative duration: `90s`, `15m`, `1h`, `1d`, `2w`.
+pub fn parse_relative(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let unit = s.chars().last()?;
+    let secs_per_unit: u64 = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let n: u64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    n.checked_mul(secs_per_unit)
+}
+
+/// A point in time given as unix seconds (`1714566600`) or as an age relative
+/// to `now` (`7d` => a week before `now`).
+pub fn parse_since(s: &str, now: u64) -> Result<u64, String> {
+    let s = s.trim();
+    if let Ok(ts) = s.parse::<u64>() {
+        return Ok(ts);
+    }
+    parse_relative(s)
+        .map(|age| now.saturating_sub(age))
+        .ok_or_else(|| format!("expected unix seconds or an age like 1h, 1d, 7d (got {s:?})"))
+}
+
+/// clap value parser for `--since`, relative to the current time.
+pub fn since_arg(s: &str) -> Result<u64, String> {
+    parse_since(s, crate::frequency::unix_seconds_now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_units() {
+        assert_eq!(parse_relative("90s"), Some(90));
+        assert_eq!(parse_relative("15m"), Some(15 * 60));
+        assert_eq!(parse_relative("1h"), Some(3600));
+        assert_eq!(parse_relative(" 1d "), Some(86_400));
+        assert_eq!(parse_relative("2w"), Some(2 * 604_800));
+        assert_eq!(parse_relative("0d"), Some(0));
+    }
+
+    #[test]
+    fn relative_rejects_bad_input() {
+        for bad in [
+            "", " ", "d", "5", "5y", "5D", "1.5h", "-1d", "5 d", "h1", "5é",
+        ] {
+            assert_eq!(parse_relative(bad), None, "{bad:?}");
+        }
+        // Overflow of the count or of the multiplication.
+        assert_eq!(parse_relative("99999999999999999999s"), None);
+        assert_eq!(parse_relative(&format!("{}w", u64::MAX / 2)), None);
+        assert_eq!(parse_relative(&format!("{}s", u64::MAX)), Some(u64::MAX));
+    }
+
+    #[test]
+    fn since_takes_timestamps_or_ages() {
+        let now = 1_000_000;
+        assert_eq!(parse_since("1714566600", now), Ok(1_714_566_600));
+        assert_eq!(parse_since("1h", now), Ok(now - 3600));
+        // Ages older than the epoch clamp to 0.
+        assert_eq!(parse_since("52w", 100), Ok(0));
+        assert!(parse_since("", now).is_err());
+        assert!(
+            parse_since("yesterday", now)
+                .unwrap_err()
+                .contains("yesterday")
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::models::DesktopEntryIndexed;
+use crate::search::normalize_query;
+use std::collections::HashMap;
+
+/// Byte-trigram postings over `DesktopEntryIndexed::norm`, used to narrow the
+/// daemon's candidate set before the exact token-prefix check.
+///
+/// Only a prefilter: every entry that can match is returned, plus some that
+/// don't (the trigrams of a token may appear in different words).
+pub struct TrigramIndex {
+    postings: HashMap<[u8; 3], Vec<usize>>,
+    entry_count: usize,
+}
+
+impl TrigramIndex {
+    pub fn build(entries: &[DesktopEntryIndexed]) -> Self {
+        let mut postings: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+
+        for (idx, e) in entries.iter().enumerate() {
+            for w in e.norm.as_bytes().windows(3) {
+                let list = postings.entry([w[0], w[1], w[2]]).or_default();
+                // Entries are visited in order, so a repeat can only be at the end.
+                if list.last() != Some(&idx) {
+                    list.push(idx);
+                }
+            }
+        }
+
+        Self {
+            postings,
+            entry_count: entries.len(),
+        }
+    }
+
+    /// Sorted entry indices that contain every trigram of every query token.
+    /// Tokens shorter than 3 bytes can't be checked, so a query made only of
+    /// those returns every entry (full scan).
+    pub fn candidates(&self, query: &str) -> Vec<usize> {
+        let mut lists: Vec<&[usize]> = Vec::new();
+
+        for token in normalize_query(query) {
+            for w in token.as_bytes().windows(3) {
+                match self.postings.get(&[w[0], w[1], w[2]]) {
+                    Some(list) => lists.push(list),
+                    None => return Vec::new(),
+                }
+            }
+        }
+
+        if lists.is_empty() {
+            return (0..self.entry_count).collect();
+        }
+
+        // Intersect starting from the rarest trigram.
+        lists.sort_by_key(|l| l.len());
+        let mut out = lists[0].to_vec();
+        for list in &lists[1..] {
+            out.retain(|idx| list.binary_search(idx).is_ok());
+            if out.is_empty() {
+                break;
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::parse_desktop_file_using_roots;
+    use crate::search::norm_has_token_prefix;
+    use std::time::Instant;
+
+    /// Parse one `<id>.desktop` per `(id, keys)` pair.
+    fn entries(apps: &[(String, String)]) -> Vec<DesktopEntryIndexed> {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = [dir.path().to_path_buf()];
+        apps.iter()
+            .map(|(id, keys)| {
+                let path = dir.path().join(format!("{id}.desktop"));
+                std::fs::write(
+                    &path,
+                    format!("[Desktop Entry]\nType=Application\n{keys}\n"),
+                )
+                .unwrap();
+                parse_desktop_file_using_roots(&path, &roots, Some("C")).unwrap()
+            })
+            .collect()
+    }
+
+    fn fixture() -> Vec<DesktopEntryIndexed> {
+        let apps = [
+            ("firefox", "Name=Firefox\nComment=Browse the web"),
+            ("code", "Name=Visual Studio Code\nComment=Code editing"),
+            ("vim", "Name=Vim\nComment=Text editor"),
+            ("qqmusic", "Name=QQ音乐\nComment=音乐播放器"),
+            ("cafe", "Name=Café Menu\nKeywords=coffee;"),
+            ("refire", "Name=Bonfire\nComment=Wildfire tracker"),
+        ];
+        entries(&apps.map(|(id, keys)| (id.to_string(), format!("{keys}\nExec={id}"))))
+    }
+
+    /// Indices whose `norm` has every token of `query` as a word prefix.
+    fn exact_matches(entries: &[DesktopEntryIndexed], query: &str) -> Vec<usize> {
+        let tokens = normalize_query(query);
+        (0..entries.len())
+            .filter(|&idx| {
+                tokens
+                    .iter()
+                    .all(|t| norm_has_token_prefix(&entries[idx].norm, t))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn candidates_are_a_superset_of_exact_matches() {
+        let entries = fixture();
+        let index = TrigramIndex::build(&entries);
+
+        for query in [
+            "fire",
+            "web",
+            "code edit",
+            "editor",
+            "音乐",
+            "qq音乐",
+            "cafe",
+            "CAFÉ",
+            "xyz",
+        ] {
+            let candidates = index.candidates(query);
+            for idx in exact_matches(&entries, query) {
+                assert!(
+                    candidates.contains(&idx),
+                    "{query:?}: {} missing",
+                    entries[idx].out.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn candidates_narrow_long_tokens() {
+        let entries = fixture();
+        let index = TrigramIndex::build(&entries);
+        let ids = |query| -> Vec<&str> {
+            index
+                .candidates(query)
+                .into_iter()
+                .map(|idx| entries[idx].out.id.as_str())
+                .collect()
+        };
+
+        // "fire" is also inside "bonfire"/"wildfire": kept, left to the prefix check.
+        assert_eq!(ids("fire"), ["firefox", "refire"]);
+        assert_eq!(ids("xyz"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn short_tokens_fall_back_to_a_full_scan() {
+        let entries = fixture();
+        let index = TrigramIndex::build(&entries);
+        let all: Vec<usize> = (0..entries.len()).collect();
+
+        assert_eq!(index.candidates("vi"), all);
+        assert_eq!(index.candidates("v c"), all);
+        assert_eq!(index.candidates(""), all);
+        // A short token next to a long one: only the long one narrows.
+        assert_eq!(index.candidates("vi text"), exact_matches(&entries, "text"));
+    }
+
+    #[test]
+    fn cjk_ideographs_are_one_trigram_each() {
+        let entries = fixture();
+        let index = TrigramIndex::build(&entries);
+        let qq = entries.iter().position(|e| e.out.id == "qqmusic").unwrap();
+
+        // Each ideograph is three UTF-8 bytes, so a one-character token still narrows.
+        assert_eq!(index.candidates("音"), [qq]);
+        assert_eq!(index.candidates("音乐"), [qq]);
+        assert_eq!(index.candidates("音 firefox"), Vec::<usize>::new());
+    }
+
+    /// `cargo test --release trigram -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_candidates_against_linear_scan() {
+        const WORDS: [&str; 10] = [
+            "text", "editor", "media", "player", "web", "browser", "office", "terminal", "image",
+            "viewer",
+        ];
+        let apps: Vec<(String, String)> = (0..500)
+            .map(|i| {
+                let name = format!(
+                    "{} {} {i}",
+                    WORDS[i % WORDS.len()],
+                    WORDS[i / WORDS.len() % WORDS.len()]
+                );
+                (
+                    format!("app{i}"),
+                    format!("Name={name}\nComment=Synthetic {name}\nExec=app{i}"),
+                )
+            })
+            .collect();
+        let entries = entries(&apps);
+        let queries = [
+            "text edit",
+            "brow",
+            "media player",
+            "term",
+            "viewer 42",
+            "nothing",
+        ];
+        const ROUNDS: u32 = 200;
+
+        let t = Instant::now();
+        let index = TrigramIndex::build(&entries);
+        let build = t.elapsed();
+
+        let t = Instant::now();
+        let mut via_index = 0;
+        for _ in 0..ROUNDS {
+            for q in queries {
+                let tokens = normalize_query(q);
+                via_index += index
+                    .candidates(q)
+                    .into_iter()
+                    .filter(|&idx| {
+                        tokens
+                            .iter()
+                            .all(|t| norm_has_token_prefix(&entries[idx].norm, t))
+                    })
+                    .count();
+            }
+        }
+        let indexed = t.elapsed();
+
+        let t = Instant::now();
+        let mut linear = 0;
+        for _ in 0..ROUNDS {
+            for q in queries {
+                linear += exact_matches(&entries, q).len();
+            }
+        }
+        let scanned = t.elapsed();
+
+        assert_eq!(via_index, linear);
+        let per_query = |d: std::time::Duration| d / (ROUNDS * queries.len() as u32);
+        println!(
+            "500 entries: build {build:?}; per query: trigram {:?}, linear {:?}",
+            per_query(indexed),
+            per_query(scanned)
+        );
+    }
+}
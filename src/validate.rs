@@ -0,0 +1,432 @@
+use crate::desktop::{parse_bool, split_key_locale, split_list};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Keys the spec defines as booleans (only `true`/`false` are valid).
+const BOOLEAN_KEYS: &[&str] = &[
+    "Terminal",
+    "StartupNotify",
+    "NoDisplay",
+    "Hidden",
+    "DBusActivatable",
+    "PrefersNonDefaultGPU",
+    "SingleMainWindow",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Warning => "WARNING",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub level: Level,
+    /// 1-based line number, when the issue points at a specific line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Group the line being read belongs to.
+enum Current {
+    /// Before any group header.
+    Start,
+    Entry,
+    Action(String),
+    /// Vendor or unknown group (not checked).
+    Other,
+}
+
+#[derive(Default)]
+struct Group {
+    header_line: usize,
+    /// Base (non-localized) keys with their value and line number.
+    keys: BTreeMap<String, (String, usize)>,
+}
+
+/// Check `.desktop` file contents against the parts of the Desktop Entry spec
+/// the indexer cares about. Issues come back in file order.
+pub fn validate_desktop(data: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut entry: Option<Group> = None;
+    let mut actions: BTreeMap<String, Group> = BTreeMap::new();
+    let mut current = Current::Start;
+
+    for (idx, raw_line) in data.lines().enumerate() {
+        let lineno = idx + 1;
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            if line == "[Desktop Entry]" {
+                if entry.is_some() {
+                    push(
+                        &mut issues,
+                        Level::Error,
+                        lineno,
+                        "duplicate [Desktop Entry] group",
+                    );
+                }
+                entry.get_or_insert(Group {
+                    header_line: lineno,
+                    ..Group::default()
+                });
+                current = Current::Entry;
+            } else if let Some(action_id) = line
+                .strip_prefix("[Desktop Action ")
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                let action_id = action_id.trim().to_string();
+                if actions.contains_key(&action_id) {
+                    push(
+                        &mut issues,
+                        Level::Error,
+                        lineno,
+                        &format!("duplicate [Desktop Action {action_id}] group"),
+                    );
+                }
+                actions.entry(action_id.clone()).or_insert(Group {
+                    header_line: lineno,
+                    ..Group::default()
+                });
+                current = Current::Action(action_id);
+            } else {
+                // Vendor groups (X-...) are allowed and not checked.
+                current = Current::Other;
+            }
+            continue;
+        }
+
+        let Some((key_raw, value_raw)) = line.split_once('=') else {
+            push(
+                &mut issues,
+                Level::Error,
+                lineno,
+                "line is not a group header, comment or key=value pair",
+            );
+            continue;
+        };
+
+        let key_raw = key_raw.trim();
+        let value = value_raw.trim();
+        if key_raw.is_empty() {
+            push(&mut issues, Level::Error, lineno, "empty key");
+            continue;
+        }
+
+        let group = match &current {
+            Current::Start => {
+                push(
+                    &mut issues,
+                    Level::Error,
+                    lineno,
+                    &format!("key {key_raw} appears before the [Desktop Entry] group"),
+                );
+                continue;
+            }
+            Current::Other => continue,
+            Current::Entry => entry.as_mut(),
+            Current::Action(action_id) => actions.get_mut(action_id),
+        };
+        let Some(group) = group else {
+            continue;
+        };
+
+        let (key, locale) = split_key_locale(key_raw);
+        if locale.is_some() {
+            continue;
+        }
+
+        if BOOLEAN_KEYS.contains(&key) && value != "true" && value != "false" {
+            if parse_bool(value).is_some() {
+                push(
+                    &mut issues,
+                    Level::Warning,
+                    lineno,
+                    &format!("{key}={value} is not a spec boolean (use true or false)"),
+                );
+            } else {
+                push(
+                    &mut issues,
+                    Level::Error,
+                    lineno,
+                    &format!("{key}={value} is not a valid boolean"),
+                );
+            }
+        }
+
+        if group.keys.contains_key(key) {
+            push(
+                &mut issues,
+                Level::Warning,
+                lineno,
+                &format!("duplicate key {key} (last value wins)"),
+            );
+        }
+        group
+            .keys
+            .insert(key.to_string(), (value.to_string(), lineno));
+    }
+
+    let Some(entry) = entry else {
+        issues.push(Issue {
+            level: Level::Error,
+            line: None,
+            message: "missing [Desktop Entry] group".to_string(),
+        });
+        return issues;
+    };
+
+    check_entry(&entry, &mut issues);
+    check_actions(&entry, &actions, &mut issues);
+
+    issues.sort_by_key(|i| i.line.unwrap_or(usize::MAX));
+    issues
+}
+
+fn check_entry(entry: &Group, issues: &mut Vec<Issue>) {
+    let value = |key: &str| entry.keys.get(key).map(|(v, _)| v.as_str());
+    let require = |issues: &mut Vec<Issue>, type_: &str, key: &str| {
+        if value(key).is_none() {
+            push(
+                issues,
+                Level::Error,
+                entry.header_line,
+                &format!("Type={type_} requires a {key} key"),
+            );
+        }
+    };
+
+    match value("Type") {
+        None => push(issues, Level::Error, entry.header_line, "missing Type key"),
+        Some("Application") => {
+            require(issues, "Application", "Name");
+            // D-Bus activatable apps may omit Exec.
+            if value("DBusActivatable") != Some("true") {
+                require(issues, "Application", "Exec");
+            }
+        }
+        Some("Link") => {
+            require(issues, "Link", "Name");
+            require(issues, "Link", "URL");
+        }
+        Some("Directory") => require(issues, "Directory", "Name"),
+        Some(other) => {
+            let line = entry.keys.get("Type").map(|(_, l)| *l).unwrap_or(0);
+            push(
+                issues,
+                Level::Warning,
+                line,
+                &format!("unknown Type={other} (expected Application, Link or Directory)"),
+            );
+        }
+    }
+
+    check_empty_values(entry, issues);
+}
+
+fn check_actions(entry: &Group, actions: &BTreeMap<String, Group>, issues: &mut Vec<Issue>) {
+    let (listed, actions_line) = match entry.keys.get("Actions") {
+        Some((v, line)) => (split_list(v), *line),
+        None => (Vec::new(), entry.header_line),
+    };
+
+    for aid in &listed {
+        if !actions.contains_key(aid) {
+            push(
+                issues,
+                Level::Error,
+                actions_line,
+                &format!("action {aid} listed in Actions= has no [Desktop Action {aid}] group"),
+            );
+        }
+    }
+
+    for (aid, group) in actions {
+        if !listed.contains(aid) {
+            push(
+                issues,
+                Level::Warning,
+                group.header_line,
+                &format!("[Desktop Action {aid}] is not listed in Actions= and will be ignored"),
+            );
+        }
+        if !group.keys.contains_key("Name") {
+            push(
+                issues,
+                Level::Error,
+                group.header_line,
+                &format!("[Desktop Action {aid}] requires a Name key"),
+            );
+        }
+        check_empty_values(group, issues);
+    }
+}
+
+fn check_empty_values(group: &Group, issues: &mut Vec<Issue>) {
+    if let Some((v, line)) = group.keys.get("Exec")
+        && v.is_empty()
+    {
+        push(issues, Level::Error, *line, "Exec= is empty");
+    }
+    if let Some((v, line)) = group.keys.get("Icon")
+        && v.is_empty()
+    {
+        push(issues, Level::Warning, *line, "Icon= is empty");
+    }
+}
+
+fn push(issues: &mut Vec<Issue>, level: Level, line: usize, message: &str) {
+    issues.push(Issue {
+        level,
+        line: Some(line),
+        message: message.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(level, line, message)` for each issue.
+    fn check(data: &str) -> Vec<(Level, Option<usize>, String)> {
+        validate_desktop(data)
+            .into_iter()
+            .map(|i| (i.level, i.line, i.message))
+            .collect()
+    }
+
+    #[test]
+    fn valid_entry_has_no_issues() {
+        let data = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nTerminal=false\n\
+                    Actions=new;\n\n[Desktop Action new]\nName=New\nExec=app --new\n";
+        assert!(check(data).is_empty());
+    }
+
+    #[test]
+    fn bad_boolean_is_an_error_and_a_lenient_one_a_warning() {
+        let issues = check(
+            "[Desktop Entry]\nType=Application\nName=App\nExec=app\nTerminal=maybe\nNoDisplay=1\n",
+        );
+        assert_eq!(
+            issues,
+            [
+                (
+                    Level::Error,
+                    Some(5),
+                    "Terminal=maybe is not a valid boolean".to_string()
+                ),
+                (
+                    Level::Warning,
+                    Some(6),
+                    "NoDisplay=1 is not a spec boolean (use true or false)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn application_without_name_or_exec() {
+        let issues = check("[Desktop Entry]\nType=Application\n");
+        assert_eq!(
+            issues,
+            [
+                (
+                    Level::Error,
+                    Some(1),
+                    "Type=Application requires a Name key".to_string()
+                ),
+                (
+                    Level::Error,
+                    Some(1),
+                    "Type=Application requires a Exec key".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn action_groups_must_match_the_actions_key() {
+        let data = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nActions=new;gone;\n\n\
+                    [Desktop Action new]\nName=New\n\n[Desktop Action extra]\nName=Extra\n";
+        let issues = check(data);
+        assert_eq!(
+            issues,
+            [
+                (
+                    Level::Error,
+                    Some(5),
+                    "action gone listed in Actions= has no [Desktop Action gone] group".to_string()
+                ),
+                (
+                    Level::Warning,
+                    Some(10),
+                    "[Desktop Action extra] is not listed in Actions= and will be ignored"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_before_the_first_group() {
+        let issues = check("Name=Early\n[Desktop Entry]\nType=Application\nName=App\nExec=app\n");
+        assert_eq!(
+            issues,
+            [(
+                Level::Error,
+                Some(1),
+                "key Name appears before the [Desktop Entry] group".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn duplicate_groups_and_keys() {
+        let data = "[Desktop Entry]\nType=Application\nName=App\nExec=app\n\
+                    [Desktop Entry]\nName=Again\n";
+        let issues = check(data);
+        assert_eq!(
+            issues,
+            [
+                (
+                    Level::Error,
+                    Some(5),
+                    "duplicate [Desktop Entry] group".to_string()
+                ),
+                (
+                    Level::Warning,
+                    Some(6),
+                    "duplicate key Name (last value wins)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_entry_group() {
+        let issues = check("[Desktop Action new]\nName=New\n");
+        assert_eq!(
+            issues,
+            [(
+                Level::Error,
+                None,
+                "missing [Desktop Entry] group".to_string()
+            )]
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use std::{env, path::PathBuf};
 
+/// Application roots, highest priority first: when a desktop-id exists in
+/// several roots, the first one wins (XDG_DATA_HOME over XDG_DATA_DIRS, and
+/// XDG_DATA_DIRS in listed order), then discovered package roots, then `-p`.
 pub fn build_scan_roots(extra: &[PathBuf]) -> Vec<PathBuf> {
     let mut roots = Vec::<PathBuf>::new();
 
@@ -24,6 +27,10 @@ pub fn build_scan_roots(extra: &[PathBuf]) -> Vec<PathBuf> {
         roots.push(PathBuf::from(part).join("applications"));
     }
 
+    // Package-manager exports (Flatpak, ...) missing from XDG_DATA_DIRS; already
+    // listed ones keep their position through the dedup below.
+    roots.extend(crate::scan_sources::discovered_roots());
+
     // user -p paths (scan as-is + /applications variant)
     for p in extra {
         roots.push(p.clone());
@@ -68,7 +75,26 @@ pub fn data_dir() -> PathBuf {
     base.join("desktop-indexer")
 }
 
+pub fn config_file() -> PathBuf {
+    // XDG_CONFIG_HOME (default ~/.config)
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = env::var_os("HOME").unwrap_or_default();
+            PathBuf::from(home).join(".config")
+        });
+
+    base.join("desktop-indexer").join("config.toml")
+}
+
 pub fn socket_path() -> PathBuf {
+    // Explicit override, e.g. to run several daemons side by side.
+    if let Some(path) = env::var_os("DESKTOP_INDEXER_SOCKET")
+        && !path.is_empty()
+    {
+        return PathBuf::from(path);
+    }
+
     // Prefer XDG_RUNTIME_DIR for per-session sockets.
     if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
         return PathBuf::from(dir).join("desktop-indexer.sock");
@@ -78,3 +104,8 @@ pub fn socket_path() -> PathBuf {
     let user = env::var("USER").unwrap_or_else(|_| "user".to_string());
     PathBuf::from("/tmp").join(format!("desktop-indexer-{user}.sock"))
 }
+
+pub fn pid_path() -> PathBuf {
+    // Next to the socket: $XDG_RUNTIME_DIR/desktop-indexer.pid or /tmp/desktop-indexer-$USER.pid.
+    socket_path().with_extension("pid")
+}
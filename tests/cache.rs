@@ -0,0 +1,74 @@
+mod common;
+
+use common::{command, home, indexer, make_read_only, make_writable, stderr, stdout, write_app};
+
+#[test]
+fn a_read_only_cache_dir_still_returns_entries() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let cache_home = home.path().join("cache");
+    make_read_only(&cache_home, "desktop-indexer");
+
+    let search = command(home.path())
+        .args(["--no-daemon", "search", "fire"])
+        .env("DESKTOP_INDEXER_TIMING", "1")
+        .output()
+        .unwrap();
+    make_writable(&cache_home);
+
+    assert!(search.status.success(), "{search:?}");
+    assert!(stdout(&search).starts_with("firefox\tFirefox"));
+    let err = stderr(&search);
+    assert!(err.contains("failed to write cache"), "{err}");
+    assert!(err.contains("save_cache=err("), "{err}");
+    assert!(!err.contains("panicked"), "{err}");
+}
+
+/// `*.bin` files in the cache dir.
+fn cache_files(home: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let Ok(rd) = std::fs::read_dir(home.join("cache/desktop-indexer")) else {
+        return Vec::new();
+    };
+    rd.map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "bin"))
+        .collect()
+}
+
+#[test]
+fn cache_clear_deletes_the_cache_files() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    indexer(home.path(), &["--no-daemon", "search", "fire"]);
+    let files = cache_files(home.path());
+    assert_eq!(files.len(), 1);
+    let size = std::fs::metadata(&files[0]).unwrap().len();
+    // Other files in the cache dir are left alone.
+    let note = home.path().join("cache/desktop-indexer/notes.txt");
+    std::fs::write(&note, "keep").unwrap();
+
+    let clear = indexer(home.path(), &["cache", "clear", "--json"]);
+    assert!(clear.status.success(), "{clear:?}");
+    let out: serde_json::Value = serde_json::from_str(&stdout(&clear)).unwrap();
+    assert_eq!(out["cleared_files"], 1);
+    assert_eq!(out["bytes_freed"], size);
+    assert!(cache_files(home.path()).is_empty());
+    assert!(note.exists());
+
+    let again = indexer(home.path(), &["cache", "clear"]);
+    assert_eq!(stdout(&again), "cleared 0 cache file(s), freed 0 bytes\n");
+}
+
+#[test]
+fn cache_rebuild_writes_a_fresh_cache() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    write_app(home.path(), "vlc", "Name=VLC\nExec=vlc %U");
+    indexer(home.path(), &["--no-daemon", "search", "fire"]);
+
+    let rebuild = indexer(home.path(), &["--no-daemon", "cache", "rebuild", "--json"]);
+    assert!(rebuild.status.success(), "{rebuild:?}");
+    let out: serde_json::Value = serde_json::from_str(&stdout(&rebuild)).unwrap();
+    assert_eq!(out["cleared_files"], 1);
+    assert_eq!(out["indexed"], 2);
+    assert_eq!(cache_files(home.path()).len(), 1);
+}
@@ -0,0 +1,115 @@
+// Each integration test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// The binary with every XDG directory under `home`, so the test neither
+/// touches nor sees the user's daemon, cache or apps.
+pub fn command(home: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_desktop-indexer"));
+    cmd.env_clear()
+        .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_DATA_DIRS", home.join("no-system-apps"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_RUNTIME_DIR", home.join("run"));
+    cmd
+}
+
+pub fn indexer(home: &Path, args: &[&str]) -> Output {
+    command(home).args(args).output().unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// A home with the directories `indexer` points at.
+pub fn home() -> tempfile::TempDir {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(home.path().join("data/applications")).unwrap();
+    std::fs::create_dir_all(home.path().join("run")).unwrap();
+    home
+}
+
+/// Write `<id>.desktop` with `[Desktop Entry]` `keys` to the user's apps.
+pub fn write_app(home: &Path, id: &str, keys: &str) {
+    std::fs::write(
+        home.join(format!("data/applications/{id}.desktop")),
+        format!("[Desktop Entry]\nType=Application\n{keys}\n"),
+    )
+    .unwrap();
+}
+
+/// Stops the daemon even when an assertion fails.
+pub struct StopDaemon<'a>(pub &'a Path);
+
+impl Drop for StopDaemon<'_> {
+    fn drop(&mut self) {
+        indexer(self.0, &["daemon", "stop"]);
+    }
+}
+
+/// Start the daemon for `home`; it is stopped when the guard drops.
+pub fn start_daemon(home: &Path) -> StopDaemon<'_> {
+    let start = indexer(home, &["daemon", "start"]);
+    assert!(start.status.success(), "{start:?}");
+    StopDaemon(home)
+}
+
+/// Make `dir` read-only (mode 0o555). Root ignores the mode, so when `dir`
+/// is still writable a plain file also takes the name `blocked` inside it.
+pub fn make_read_only(dir: &Path, blocked: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    if std::fs::write(dir.join("probe"), b"").is_ok() {
+        std::fs::write(dir.join(blocked), b"").unwrap();
+    }
+}
+
+/// Undo `make_read_only` so the tempdir can be removed.
+pub fn make_writable(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// The daemon socket of `home`.
+pub fn socket(home: &Path) -> std::path::PathBuf {
+    home.join("run/desktop-indexer.sock")
+}
+
+/// The scan roots the binary uses for `home`, as IPC requests carry them.
+pub fn roots(home: &Path) -> serde_json::Value {
+    serde_json::json!([
+        home.join("data/applications"),
+        home.join("no-system-apps/applications"),
+    ])
+}
+
+/// Send one JSON request line to the daemon of `home` and read its answer.
+pub fn request(home: &Path, req: &serde_json::Value) -> serde_json::Value {
+    use std::io::{BufRead, BufReader, Write};
+
+    let mut stream = std::os::unix::net::UnixStream::connect(socket(home)).unwrap();
+    writeln!(stream, "{req}").unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+    serde_json::from_str(&line).unwrap()
+}
+
+/// Write `config.toml` for `home`.
+pub fn write_config(home: &Path, toml: &str) {
+    let dir = home.join("config/desktop-indexer");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), toml).unwrap();
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use common::{home, indexer, stderr, stdout};
+
+#[test]
+fn completions_are_printed_for_every_shell() {
+    let home = home();
+    for shell in ["bash", "zsh", "fish", "elvish"] {
+        let output = indexer(home.path(), &["completions", "--shell", shell]);
+        assert!(output.status.success(), "{shell}: {}", stderr(&output));
+        let script = stdout(&output);
+        for word in ["search", "list", "launch", "limit"] {
+            assert!(script.contains(word), "{shell} is missing {word}");
+        }
+    }
+    assert!(
+        !indexer(home.path(), &["completions", "--shell", "tcsh"])
+            .status
+            .success()
+    );
+}
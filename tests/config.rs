@@ -0,0 +1,102 @@
+mod common;
+
+use common::{
+    home, indexer, request, roots, start_daemon, stderr, stdout, write_app, write_config,
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// Eight apps that all match "tool".
+fn home_with_tools() -> tempfile::TempDir {
+    let home = home();
+    for i in 0..8 {
+        write_app(
+            home.path(),
+            &format!("tool{i}"),
+            &format!("Name=Tool {i}\nExec=tool{i}"),
+        );
+    }
+    home
+}
+
+#[test]
+fn default_limit_caps_local_searches() {
+    let home = home_with_tools();
+    write_config(home.path(), "default_limit = 5\n");
+
+    let count = |args: &[&str]| {
+        let output = indexer(home.path(), args);
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output).lines().count()
+    };
+    assert_eq!(count(&["--no-daemon", "search", "tool"]), 5);
+    assert_eq!(count(&["--no-daemon", "search", "tool", "--limit", "7"]), 7);
+    assert_eq!(
+        count(&["--no-daemon", "search", "", "--empty-mode", "all-apps"]),
+        5
+    );
+}
+
+#[test]
+fn default_limit_applies_to_daemon_requests_without_a_limit() {
+    let home = home_with_tools();
+    write_config(home.path(), "default_limit = 5\n");
+    let _daemon = start_daemon(home.path());
+
+    let search = |limit: Option<usize>| {
+        let mut req = json!({"cmd": "search", "roots": roots(home.path()), "query": "tool"});
+        if let Some(limit) = limit {
+            req["limit"] = json!(limit);
+        }
+        let resp = request(home.path(), &req);
+        assert_eq!(resp["type"], "entries", "{resp}");
+        resp["entries"].as_array().unwrap().len()
+    };
+    assert_eq!(search(None), 5);
+    assert_eq!(search(Some(7)), 7);
+
+    let output = indexer(home.path(), &["--trace", "search", "tool"]);
+    assert!(
+        stderr(&output).contains("mode=daemon"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(stdout(&output).lines().count(), 5);
+}
+
+#[test]
+fn warmup_at_start_builds_the_index_before_any_search() {
+    let home = home_with_tools();
+    write_config(home.path(), "warmup_at_start = true\n");
+    let _stop = start_daemon(home.path());
+
+    let indexes = || {
+        let out = indexer(home.path(), &["daemon", "status", "--verbose", "--json"]);
+        let status: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+        status["indexes"].as_array().cloned().unwrap_or_default()
+    };
+    let mut waited = Duration::ZERO;
+    let warm = loop {
+        let indexes = indexes();
+        if !indexes.is_empty() {
+            break indexes;
+        }
+        assert!(waited < Duration::from_secs(5), "no index after startup");
+        std::thread::sleep(Duration::from_millis(20));
+        waited += Duration::from_millis(20);
+    };
+    assert_eq!(warm.len(), 1);
+    assert_eq!(warm[0]["entry_count"], 8);
+
+    // The first search uses that index instead of building its own.
+    let search = indexer(home.path(), &["--trace", "search", "tool"]);
+    assert!(
+        stderr(&search).contains("mode=daemon"),
+        "{}",
+        stderr(&search)
+    );
+    assert_eq!(stdout(&search).lines().count(), 8);
+    let after = indexes();
+    assert_eq!(after.len(), 1, "{after:?}");
+    assert_eq!(after[0]["last_built_sec"], warm[0]["last_built_sec"]);
+}
@@ -0,0 +1,413 @@
+mod common;
+
+use common::{
+    command, home, indexer, request, roots, socket, start_daemon, stderr, stdout, write_app,
+    write_config,
+};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn concurrent_searches_are_answered_or_turned_away() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let _stop = start_daemon(home.path());
+    let search = json!({"cmd": "search", "roots": roots(home.path()), "query": "fire", "limit": 5});
+
+    let answers: Vec<serde_json::Value> = std::thread::scope(|s| {
+        let threads: Vec<_> = (0..64)
+            .map(|_| s.spawn(|| request(home.path(), &search)))
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+
+    for answer in &answers {
+        match answer["type"].as_str() {
+            Some("entries") => assert_eq!(answer["entries"][0]["id"], "firefox"),
+            Some("error") => assert_eq!(answer["message"], "busy"),
+            _ => panic!("unexpected answer {answer}"),
+        }
+    }
+    assert!(answers.iter().any(|a| a["type"] == "entries"));
+    // Still up, and serving again once the closed connections are dropped
+    // from its queue.
+    let recovered = (0..50).any(|_| {
+        std::thread::sleep(Duration::from_millis(20));
+        request(home.path(), &search)["type"] == "entries"
+    });
+    assert!(recovered);
+}
+
+#[test]
+fn connections_past_max_pending_get_busy() {
+    let home = home();
+    write_config(home.path(), "max_pending = 1\n");
+    let _stop = start_daemon(home.path());
+
+    let status = |stream: &mut BufReader<UnixStream>| -> serde_json::Value {
+        writeln!(stream.get_mut(), "{}", json!({"cmd": "status"})).unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+    let connect = || BufReader::new(UnixStream::connect(socket(home.path())).unwrap());
+
+    // A served keep-alive connection holds the only queue slot. Retried while
+    // the connection `daemon start` used for its warmup still holds it.
+    let _held = (0..50)
+        .find_map(|_| {
+            let mut stream = connect();
+            if status(&mut stream)["type"] == "status" {
+                return Some(stream);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+            None
+        })
+        .expect("the daemon never served a connection");
+
+    let answer = status(&mut connect());
+    assert_eq!(answer["type"], "error", "{answer}");
+    assert_eq!(answer["message"], "busy");
+}
+
+#[test]
+fn reload_picks_up_changed_and_new_apps() {
+    let home = home();
+    write_app(home.path(), "editor", "Name=Old Editor\nExec=editor");
+    let _stop = start_daemon(home.path());
+    let search = |query: &str| {
+        let output = indexer(home.path(), &["--trace", "search", query]);
+        assert!(
+            stderr(&output).contains("mode=daemon"),
+            "{}",
+            stderr(&output)
+        );
+        stdout(&output)
+    };
+    assert_eq!(search("editor"), "editor\tOld Editor\n");
+
+    write_app(home.path(), "editor", "Name=New Editor\nExec=editor");
+    write_app(home.path(), "viewer", "Name=Viewer\nExec=viewer");
+    let reload = indexer(home.path(), &["daemon", "reload"]);
+    assert!(reload.status.success(), "{}", stderr(&reload));
+
+    assert_eq!(search("editor"), "editor\tNew Editor\n");
+    assert_eq!(search("viewer"), "viewer\tViewer\n");
+
+    // Over IPC directly: an ok, and the index is still served afterwards.
+    let resp = request(
+        home.path(),
+        &json!({"cmd": "reload", "roots": roots(home.path())}),
+    );
+    assert_eq!(resp["type"], "ok", "{resp}");
+    assert_eq!(search("viewer"), "viewer\tViewer\n");
+}
+
+#[test]
+fn ping_is_answered_with_pong() {
+    let home = home();
+    let _stop = start_daemon(home.path());
+
+    let resp = request(home.path(), &json!({"cmd": "ping", "protocol_version": 1}));
+    assert_eq!(resp["type"], "pong", "{resp}");
+    assert_eq!(resp["protocol_version"], 1);
+    // Clients that predate versioning send no version.
+    assert_eq!(
+        request(home.path(), &json!({"cmd": "ping"}))["type"],
+        "pong"
+    );
+}
+
+#[test]
+fn socket_path_can_be_overridden() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let custom = home.path().join("custom/indexer.sock");
+    std::fs::create_dir_all(custom.parent().unwrap()).unwrap();
+    let with_socket = |args: &[&str]| {
+        command(home.path())
+            .env("DESKTOP_INDEXER_SOCKET", &custom)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let start = with_socket(&["daemon", "start"]);
+    assert!(start.status.success(), "{}", stderr(&start));
+    let _stop = StopDaemonAt {
+        home: home.path(),
+        socket: &custom,
+    };
+    assert!(custom.exists());
+    assert!(!socket(home.path()).exists());
+
+    let search = with_socket(&["--trace", "search", "fire"]);
+    assert!(
+        stderr(&search).contains("mode=daemon"),
+        "{}",
+        stderr(&search)
+    );
+    assert!(stdout(&search).starts_with("firefox\t"));
+    // Without the variable, clients look in XDG_RUNTIME_DIR and find nothing.
+    let local = indexer(home.path(), &["--trace", "search", "fire"]);
+    assert!(
+        !stderr(&local).contains("mode=daemon"),
+        "{}",
+        stderr(&local)
+    );
+
+    let stop = with_socket(&["daemon", "stop"]);
+    assert!(stop.status.success(), "{}", stderr(&stop));
+    let gone = (0..50).any(|_| {
+        std::thread::sleep(Duration::from_millis(20));
+        !custom.exists()
+    });
+    assert!(gone);
+}
+
+/// Stops a daemon started with a custom socket, even when an assertion fails.
+struct StopDaemonAt<'a> {
+    home: &'a Path,
+    socket: &'a Path,
+}
+
+impl Drop for StopDaemonAt<'_> {
+    fn drop(&mut self) {
+        let _ = command(self.home)
+            .env("DESKTOP_INDEXER_SOCKET", self.socket)
+            .args(["daemon", "stop"])
+            .output();
+    }
+}
+
+#[test]
+fn batch_answers_each_request_in_order() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let _stop = start_daemon(home.path());
+    let roots = roots(home.path());
+
+    let resp = request(
+        home.path(),
+        &json!({"cmd": "batch", "requests": [
+            {"cmd": "warmup", "roots": roots},
+            {"cmd": "search", "roots": roots, "query": "fire", "limit": 5},
+            {"cmd": "ping"},
+        ]}),
+    );
+    assert_eq!(resp["type"], "batch", "{resp}");
+    let responses = resp["responses"].as_array().unwrap();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["type"], "ok");
+    assert_eq!(responses[1]["type"], "entries");
+    assert_eq!(responses[1]["entries"][0]["id"], "firefox");
+    assert_eq!(responses[2]["type"], "pong");
+
+    // The same through `daemon batch`, one request per stdin line.
+    let mut child = command(home.path())
+        .args(["daemon", "batch"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    writeln!(
+        child.stdin.take().unwrap(),
+        "{}\n{}",
+        json!({"cmd": "warmup", "roots": roots}),
+        json!({"cmd": "search", "roots": roots, "query": "fire"}),
+    )
+    .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let lines: Vec<serde_json::Value> = stdout(&output)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], "ok");
+    assert_eq!(lines[1]["entries"][0]["id"], "firefox");
+}
+
+#[test]
+fn shutdown_still_answers_a_queued_warmup() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let _stop = start_daemon(home.path());
+
+    // Connected before the shutdown, but its request only arrives after.
+    let queued = UnixStream::connect(socket(home.path())).unwrap();
+    let resp = request(home.path(), &json!({"cmd": "shutdown"}));
+    assert_eq!(resp["type"], "ok", "{resp}");
+
+    writeln!(
+        &queued,
+        "{}",
+        json!({"cmd": "warmup", "roots": roots(home.path())})
+    )
+    .unwrap();
+    let mut line = String::new();
+    BufReader::new(&queued).read_line(&mut line).unwrap();
+    let resp: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(resp["type"], "ok", "{resp}");
+    drop(queued);
+
+    // The daemon then exits and cleans up its socket.
+    wait_for_exit(home.path());
+}
+
+/// Wait until the daemon of `home` has removed its socket.
+fn wait_for_exit(home: &Path) {
+    let mut waited = Duration::ZERO;
+    while socket(home).exists() {
+        assert!(waited < Duration::from_secs(5), "daemon did not exit");
+        std::thread::sleep(Duration::from_millis(20));
+        waited += Duration::from_millis(20);
+    }
+}
+
+#[test]
+fn stale_pid_file_does_not_block_start() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    let pid_file = socket(home.path()).with_extension("pid");
+    std::fs::create_dir_all(pid_file.parent().unwrap()).unwrap();
+
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    let dead = exited.id();
+    exited.wait().unwrap();
+    // A dead process, then a live one that is not the daemon.
+    for stale in [dead, std::process::id()] {
+        std::fs::write(&pid_file, format!("{stale}\n")).unwrap();
+
+        let status = indexer(home.path(), &["daemon", "status"]);
+        assert!(
+            stdout(&status).starts_with("daemon not running"),
+            "{status:?}"
+        );
+
+        let stop = start_daemon(home.path());
+        let status = stdout(&indexer(home.path(), &["daemon", "status"]));
+        assert!(status.starts_with("daemon running"), "{status}");
+        let pid = status
+            .lines()
+            .find_map(|l| l.strip_prefix("pid="))
+            .expect("status reports the pid");
+        assert_ne!(pid, stale.to_string());
+        assert_eq!(std::fs::read_to_string(&pid_file).unwrap().trim(), pid);
+        drop(stop);
+        wait_for_exit(home.path());
+    }
+}
+
+#[test]
+fn generate_systemd_escapes_paths_for_unit_files() {
+    let home = home();
+    let config = home.path().join("My 100% config.toml");
+    std::fs::write(&config, "").unwrap();
+    let sock = home.path().join("50% run/indexer.sock");
+
+    let output = command(home.path())
+        .env("DESKTOP_INDEXER_SOCKET", &sock)
+        .arg("--config")
+        .arg(&config)
+        .args(["daemon", "generate-systemd"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let service = String::from_utf8(output.stdout.clone()).unwrap();
+    let exe = env!("CARGO_BIN_EXE_desktop-indexer").replace('%', "%%");
+    let config = config.display().to_string().replace('%', "%%");
+    let sock = sock.display().to_string().replace('%', "%%");
+    assert_eq!(
+        service,
+        format!(
+            "[Unit]\nDescription=desktop-indexer daemon\n\n\
+             [Service]\nType=notify\n\
+             ExecStart={exe} --config \"{config}\" run-daemon --systemd\n\
+             Environment=\"DESKTOP_INDEXER_SOCKET={sock}\"\n\
+             Restart=on-failure\n\n\
+             [Install]\nWantedBy=default.target\n"
+        )
+    );
+    assert!(stderr(&output).contains("systemctl --user"));
+
+    let output = command(home.path())
+        .env(
+            "DESKTOP_INDEXER_SOCKET",
+            home.path().join("50% run/indexer.sock"),
+        )
+        .args(["daemon", "generate-systemd", "--activate-socket"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let socket_unit = String::from_utf8(output.stdout.clone()).unwrap();
+    assert!(
+        socket_unit.contains(&format!("\nListenStream={sock}\n")),
+        "{socket_unit}"
+    );
+    assert!(socket_unit.contains("\nWantedBy=sockets.target\n"));
+}
+
+#[test]
+fn get_actions_returns_every_action_section() {
+    let home = home();
+    write_app(
+        home.path(),
+        "firefox",
+        "Name=Firefox\nExec=firefox %u\nActions=new-window;private;profiles;\n\
+         [Desktop Action new-window]\nName=New Window\nExec=firefox --new-window\n\
+         [Desktop Action private]\nName=Private Window\nExec=firefox --private-window\n\
+         [Desktop Action profiles]\nName=Profile Manager\nExec=firefox -P",
+    );
+    let _stop = start_daemon(home.path());
+
+    let resp = request(
+        home.path(),
+        &json!({"cmd": "get-actions", "roots": roots(home.path()), "desktop_id": "firefox"}),
+    );
+    assert_eq!(resp["type"], "actions", "{resp}");
+    let actions: Vec<(&str, &str, &str)> = resp["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| {
+            (
+                a["id"].as_str().unwrap(),
+                a["name"].as_str().unwrap(),
+                a["exec"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("new-window", "New Window", "firefox --new-window"),
+            ("private", "Private Window", "firefox --private-window"),
+            ("profiles", "Profile Manager", "firefox -P"),
+        ]
+    );
+
+    let resp = request(
+        home.path(),
+        &json!({"cmd": "get-actions", "roots": roots(home.path()), "desktop_id": "missing"}),
+    );
+    assert_eq!(resp["type"], "error", "{resp}");
+
+    // The CLI prints the same list, from the daemon or locally.
+    for args in [
+        &["--trace", "actions", "firefox.desktop"][..],
+        &["--no-daemon", "actions", "firefox"][..],
+    ] {
+        let out = indexer(home.path(), args);
+        assert!(out.status.success(), "{}", stderr(&out));
+        assert_eq!(
+            stdout(&out),
+            "new-window\tNew Window\nprivate\tPrivate Window\nprofiles\tProfile Manager\n"
+        );
+    }
+    let out = indexer(home.path(), &["--trace", "actions", "firefox"]);
+    assert!(stderr(&out).contains("mode=daemon"), "{}", stderr(&out));
+}
@@ -0,0 +1,20 @@
+mod common;
+
+use common::{home, indexer, start_daemon, stderr, stdout, write_app};
+
+#[test]
+fn restart_leaves_a_daemon_that_serves_searches() {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+
+    let _stop = start_daemon(home.path());
+
+    let restart = indexer(home.path(), &["daemon", "restart"]);
+    assert!(restart.status.success(), "{restart:?}");
+    assert_eq!(stdout(&restart), "daemon restarted\n");
+
+    let search = indexer(home.path(), &["--trace", "search", "fire"]);
+    assert!(search.status.success());
+    assert!(stderr(&search).contains("mode=daemon"));
+    assert!(stdout(&search).starts_with("firefox\tFirefox"));
+}
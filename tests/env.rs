@@ -0,0 +1,72 @@
+mod common;
+
+use common::{command, home, stderr, stdout};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+#[test]
+fn env_reports_the_resolved_paths() {
+    let home = home();
+    let output = command(home.path())
+        .env("XDG_CURRENT_DESKTOP", "GNOME:Unity")
+        .env("LANG", "fr_FR.UTF-8")
+        .args(["env", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let env: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+
+    let h = home.path().display();
+    assert_eq!(env["socket"], format!("{h}/run/desktop-indexer.sock"));
+    assert_eq!(
+        env["scan_roots"][0],
+        format!("{h}/data/applications"),
+        "{env}"
+    );
+    let var = |name: &str| {
+        env["vars"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|v| v["name"] == name)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(
+        var("XDG_CURRENT_DESKTOP")["derived"][0],
+        "desktops: GNOME, Unity"
+    );
+    assert_eq!(var("LANG")["derived"][0], "locales: fr_FR, fr");
+    assert_eq!(var("USER")["value"], serde_json::Value::Null);
+}
+
+#[test]
+fn env_survives_odd_and_missing_values() {
+    let home = home();
+    let odd = [
+        ("XDG_DATA_DIRS", OsStr::new("::")),
+        ("DESKTOP_INDEXER_LOCALES", OsStr::new(": :")),
+        ("LC_ALL", OsStr::new("")),
+        ("XDG_CURRENT_DESKTOP", OsStr::from_bytes(b"KDE\xff")),
+        ("DESKTOP_INDEXER_TIMING", OsStr::new("1")),
+    ];
+    for json in [false, true] {
+        let mut cmd = command(home.path());
+        cmd.envs(odd).arg("env");
+        if json {
+            cmd.arg("--json");
+        }
+        let output = cmd.output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert!(!stderr(&output).contains("panicked"));
+    }
+
+    // Not even HOME or PATH.
+    let output = command(home.path())
+        .env_clear()
+        .arg("env")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("HOME (unset)"));
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use common::{command, home, start_daemon, stderr, stdout, write_app};
+use std::path::Path;
+
+/// Ids printed by a local `desktop-indexer <args>` run with extra `env`.
+fn ids(home: &Path, args: &[&str], env: &[(&str, &str)]) -> Vec<String> {
+    let output = command(home)
+        .envs(env.iter().copied())
+        .args(args)
+        .args(["--no-daemon", "--output-format", "id-only"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).lines().map(str::to_string).collect()
+}
+
+#[test]
+fn de_filter_hides_entries_for_other_desktops() {
+    let home = home();
+    write_app(
+        home.path(),
+        "kde-tool",
+        "Name=KDE Tool\nExec=kde-tool\nOnlyShowIn=KDE;",
+    );
+    write_app(
+        home.path(),
+        "no-gnome",
+        "Name=No Gnome\nExec=no-gnome\nNotShowIn=GNOME;",
+    );
+    write_app(home.path(), "plain", "Name=Plain\nExec=plain");
+    let gnome = [("XDG_CURRENT_DESKTOP", "GNOME")];
+
+    assert_eq!(
+        ids(home.path(), &["list"], &gnome),
+        ["kde-tool", "no-gnome", "plain"]
+    );
+    assert_eq!(
+        ids(home.path(), &["list", "--de-filter"], &gnome),
+        ["plain"]
+    );
+    assert_eq!(
+        ids(home.path(), &["search", "tool", "--de-filter"], &gnome),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        ids(
+            home.path(),
+            &["list", "--de-filter"],
+            &[("XDG_CURRENT_DESKTOP", "KDE")]
+        ),
+        ["kde-tool", "no-gnome", "plain"]
+    );
+}
+
+#[test]
+fn nodisplay_entries_need_show_nodisplay_locally_and_from_the_daemon() {
+    let home = home();
+    write_app(home.path(), "viewer", "Name=Image Viewer\nExec=viewer");
+    write_app(
+        home.path(),
+        "helper",
+        "Name=Image Helper\nExec=helper\nNoDisplay=true",
+    );
+
+    assert_eq!(ids(home.path(), &["search", "image"], &[]), ["viewer"]);
+    assert_eq!(
+        ids(home.path(), &["search", "image", "--show-nodisplay"], &[]),
+        ["helper", "viewer"]
+    );
+    assert_eq!(ids(home.path(), &["list"], &[]), ["viewer"]);
+    assert_eq!(
+        ids(home.path(), &["list", "--show-nodisplay"], &[]),
+        ["helper", "viewer"]
+    );
+
+    // One daemon index serves both kinds of request.
+    let _daemon = start_daemon(home.path());
+    let search = |extra: &[&str]| {
+        let output = command(home.path())
+            .args(["--trace", "search", "image", "--output-format", "id-only"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(
+            stderr(&output).contains("mode=daemon"),
+            "{}",
+            stderr(&output)
+        );
+        stdout(&output)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(search(&[]), ["viewer"]);
+    assert_eq!(search(&["--show-nodisplay"]), ["helper", "viewer"]);
+    assert_eq!(search(&[]), ["viewer"]);
+}
+
+#[test]
+fn list_category_and_mime_filters_combine() {
+    let home = home();
+    write_app(
+        home.path(),
+        "firefox",
+        "Name=Firefox\nExec=firefox %u\nCategories=Network;WebBrowser;\nMimeType=text/html;x-scheme-handler/https;",
+    );
+    write_app(
+        home.path(),
+        "mail",
+        "Name=Mail\nExec=mail %u\nCategories=Network;Email;\nMimeType=x-scheme-handler/mailto;",
+    );
+    write_app(
+        home.path(),
+        "editor",
+        "Name=Editor\nExec=editor %F\nCategories=Utility;TextEditor;\nMimeType=text/html;text/plain;",
+    );
+    write_app(home.path(), "plain", "Name=Plain\nExec=plain");
+
+    let network = ["list", "--filter-category", "network"];
+    let html = ["list", "--filter-mime", "text/html"];
+    let both = [
+        "list",
+        "--filter-category",
+        "Network",
+        "--filter-mime",
+        "text/html",
+    ];
+    assert_eq!(ids(home.path(), &network, &[]), ["firefox", "mail"]);
+    assert_eq!(ids(home.path(), &html, &[]), ["editor", "firefox"]);
+    assert_eq!(ids(home.path(), &both, &[]), ["firefox"]);
+
+    // The daemon applies the same filters.
+    let _daemon = start_daemon(home.path());
+    let list = |args: &[&str]| {
+        let output = command(home.path())
+            .arg("--trace")
+            .args(args)
+            .args(["--output-format", "id-only"])
+            .output()
+            .unwrap();
+        assert!(
+            stderr(&output).contains("mode=daemon"),
+            "{}",
+            stderr(&output)
+        );
+        stdout(&output)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(list(&network), ["firefox", "mail"]);
+    assert_eq!(list(&html), ["editor", "firefox"]);
+    assert_eq!(list(&both), ["firefox"]);
+}
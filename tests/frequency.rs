@@ -0,0 +1,97 @@
+mod common;
+
+use common::{home, indexer, request, start_daemon, stderr, stdout, write_app};
+use serde_json::json;
+use std::path::Path;
+
+fn fixture() -> tempfile::TempDir {
+    let home = home();
+    write_app(home.path(), "firefox", "Name=Firefox\nExec=firefox %u");
+    write_app(home.path(), "vlc", "Name=VLC\nExec=vlc %U");
+    home
+}
+
+/// Ids the empty query lists by launch count, and who answered.
+fn frequent(home: &Path, args: &[&str]) -> (Vec<String>, String) {
+    let mut all = vec!["--trace", "search", "", "--empty-mode", "frequency"];
+    all.extend(args);
+    let out = indexer(home, &all);
+    assert!(out.status.success(), "{out:?}");
+    let ids = stdout(&out)
+        .lines()
+        .map(|l| l.split('\t').next().unwrap().to_string())
+        .collect();
+    (ids, stderr(&out))
+}
+
+#[test]
+fn reset_without_a_daemon_edits_the_store() {
+    let home = fixture();
+    let h = home.path();
+    indexer(
+        h,
+        &["--no-daemon", "frequency", "set", "firefox", "--freq", "5"],
+    );
+    indexer(
+        h,
+        &["--no-daemon", "frequency", "set", "vlc", "--freq", "2"],
+    );
+
+    let one = indexer(h, &["frequency", "reset", "--id", "firefox.desktop"]);
+    assert!(one.status.success(), "{one:?}");
+    assert_eq!(stdout(&one), "removed 1 entry\n");
+    assert_eq!(frequent(h, &["--no-daemon"]).0, ["vlc"]);
+
+    let all = indexer(h, &["frequency", "reset", "--yes"]);
+    assert_eq!(stdout(&all), "removed 1 entry\n");
+    assert!(frequent(h, &["--no-daemon"]).0.is_empty());
+}
+
+#[test]
+fn reset_clears_the_running_daemons_copy() {
+    let home = fixture();
+    let h = home.path();
+    let _stop = start_daemon(h);
+    indexer(h, &["frequency", "set", "firefox", "--freq", "5"]);
+    indexer(h, &["frequency", "set", "vlc", "--freq", "2"]);
+    let (ids, trace) = frequent(h, &[]);
+    assert!(trace.contains("mode=daemon"), "{trace}");
+    assert_eq!(ids, ["firefox", "vlc"]);
+
+    let reset = indexer(h, &["frequency", "reset", "--id", "firefox"]);
+    assert_eq!(stdout(&reset), "removed 1 entry\n");
+    assert_eq!(frequent(h, &[]).0, ["vlc"]);
+
+    indexer(h, &["frequency", "reset", "--yes"]);
+    let (ids, trace) = frequent(h, &[]);
+    assert!(trace.contains("mode=daemon"), "{trace}");
+    assert!(ids.is_empty(), "{ids:?}");
+}
+
+#[test]
+fn increment_and_set_update_the_daemons_counts() {
+    let home = fixture();
+    let h = home.path();
+    let _stop = start_daemon(h);
+
+    for id in ["vlc", "vlc.desktop", "firefox"] {
+        let resp = request(h, &json!({"cmd": "increment-frequency", "id": id}));
+        assert_eq!(resp["type"], "ok", "{resp}");
+    }
+    let (ids, trace) = frequent(h, &[]);
+    assert!(trace.contains("mode=daemon"), "{trace}");
+    assert_eq!(ids, ["vlc", "firefox"]);
+
+    let resp = request(
+        h,
+        &json!({"cmd": "set-frequency", "id": "firefox", "freq": 10, "last_used": 1_700_000_000}),
+    );
+    assert_eq!(resp["type"], "ok", "{resp}");
+    assert_eq!(frequent(h, &[]).0, ["firefox", "vlc"]);
+
+    // The CLI goes through the same requests.
+    for _ in 0..10 {
+        indexer(h, &["frequency", "increment", "vlc"]);
+    }
+    assert_eq!(frequent(h, &[]).0, ["vlc", "firefox"]);
+}
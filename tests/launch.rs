@@ -0,0 +1,108 @@
+mod common;
+
+use common::{home, indexer, stderr, stdout, write_app};
+use std::time::Duration;
+
+#[test]
+fn dry_run_prints_the_dbus_activation_call() {
+    let home = home();
+    write_app(
+        home.path(),
+        "org.example.Text-Editor",
+        "Name=Text Editor\nExec=text-editor %U\nDBusActivatable=true",
+    );
+
+    let out = indexer(
+        home.path(),
+        &[
+            "--no-daemon",
+            "launch",
+            "--dry-run",
+            "org.example.Text-Editor",
+        ],
+    );
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        stdout(&out),
+        "gdbus call --session --dest org.example.Text-Editor \
+         --object-path /org/example/Text_Editor \
+         --method org.freedesktop.Application.Activate '@a{sv} {}'\n"
+    );
+}
+
+#[test]
+fn dry_run_with_a_file_uses_exec_instead_of_dbus() {
+    let home = home();
+    write_app(
+        home.path(),
+        "org.example.Text-Editor",
+        "Name=Text Editor\nExec=text-editor %U\nDBusActivatable=true",
+    );
+
+    let out = indexer(
+        home.path(),
+        &[
+            "--no-daemon",
+            "launch",
+            "--dry-run",
+            "--file",
+            "/tmp/notes.txt",
+            "org.example.Text-Editor",
+        ],
+    );
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(stdout(&out), "text-editor /tmp/notes.txt\n");
+}
+
+#[test]
+fn env_flags_reach_the_launched_app() {
+    let home = home();
+    let dump = home.path().join("env.txt");
+    write_app(
+        home.path(),
+        "envdump",
+        &format!(
+            "Name=Env Dump\nExec=sh -c \"env >{}.tmp && mv {0}.tmp {0}\"",
+            dump.display()
+        ),
+    );
+
+    let out = indexer(
+        home.path(),
+        &[
+            "--no-daemon",
+            "launch",
+            "--env",
+            "DI_TEST_FOO=bar",
+            "--env",
+            "DI_TEST_OPTS=a=b",
+            "envdump",
+        ],
+    );
+    assert!(out.status.success(), "{out:?}");
+
+    // The app runs detached; wait for it to finish writing.
+    let mut waited = Duration::ZERO;
+    while !dump.exists() {
+        assert!(waited < Duration::from_secs(5), "app did not run");
+        std::thread::sleep(Duration::from_millis(20));
+        waited += Duration::from_millis(20);
+    }
+    let env = std::fs::read_to_string(&dump).unwrap();
+    let lines: Vec<&str> = env.lines().collect();
+    assert!(lines.contains(&"DI_TEST_FOO=bar"), "{env}");
+    assert!(lines.contains(&"DI_TEST_OPTS=a=b"), "{env}");
+}
+
+#[test]
+fn env_flag_without_equals_is_rejected() {
+    let home = home();
+    write_app(home.path(), "app", "Name=App\nExec=app");
+
+    let out = indexer(
+        home.path(),
+        &["--no-daemon", "launch", "--env", "DI_TEST_FOO", "app"],
+    );
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("expected KEY=VALUE"), "{out:?}");
+}
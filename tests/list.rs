@@ -0,0 +1,72 @@
+mod common;
+
+use common::{home, indexer, stderr, stdout, write_app};
+
+fn fixture() -> tempfile::TempDir {
+    let home = home();
+    write_app(
+        home.path(),
+        "firefox",
+        "Name=Firefox\nExec=firefox %u\nActions=new-window;private;\n\
+         [Desktop Action new-window]\nName=New Window\nExec=firefox --new-window\n\
+         [Desktop Action private]\nName=Private Window\nExec=firefox --private-window",
+    );
+    write_app(home.path(), "vlc", "Name=VLC\nExec=vlc %U");
+    home
+}
+
+#[test]
+fn with_actions_adds_a_line_per_action() {
+    let home = fixture();
+    let out = indexer(home.path(), &["--no-daemon", "list", "--with-actions"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        stdout(&out),
+        "firefox\tFirefox\n\
+         firefox:new-window\tNew Window\n\
+         firefox:private\tPrivate Window\n\
+         vlc\tVLC\n"
+    );
+
+    let out = indexer(
+        home.path(),
+        &[
+            "--no-daemon",
+            "list",
+            "--with-actions",
+            "--output-format",
+            "id-only",
+        ],
+    );
+    assert_eq!(
+        stdout(&out),
+        "firefox\nfirefox:new-window\nfirefox:private\nvlc\n"
+    );
+}
+
+#[test]
+fn with_actions_json_nests_actions_under_each_entry() {
+    let home = fixture();
+    let out = indexer(
+        home.path(),
+        &["--no-daemon", "list", "--with-actions", "--json"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let list: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+    let list = list.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list[0]["entry"]["id"], "firefox");
+    let actions: Vec<(&str, &str)> = list[0]["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| (a["id"].as_str().unwrap(), a["name"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        actions,
+        [("new-window", "New Window"), ("private", "Private Window")]
+    );
+    assert_eq!(list[1]["entry"]["id"], "vlc");
+    assert_eq!(list[1]["actions"], serde_json::json!([]));
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{command, home, stderr, stdout, write_app};
+use std::path::Path;
+
+/// `Name` of the only entry, listed locally with `args` and extra `env`.
+fn listed_name(home: &Path, args: &[&str], env: &[(&str, &str)]) -> String {
+    let output = command(home)
+        .envs(env.iter().copied())
+        .args(["list", "--no-daemon", "--output-format", "name-only"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).trim_end().to_string()
+}
+
+fn bilingual_home() -> tempfile::TempDir {
+    let home = home();
+    write_app(
+        home.path(),
+        "files",
+        "Name=Files\nName[fr]=Fichiers\nName[de_DE]=Dateien\nName[de]=Dateimanager\nExec=files",
+    );
+    home
+}
+
+#[test]
+fn locale_flag_picks_the_translated_name() {
+    let home = bilingual_home();
+    let lang = [("LANG", "de_DE.UTF-8")];
+    assert_eq!(listed_name(home.path(), &[], &lang), "Dateien");
+    assert_eq!(
+        listed_name(home.path(), &["--locale", "fr"], &lang),
+        "Fichiers"
+    );
+    assert_eq!(
+        listed_name(home.path(), &["--locale", "fr_BE"], &lang),
+        "Fichiers"
+    );
+    assert_eq!(listed_name(home.path(), &["--locale", "C"], &lang), "Files");
+}
+
+#[test]
+fn locales_env_var_wins_over_lang() {
+    let home = bilingual_home();
+    let env = |locales| {
+        [
+            ("LANG", "fr_FR.UTF-8"),
+            ("DESKTOP_INDEXER_LOCALES", locales),
+        ]
+    };
+    assert_eq!(listed_name(home.path(), &[], &env("de_DE")), "Dateien");
+    assert_eq!(
+        listed_name(home.path(), &[], &env("de_AT:fr")),
+        "Dateimanager"
+    );
+    assert_eq!(listed_name(home.path(), &[], &env("es:fr")), "Fichiers");
+    // Nothing usable in the list: LANG applies.
+    assert_eq!(listed_name(home.path(), &[], &env(": :")), "Fichiers");
+}
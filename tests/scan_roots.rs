@@ -0,0 +1,134 @@
+mod common;
+
+use common::{command, home, stdout};
+use std::path::Path;
+
+/// The roots `scan` lists under `home`, in priority order. System roots
+/// (`/run/current-system/sw`, Flatpak, ...) depend on the machine.
+fn scan_roots(home: &Path, envs: &[(&str, String)]) -> Vec<String> {
+    let out = command(home)
+        .args(["--no-daemon", "scan"])
+        .envs(envs.iter().map(|(k, v)| (k, v)))
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{out:?}");
+    stdout(&out)
+        .lines()
+        .skip_while(|l| *l != "roots:")
+        .skip(1)
+        .map_while(|l| l.strip_prefix("  "))
+        .filter(|r| Path::new(r).starts_with(home))
+        .map(str::to_string)
+        .collect()
+}
+
+fn mkdir(path: &Path) -> String {
+    std::fs::create_dir_all(path).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn nix_and_guix_profiles_under_home_are_discovered() {
+    let home = home();
+    let h = home.path();
+    let nix = mkdir(&h.join(".nix-profile/share/applications"));
+    let guix = mkdir(&h.join(".guix-profile/share/applications"));
+    let extra = mkdir(&h.join("profiles/extra/share/applications"));
+    let missing = h.join("profiles/missing");
+
+    let roots = scan_roots(
+        h,
+        &[(
+            "NIX_PROFILES",
+            format!(
+                "{} {}",
+                missing.display(),
+                h.join("profiles/extra").display()
+            ),
+        )],
+    );
+
+    assert_eq!(
+        roots,
+        [
+            h.join("data/applications").display().to_string(),
+            h.join("no-system-apps/applications").display().to_string(),
+            nix,
+            extra,
+            guix,
+        ]
+    );
+}
+
+#[test]
+fn a_profile_already_in_xdg_data_dirs_is_listed_once() {
+    let home = home();
+    let h = home.path();
+    let nix = mkdir(&h.join(".nix-profile/share/applications"));
+
+    let roots = scan_roots(
+        h,
+        &[(
+            "XDG_DATA_DIRS",
+            h.join(".nix-profile/share").display().to_string(),
+        )],
+    );
+
+    // Kept at its XDG_DATA_DIRS position, not appended again.
+    assert_eq!(
+        roots,
+        [h.join("data/applications").display().to_string(), nix]
+    );
+}
+
+#[test]
+fn apps_in_a_nix_profile_are_found() {
+    let home = home();
+    let apps = home.path().join(".nix-profile/share/applications");
+    std::fs::create_dir_all(&apps).unwrap();
+    std::fs::write(
+        apps.join("hello.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Hello\nExec=hello\n",
+    )
+    .unwrap();
+
+    let out = command(home.path())
+        .args(["--no-daemon", "search", "hello"])
+        .output()
+        .unwrap();
+    assert!(stdout(&out).starts_with("hello\tHello"), "{out:?}");
+}
+
+#[test]
+fn user_apps_shadow_system_apps_with_the_same_id() {
+    let home = home();
+    let h = home.path();
+    let write = |dir: &Path, name: &str| {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join("editor.desktop"),
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec=editor\n"),
+        )
+        .unwrap();
+    };
+    write(&h.join("data/applications"), "User Editor");
+    write(&h.join("sys-a/applications"), "System A Editor");
+    write(&h.join("sys-b/applications"), "System B Editor");
+
+    let listed = |data_dirs: String| {
+        let out = command(h)
+            .args(["--no-daemon", "list"])
+            .env("XDG_DATA_DIRS", data_dirs)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{out:?}");
+        stdout(&out)
+    };
+    let dirs = |a: &str, b: &str| format!("{}:{}", h.join(a).display(), h.join(b).display());
+
+    assert_eq!(listed(dirs("sys-a", "sys-b")), "editor\tUser Editor\n");
+    std::fs::remove_file(h.join("data/applications/editor.desktop")).unwrap();
+    // Between system dirs, XDG_DATA_DIRS order decides.
+    assert_eq!(listed(dirs("sys-a", "sys-b")), "editor\tSystem A Editor\n");
+    assert_eq!(listed(dirs("sys-b", "sys-a")), "editor\tSystem B Editor\n");
+}
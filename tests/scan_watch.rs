@@ -0,0 +1,44 @@
+mod common;
+
+use common::{command, home};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+#[test]
+fn watch_reports_changes_and_exits_cleanly_on_sigint() {
+    let home = home();
+    let apps = home.path().join("data/applications");
+    let mut child = command(home.path())
+        .args(["scan", "--watch"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    // The initial scan ends with the (empty) file list.
+    for line in lines.by_ref() {
+        if line.unwrap().starts_with("showing=") {
+            break;
+        }
+    }
+    // The watch starts right after; give the watcher a moment to register.
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let path = apps.join("firefox.desktop");
+    std::fs::write(
+        &path,
+        "[Desktop Entry]\nType=Application\nName=Firefox\nExec=firefox\n",
+    )
+    .unwrap();
+    assert_eq!(
+        lines.next().unwrap().unwrap(),
+        format!("ADD {}", path.display())
+    );
+
+    let kill = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(0));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{home, indexer, stderr, stdout, write_app};
+
+#[test]
+fn empty_query_all_apps_lists_entries_a_to_z() {
+    let home = home();
+    for (id, name) in [
+        ("zed", "Zed"),
+        ("files", "Files"),
+        ("mpv", "MPV"),
+        ("calc", "Calculator"),
+        ("terminal", "Terminal"),
+        ("browser", "Browser"),
+        ("alacritty", "Alacritty"),
+    ] {
+        write_app(home.path(), id, &format!("Name={name}\nExec={id}"));
+    }
+
+    let output = indexer(
+        home.path(),
+        &[
+            "--no-daemon",
+            "search",
+            "",
+            "--empty-mode",
+            "all-apps",
+            "--limit",
+            "5",
+            "--json",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&stdout(&output)).unwrap();
+    let names: Vec<&str> = entries
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["Alacritty", "Browser", "Calculator", "Files", "MPV"]
+    );
+}